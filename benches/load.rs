@@ -0,0 +1,34 @@
+use {
+  media::{Package, Template},
+  std::{collections::HashMap, hint::black_box, time::Instant},
+};
+
+const FILES: usize = 10_000;
+
+const ITERATIONS: u32 = 20;
+
+fn main() {
+  let files = (0..FILES)
+    .map(|i| (format!("{i}.jpg").into(), i.to_le_bytes().to_vec()))
+    .collect::<HashMap<_, _>>();
+
+  let package = Package::build(
+    files,
+    Template::Comic {
+      dimensions: Vec::new(),
+      pages: (0..FILES).map(|i| format!("{i}.jpg").into()).collect(),
+    },
+  )
+  .unwrap();
+
+  let start = Instant::now();
+
+  for _ in 0..ITERATIONS {
+    black_box(Package::parse(black_box(&package)).unwrap());
+  }
+
+  println!(
+    "load {FILES} files: {:?} per iteration",
+    start.elapsed() / ITERATIONS
+  );
+}
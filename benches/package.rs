@@ -0,0 +1,60 @@
+use {
+  camino::Utf8PathBuf,
+  criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput},
+  media::{Package, Template},
+  std::{collections::HashMap, fs, hint::black_box},
+};
+
+const CASES: &[(usize, usize)] = &[(10_000, 1 << 10), (1_000, 1 << 16), (50, 10 << 20)];
+
+fn package(c: &mut Criterion) {
+  let mut group = c.benchmark_group("package");
+
+  group.sample_size(10);
+
+  for &(files, size) in CASES {
+    let tempdir = tempfile::tempdir().unwrap();
+
+    let root = Utf8PathBuf::try_from(tempdir.path().join("root")).unwrap();
+    let output = Utf8PathBuf::try_from(tempdir.path().join("output.package")).unwrap();
+
+    fs::create_dir(&root).unwrap();
+
+    let mut hashes = HashMap::new();
+
+    for i in 0..files {
+      let path = Utf8PathBuf::from(format!("{i}.jpg"));
+
+      let mut content = vec![0xFF; size];
+      content[..8].copy_from_slice(&i.to_le_bytes());
+
+      fs::write(root.join(&path), &content).unwrap();
+
+      hashes.insert(path, (blake3::hash(&content), content.len() as u64));
+    }
+
+    let manifest = Template::Comic {
+      dimensions: Vec::new(),
+      pages: (0..files).map(|i| format!("{i}.jpg").into()).collect(),
+    }
+    .manifest(&hashes);
+
+    let case = format!("{files}x{size}");
+
+    group.throughput(Throughput::Bytes((files * size) as u64));
+
+    group.bench_function(BenchmarkId::new("save", &case), |b| {
+      b.iter(|| Package::save(hashes.clone(), &manifest, &output, &root, false).unwrap());
+    });
+
+    group.bench_function(BenchmarkId::new("load", &case), |b| {
+      b.iter(|| black_box(Package::load(black_box(&output)).unwrap()));
+    });
+  }
+
+  group.finish();
+}
+
+criterion_group!(benches, package);
+
+criterion_main!(benches);
@@ -0,0 +1,68 @@
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Entry {
+  pub compressed: bool,
+  pub hash: Hash,
+  pub len: u64,
+  pub stored: u64,
+}
+
+impl Entry {
+  pub fn read(reader: &mut impl Read) -> Result<Self, package::Error> {
+    let hash = reader.read_hash()?;
+    let len = reader.read_u64()?;
+    let compressed = reader.read_u8()?;
+    let stored = reader.read_u64()?;
+
+    usize::try_from(len).context(package::FileLengthRange { len })?;
+    usize::try_from(stored).context(package::FileLengthRange { len: stored })?;
+
+    ensure!(
+      compressed <= 1,
+      package::CompressionFlag {
+        hash,
+        flag: compressed,
+      }
+    );
+
+    Ok(Self {
+      compressed: compressed == 1,
+      hash,
+      len,
+      stored,
+    })
+  }
+
+  pub fn write(self, writer: &mut impl Write) -> io::Result<()> {
+    writer.write_hash(self.hash)?;
+    writer.write_u64(self.len)?;
+    writer.write_u8(self.compressed.into())?;
+    writer.write_u64(self.stored)
+  }
+
+  pub fn decode(self, stored: Vec<u8>) -> Result<Vec<u8>, package::Error> {
+    let content = if self.compressed {
+      let mut content = Vec::new();
+      zstd::Decoder::with_buffer(stored.as_slice())
+        .context(package::Decompress { hash: self.hash })?
+        .take(self.len.saturating_add(1))
+        .read_to_end(&mut content)
+        .context(package::Decompress { hash: self.hash })?;
+      content
+    } else {
+      stored
+    };
+
+    ensure!(
+      content.len().into_u64() == self.len,
+      package::DecompressedLength {
+        actual: content.len().into_u64(),
+        expected: self.len,
+        hash: self.hash,
+      }
+    );
+
+    Ok(content)
+  }
+}
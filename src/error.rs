@@ -3,23 +3,55 @@ use super::*;
 #[derive(Debug, Snafu)]
 #[snafu(context(suffix(false)), visibility(pub))]
 pub enum Error {
+  #[snafu(display("address {address} is already in use, try a different port with `--address`"))]
+  AddressInUse {
+    address: SocketAddr,
+    backtrace: Option<Backtrace>,
+    source: io::Error,
+  },
+  #[snafu(display(
+    "permission denied binding to {address}, ports below 1024 usually require elevated privileges, try a higher port with `--address`"
+  ))]
+  AddressPermissionDenied {
+    address: SocketAddr,
+    backtrace: Option<Backtrace>,
+    source: io::Error,
+  },
   #[snafu(display("app package must be of type `app` not `{ty}`"))]
   AppType {
     backtrace: Option<Backtrace>,
     ty: Type,
   },
+  #[snafu(display("I/O error binding to {address}"))]
+  Bind {
+    address: SocketAddr,
+    backtrace: Option<Backtrace>,
+    source: io::Error,
+  },
+  #[snafu(display("caption refers to missing image `{image}`"))]
+  CaptionImageMissing {
+    backtrace: Option<Backtrace>,
+    image: String,
+  },
+  #[snafu(display("content package name `{name}` given more than once"))]
+  ContentNameDuplicated {
+    backtrace: Option<Backtrace>,
+    name: String,
+  },
   #[snafu(display(
-    "content package of type `{content}` cannot be opened by app that handles `{handles}`"
+    "content package of type `{content}` cannot be opened by app that handles `{}`",
+    handles::display(handles),
   ))]
   ContentType {
     backtrace: Option<Backtrace>,
     content: Type,
-    handles: Type,
+    handles: BTreeSet<Type>,
   },
-  #[snafu(display("failed to get current directory"))]
-  CurrentDir {
+  #[snafu(display("`created_at` value `{created_at}` in `{path}` is not an RFC 3339 timestamp"))]
+  CreatedAt {
     backtrace: Option<Backtrace>,
-    source: io::Error,
+    created_at: String,
+    path: Utf8PathBuf,
   },
   #[snafu(display("failed to deserialize YAML package metadata at `{path}`"))]
   DeserializeMetadata {
@@ -27,11 +59,51 @@ pub enum Error {
     path: Utf8PathBuf,
     source: serde_yaml::Error,
   },
+  #[snafu(display("failed to deserialize YAML at `{path}`"))]
+  DeserializeYaml {
+    backtrace: Option<Backtrace>,
+    path: Utf8PathBuf,
+    source: serde_yaml::Error,
+  },
+  #[snafu(display("package `{package}` does not contain `{path}`"))]
+  FileNotFound {
+    backtrace: Option<Backtrace>,
+    package: Utf8PathBuf,
+    path: String,
+  },
+  #[snafu(display("failed to parse ignore file `{path}`"))]
+  Ignore {
+    backtrace: Option<Backtrace>,
+    path: Utf8PathBuf,
+    source: ignore::Error,
+  },
+  #[snafu(display("failed to decode image dimensions of `{path}`"))]
+  ImageDecode {
+    backtrace: Option<Backtrace>,
+    path: Utf8PathBuf,
+  },
   #[snafu(display("missing `index.html` in `{root}`"))]
   Index {
     backtrace: Option<Backtrace>,
     root: Utf8PathBuf,
   },
+  #[snafu(display("invalid header path glob `{glob}`"))]
+  InvalidHeaderGlob {
+    backtrace: Option<Backtrace>,
+    glob: String,
+    source: globset::Error,
+  },
+  #[snafu(display("invalid header name `{name}`"))]
+  InvalidHeaderName {
+    backtrace: Option<Backtrace>,
+    name: String,
+  },
+  #[snafu(display("invalid value `{value}` for header `{name}`"))]
+  InvalidHeaderValue {
+    backtrace: Option<Backtrace>,
+    name: String,
+    value: String,
+  },
   #[snafu(display("invalid page filename `{path}`"))]
   InvalidPage {
     backtrace: Option<Backtrace>,
@@ -44,15 +116,27 @@ pub enum Error {
     path: Utf8PathBuf,
     source: io::Error,
   },
+  #[snafu(display("invalid log level `{level}`"))]
+  LogLevel {
+    backtrace: Option<Backtrace>,
+    level: String,
+    source: tracing_subscriber::filter::ParseError,
+  },
   #[snafu(display("missing `metadata.yaml` in `{root}`"))]
   MetadataMissing {
     backtrace: Option<Backtrace>,
     root: Utf8PathBuf,
   },
-  #[snafu(display("comic package in `{root}` contains no pages"))]
+  #[snafu(display("{ty} package in `{root}` is empty"))]
   NoPages {
     backtrace: Option<Backtrace>,
     root: Utf8PathBuf,
+    ty: Type,
+  },
+  #[snafu(display("output directory `{output}` is not empty"))]
+  OutputNotEmpty {
+    backtrace: Option<Backtrace>,
+    output: Utf8PathBuf,
   },
   #[snafu(display("package output `{output}` may not be in `{root}`"))]
   OutputInRoot {
@@ -65,18 +149,42 @@ pub enum Error {
     backtrace: Option<Backtrace>,
     output: Utf8PathBuf,
   },
+  #[snafu(display("failed to export package to `{path}`"))]
+  PackageExport {
+    path: Utf8PathBuf,
+    #[snafu(backtrace)]
+    source: package::Error,
+  },
+  #[snafu(display("failed to extract package to `{path}`"))]
+  PackageExtract {
+    path: Utf8PathBuf,
+    #[snafu(backtrace)]
+    source: package::Error,
+  },
   #[snafu(display("failed to load package `{path}`"))]
   PackageLoad {
     path: Utf8PathBuf,
     #[snafu(backtrace)]
     source: package::Error,
   },
+  #[snafu(display("failed to read `{path}` from package"))]
+  PackageRead {
+    path: String,
+    #[snafu(backtrace)]
+    source: package::Error,
+  },
   #[snafu(display("failed to save package to `{path}`"))]
   PackageSave {
     path: Utf8PathBuf,
     #[snafu(backtrace)]
     source: package::Error,
   },
+  #[snafu(display("failed to verify package `{path}`"))]
+  PackageVerify {
+    path: Utf8PathBuf,
+    #[snafu(backtrace)]
+    source: package::Error,
+  },
   #[snafu(display("multifple page {page}s"))]
   PageDuplicated {
     backtrace: Option<Backtrace>,
@@ -87,6 +195,20 @@ pub enum Error {
     backtrace: Option<Backtrace>,
     page: u64,
   },
+  #[snafu(display(
+    "path `{path}` found in more than one root: {}",
+    roots.iter().map(|root| format!("`{root}`")).collect::<Vec<String>>().join(", "),
+  ))]
+  PathConflict {
+    backtrace: Option<Backtrace>,
+    path: Utf8PathBuf,
+    roots: Vec<Utf8PathBuf>,
+  },
+  #[snafu(display("path `{path}` found more than once"))]
+  PathDuplicated {
+    backtrace: Option<Backtrace>,
+    path: Utf8PathBuf,
+  },
   #[snafu(
     display("path contains invalid UTF-8: `{}`", path.display())
   )]
@@ -94,6 +216,18 @@ pub enum Error {
     backtrace: Option<Backtrace>,
     path: PathBuf,
   },
+  #[snafu(display("redirect from `{from}` has invalid location `{location}`"))]
+  RedirectLocation {
+    backtrace: Option<Backtrace>,
+    from: String,
+    location: String,
+  },
+  #[snafu(display("redirect from `{from}` has status {status} which is not a redirect status"))]
+  RedirectStatus {
+    backtrace: Option<Backtrace>,
+    from: String,
+    status: u16,
+  },
   #[snafu(display("I/O error initializing async runtime"))]
   Runtime {
     backtrace: Option<Backtrace>,
@@ -105,21 +239,113 @@ pub enum Error {
     backtrace: Option<Backtrace>,
     source: io::Error,
   },
+  #[snafu(display("symlink `{path}` points to `{target}` outside of package root"))]
+  SymlinkEscapesRoot {
+    backtrace: Option<Backtrace>,
+    path: Utf8PathBuf,
+    target: Utf8PathBuf,
+  },
+  #[snafu(display("failed to query systemd socket"))]
+  SystemdSocket {
+    backtrace: Option<Backtrace>,
+    source: io::Error,
+  },
+  #[snafu(display(
+    "`--systemd` was passed but no socket was received, `LISTEN_FDS` and `LISTEN_PID` must be set \
+     by systemd socket activation"
+  ))]
+  SystemdSocketMissing { backtrace: Option<Backtrace> },
+  #[snafu(display("I/O error writing to stdout"))]
+  Stdout {
+    backtrace: Option<Backtrace>,
+    source: io::Error,
+  },
+  #[snafu(display("failed to read tar archive `{path}`"))]
+  Tar {
+    backtrace: Option<Backtrace>,
+    path: Utf8PathBuf,
+    source: io::Error,
+  },
+  #[snafu(display("tar entry `{path}` is not a relative path"))]
+  TarEntryPath {
+    backtrace: Option<Backtrace>,
+    path: String,
+  },
+  #[snafu(display("failed to build thread pool"))]
+  ThreadPool {
+    backtrace: Option<Backtrace>,
+    source: rayon::ThreadPoolBuildError,
+  },
+  #[snafu(display("`--tls-cert` and `--tls-key` must be passed together"))]
+  TlsArgs { backtrace: Option<Backtrace> },
+  #[snafu(display("failed to load TLS certificate `{cert}` and key `{key}`"))]
+  TlsConfig {
+    backtrace: Option<Backtrace>,
+    cert: Utf8PathBuf,
+    key: Utf8PathBuf,
+    source: io::Error,
+  },
+  #[snafu(display(
+    "table of contents entry `{title}` refers to chapter {chapter} but book has {chapters} chapters"
+  ))]
+  TocIndexOutOfBounds {
+    backtrace: Option<Backtrace>,
+    chapter: usize,
+    chapters: usize,
+    title: String,
+  },
+  #[snafu(display("missing `toc.yaml` in `{root}`"))]
+  TocMissing {
+    backtrace: Option<Backtrace>,
+    root: Utf8PathBuf,
+  },
+  #[snafu(display("audio package has {tracks} tracks but {titles} titles"))]
+  TrackTitles {
+    backtrace: Option<Backtrace>,
+    titles: u64,
+    tracks: u64,
+  },
   #[snafu(display("unexpected file `{file}` in {ty} package"))]
   UnexpectedFile {
     backtrace: Option<Backtrace>,
     file: Utf8PathBuf,
     ty: Type,
   },
+  #[snafu(display("{failed} of {total} packages failed verification"))]
+  VerifyFailed {
+    backtrace: Option<Backtrace>,
+    failed: u64,
+    total: u64,
+  },
+  #[snafu(display("missing `video.mp4` in `{root}`"))]
+  VideoMissing {
+    backtrace: Option<Backtrace>,
+    root: Utf8PathBuf,
+  },
   #[snafu(display("failed to walk directory `{root}`"))]
   WalkDir {
     backtrace: Option<Backtrace>,
     root: Utf8PathBuf,
     source: walkdir::Error,
   },
+  #[snafu(display("failed to watch `{path}`"))]
+  Watch {
+    backtrace: Option<Backtrace>,
+    path: Utf8PathBuf,
+    source: notify::Error,
+  },
 }
 
 impl Error {
+  // the error and its sources on one line, for log events
+  pub(crate) fn chain(&self) -> String {
+    self
+      .iter_chain()
+      .map(ToString::to_string)
+      .collect::<Vec<String>>()
+      .join(": ")
+  }
+
   pub fn report(&self) {
     eprintln!("error: {self}");
 
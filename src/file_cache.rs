@@ -0,0 +1,95 @@
+use super::*;
+
+#[derive(Debug)]
+pub(crate) struct FileCache {
+  budget: u64,
+  entries: HashMap<Hash, (u64, Bytes)>,
+  len: u64,
+  recency: BTreeMap<u64, Hash>,
+  tick: u64,
+}
+
+impl FileCache {
+  pub(crate) fn new(budget: u64) -> Self {
+    Self {
+      budget,
+      entries: HashMap::new(),
+      len: 0,
+      recency: BTreeMap::new(),
+      tick: 0,
+    }
+  }
+
+  pub(crate) fn get(&mut self, hash: Hash) -> Option<Bytes> {
+    let tick = self.next_tick();
+
+    let (last, content) = self.entries.get_mut(&hash)?;
+
+    self.recency.remove(last);
+    self.recency.insert(tick, hash);
+    *last = tick;
+
+    Some(content.clone())
+  }
+
+  pub(crate) fn insert(&mut self, hash: Hash, content: Bytes) {
+    let len = content.len().into_u64();
+
+    if len > self.budget || self.entries.contains_key(&hash) {
+      return;
+    }
+
+    while self.len + len > self.budget {
+      let Some((_tick, evicted)) = self.recency.pop_first() else {
+        break;
+      };
+
+      let (_tick, content) = self.entries.remove(&evicted).unwrap();
+
+      self.len -= content.len().into_u64();
+    }
+
+    let tick = self.next_tick();
+
+    self.recency.insert(tick, hash);
+    self.entries.insert(hash, (tick, content));
+    self.len += len;
+  }
+
+  fn next_tick(&mut self) -> u64 {
+    self.tick += 1;
+    self.tick
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn least_recently_used_is_evicted() {
+    let mut cache = FileCache::new(6);
+
+    cache.insert(blake3::hash(b"foo"), Bytes::from_static(b"foo"));
+    cache.insert(blake3::hash(b"bar"), Bytes::from_static(b"bar"));
+
+    assert_eq!(cache.get(blake3::hash(b"foo")).unwrap(), b"foo".as_slice());
+
+    cache.insert(blake3::hash(b"baz"), Bytes::from_static(b"baz"));
+
+    assert_eq!(cache.get(blake3::hash(b"foo")).unwrap(), b"foo".as_slice());
+    assert_eq!(cache.get(blake3::hash(b"bar")), None);
+    assert_eq!(cache.get(blake3::hash(b"baz")).unwrap(), b"baz".as_slice());
+    assert_eq!(cache.len, 6);
+  }
+
+  #[test]
+  fn oversized_files_are_not_cached() {
+    let mut cache = FileCache::new(2);
+
+    cache.insert(blake3::hash(b"foo"), Bytes::from_static(b"foo"));
+
+    assert_eq!(cache.get(blake3::hash(b"foo")), None);
+    assert_eq!(cache.len, 0);
+  }
+}
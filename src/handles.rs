@@ -0,0 +1,82 @@
+use {
+  super::*,
+  serde::{de, Deserializer, Serializer},
+};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+  One(Type),
+  Many(BTreeSet<Type>),
+}
+
+pub(crate) fn serialize<S: Serializer>(
+  handles: &BTreeSet<Type>,
+  serializer: S,
+) -> Result<S::Ok, S::Error> {
+  match handles.first() {
+    Some(ty) if handles.len() == 1 => ty.serialize(serializer),
+    _ => handles.serialize(serializer),
+  }
+}
+
+pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+  deserializer: D,
+) -> Result<BTreeSet<Type>, D::Error> {
+  let handles = match OneOrMany::deserialize(deserializer)? {
+    OneOrMany::One(ty) => [ty].into(),
+    OneOrMany::Many(handles) => handles,
+  };
+
+  if handles.is_empty() {
+    return Err(de::Error::custom("app must handle at least one type"));
+  }
+
+  Ok(handles)
+}
+
+pub(crate) fn display(handles: &BTreeSet<Type>) -> String {
+  handles
+    .iter()
+    .map(Type::to_string)
+    .collect::<Vec<String>>()
+    .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[derive(Debug, Deserialize, PartialEq, Serialize)]
+  struct App {
+    #[serde(with = "super")]
+    handles: BTreeSet<Type>,
+  }
+
+  #[test]
+  fn one_or_many() {
+    #[track_caller]
+    fn case(yaml: &str, handles: &[Type]) {
+      let app = serde_yaml::from_str::<App>(yaml).unwrap();
+      assert_eq!(app.handles, handles.iter().copied().collect());
+      assert_eq!(
+        serde_yaml::from_str::<App>(&serde_yaml::to_string(&app).unwrap()).unwrap(),
+        app,
+      );
+    }
+
+    case("handles: comic", &[Type::Comic]);
+    case("handles: [comic]", &[Type::Comic]);
+    case("handles: [gallery, comic]", &[Type::Comic, Type::Gallery]);
+
+    assert_eq!(
+      serde_yaml::to_string(&App {
+        handles: [Type::Comic].into(),
+      })
+      .unwrap(),
+      "handles: comic\n",
+    );
+
+    assert!(serde_yaml::from_str::<App>("handles: []").is_err());
+  }
+}
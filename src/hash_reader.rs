@@ -0,0 +1,27 @@
+use super::*;
+
+pub(crate) struct HashReader<R> {
+  hasher: Hasher,
+  inner: R,
+}
+
+impl<R: Read> HashReader<R> {
+  pub(crate) fn new(inner: R) -> Self {
+    Self {
+      hasher: Hasher::new(),
+      inner,
+    }
+  }
+
+  pub(crate) fn finalize(&self) -> Hash {
+    self.hasher.finalize()
+  }
+}
+
+impl<R: Read> Read for HashReader<R> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let n = self.inner.read(buf)?;
+    self.hasher.update(&buf[..n]);
+    Ok(n)
+  }
+}
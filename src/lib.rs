@@ -0,0 +1,112 @@
+#![allow(clippy::result_large_err)]
+
+pub use self::{
+  error::Error,
+  load_options::LoadOptions,
+  manifest::Manifest,
+  mapped_package::MappedPackage,
+  package::Package,
+  package_info::{EntryInfo, PackageInfo},
+  package_reader::PackageReader,
+  provenance::Provenance,
+  redirect::Redirect,
+  summary::Summary,
+  template::Template,
+  ty::{ParseTypeError, Type},
+};
+
+use {
+  self::{
+    entry::Entry,
+    file_cache::FileCache,
+    hash_reader::HashReader,
+    into_u64::IntoU64,
+    metadata::Metadata,
+    package::Content,
+    path_ext::{PathExt, Utf8PathExt},
+    read_ext::ReadExt,
+    subcommand::Subcommand,
+    toc_entry::TocEntry,
+    write_ext::WriteExt,
+  },
+  axum::http::header,
+  blake3::{Hash, Hasher},
+  bytes::Bytes,
+  camino::{Utf8Component, Utf8Path, Utf8PathBuf},
+  clap::Parser,
+  ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey, SIGNATURE_LENGTH},
+  globset::{Glob, GlobSet, GlobSetBuilder},
+  libc::EXIT_FAILURE,
+  listenfd::ListenFd,
+  mime_guess::{mime, Mime},
+  regex::Regex,
+  serde::{Deserialize, Serialize},
+  snafu::{ensure, ErrorCompat, IntoError, OptionExt, ResultExt, Snafu},
+  std::{
+    backtrace::{Backtrace, BacktraceStatus},
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    convert::Infallible,
+    fmt::{self, Display, Formatter},
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write},
+    iter,
+    net::SocketAddr,
+    num::{ParseIntError, TryFromIntError},
+    path::PathBuf,
+    process,
+    sync::{Arc, Mutex},
+  },
+  symphonia::core::io::MediaSource,
+  tempfile::NamedTempFile,
+  walkdir::WalkDir,
+  zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter},
+};
+
+#[cfg(test)]
+#[macro_use]
+mod test;
+
+#[cfg(test)]
+use test::*;
+
+mod entry;
+mod error;
+mod file_cache;
+mod handles;
+mod hash_reader;
+mod into_u64;
+mod load_options;
+mod manifest;
+mod mapped_package;
+mod metadata;
+mod package;
+mod package_info;
+mod package_reader;
+mod path_ext;
+mod provenance;
+mod read_ext;
+mod redirect;
+mod sniff;
+mod strip;
+mod subcommand;
+mod summary;
+mod template;
+mod thumbnail;
+mod toc_entry;
+mod ty;
+mod write_ext;
+
+type Result<T = (), E = Error> = std::result::Result<T, E>;
+
+pub fn serve(address: SocketAddr, app: Package, content: Package) -> Result {
+  subcommand::server::Server::serve(address, app, content)
+}
+
+#[doc(hidden)]
+pub fn run_cli() {
+  if let Err(err) = Subcommand::parse().run() {
+    err.report();
+    process::exit(EXIT_FAILURE)
+  }
+}
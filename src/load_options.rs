@@ -0,0 +1,29 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LoadOptions {
+  pub max_file_count: u64,
+  pub max_file_len: u64,
+  pub max_total_len: u64,
+}
+
+impl LoadOptions {
+  pub const DEFAULT_MAX_FILE_LEN: u64 = 1 << 32;
+  pub const DEFAULT_MAX_TOTAL_LEN: u64 = 1 << 36;
+
+  // limits for validating untrusted bytes, such as fuzzer input, low enough
+  // that a package declaring large files can't exhaust memory
+  pub const VALIDATE: Self = Self {
+    max_file_count: 1 << 16,
+    max_file_len: 1 << 24,
+    max_total_len: 1 << 26,
+  };
+}
+
+impl Default for LoadOptions {
+  fn default() -> Self {
+    Self {
+      max_file_count: u64::MAX,
+      max_file_len: Self::DEFAULT_MAX_FILE_LEN,
+      max_total_len: Self::DEFAULT_MAX_TOTAL_LEN,
+    }
+  }
+}
@@ -1,46 +1,576 @@
 use super::*;
 
 #[derive(Debug, Deserialize, Serialize, PartialEq)]
-#[serde(rename_all = "snake_case", tag = "type")]
+#[serde(remote = "Self", rename_all = "snake_case", tag = "type")]
 pub enum Manifest {
   App {
-    handles: Type,
+    #[serde(with = "handles")]
+    handles: BTreeSet<Type>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    headers: BTreeMap<String, BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    mime_overrides: BTreeMap<String, String>,
     paths: BTreeMap<String, Hash>,
+    #[serde(flatten)]
+    provenance: Provenance,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    redirects: BTreeMap<String, Redirect>,
+  },
+  Audio {
+    #[serde(default)]
+    durations_ms: Vec<Option<u64>>,
+    titles: Vec<String>,
+    tracks: Vec<Hash>,
+    #[serde(flatten)]
+    provenance: Provenance,
+  },
+  Book {
+    chapters: Vec<Hash>,
+    toc: Vec<(String, usize)>,
+    #[serde(flatten)]
+    provenance: Provenance,
   },
   Comic {
+    #[serde(default)]
+    content_types: Vec<String>,
+    #[serde(default)]
+    dimensions: Vec<(u32, u32)>,
     pages: Vec<Hash>,
+    #[serde(flatten)]
+    provenance: Provenance,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    thumbnails: Vec<Option<Hash>>,
+  },
+  Gallery {
+    captions: BTreeMap<String, String>,
+    images: BTreeMap<String, Hash>,
+    #[serde(flatten)]
+    provenance: Provenance,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    thumbnails: Vec<Option<Hash>>,
+  },
+  Video {
+    captions: Option<Hash>,
+    video: Hash,
+    #[serde(flatten)]
+    provenance: Provenance,
   },
 }
 
+impl Serialize for Manifest {
+  fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    #[derive(Serialize)]
+    struct Schema<'a> {
+      schema: u64,
+      #[serde(flatten, with = "Manifest")]
+      manifest: &'a Manifest,
+    }
+
+    Schema {
+      schema: Manifest::SCHEMA,
+      manifest: self,
+    }
+    .serialize(serializer)
+  }
+}
+
+impl<'de> Deserialize<'de> for Manifest {
+  fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    Manifest::deserialize(deserializer)
+  }
+}
+
 impl Manifest {
+  pub const SCHEMA: u64 = 1;
+
+  pub fn entries(&self) -> Vec<String> {
+    let indices = |len: usize| (0..len).map(|i| i.to_string()).collect();
+
+    match self {
+      Self::App { paths, .. } => paths.keys().cloned().collect(),
+      Self::Audio { tracks, .. } => indices(tracks.len()),
+      Self::Book { chapters, .. } => (0..chapters.len())
+        .map(|i| format!("chapter/{i}"))
+        .chain(["toc".into()])
+        .collect(),
+      Self::Comic {
+        pages, thumbnails, ..
+      } => (0..pages.len())
+        .map(|i| i.to_string())
+        .chain(Self::thumbnail_entries(thumbnails))
+        .collect(),
+      Self::Gallery {
+        images, thumbnails, ..
+      } => images
+        .keys()
+        .cloned()
+        .chain(["captions".into()])
+        .chain(Self::thumbnail_entries(thumbnails))
+        .collect(),
+      Self::Video { captions, .. } => ["video".into()]
+        .into_iter()
+        .chain(captions.map(|_| "captions".into()))
+        .collect(),
+    }
+  }
+
+  fn thumbnail_entries(thumbnails: &[Option<Hash>]) -> impl Iterator<Item = String> + '_ {
+    thumbnails
+      .iter()
+      .enumerate()
+      .filter(|(_i, thumbnail)| thumbnail.is_some())
+      .map(|(i, _thumbnail)| format!("thumb/{i}"))
+  }
+
+  pub fn file(&self, path: &str) -> Option<(Mime, Hash)> {
+    match self {
+      Self::App {
+        mime_overrides,
+        paths,
+        ..
+      } => {
+        let path = path.trim_start_matches('/');
+        let hash = *paths.get(path)?;
+        let content_type = mime_overrides
+          .get(path)
+          .or_else(|| mime_overrides.get(Utf8Path::new(path).extension()?))
+          .and_then(|content_type| content_type.parse().ok())
+          .unwrap_or_else(|| mime_guess::from_path(path).first_or_octet_stream());
+        Some((content_type, hash))
+      }
+      Self::Audio { tracks, .. } => Some((
+        "audio/mpeg".parse().unwrap(),
+        *tracks.get(path.parse::<usize>().ok()?)?,
+      )),
+      Self::Book { chapters, .. } => Some((
+        mime::TEXT_HTML,
+        *chapters.get(path.strip_prefix("chapter/")?.parse::<usize>().ok()?)?,
+      )),
+      Self::Comic { .. } => match path.strip_prefix("thumb/") {
+        Some(i) => self.thumbnail(i.parse().ok()?),
+        None => self.page(path.parse().ok()?),
+      },
+      Self::Gallery { images, .. } => {
+        let path = path.trim_start_matches('/');
+        if let Some(i) = path.strip_prefix("thumb/") {
+          return self.thumbnail(i.parse().ok()?);
+        }
+        Some((
+          mime_guess::from_path(path).first_or_octet_stream(),
+          *images.get(path)?,
+        ))
+      }
+      Self::Video {
+        captions, video, ..
+      } => match path {
+        "captions" => Some(("text/vtt".parse().unwrap(), (*captions)?)),
+        "video" => Some(("video/mp4".parse().unwrap(), *video)),
+        _ => None,
+      },
+    }
+  }
+
+  pub fn referenced_hashes(&self) -> impl Iterator<Item = Hash> + '_ {
+    let hashes: Box<dyn Iterator<Item = &Hash>> = match self {
+      Self::App { paths, .. } => Box::new(paths.values()),
+      Self::Audio { tracks, .. } => Box::new(tracks.iter()),
+      Self::Book { chapters, .. } => Box::new(chapters.iter()),
+      Self::Comic {
+        pages, thumbnails, ..
+      } => Box::new(pages.iter().chain(thumbnails.iter().flatten())),
+      Self::Gallery {
+        images, thumbnails, ..
+      } => Box::new(images.values().chain(thumbnails.iter().flatten())),
+      Self::Video {
+        captions, video, ..
+      } => Box::new([video].into_iter().chain(captions)),
+    };
+
+    hashes.copied()
+  }
+
+  pub fn redirect(&self, path: &str) -> Option<&Redirect> {
+    let Self::App { redirects, .. } = self else {
+      return None;
+    };
+
+    redirects
+      .iter()
+      .find(|(from, _redirect)| from.trim_start_matches('/') == path.trim_start_matches('/'))
+      .map(|(_from, redirect)| redirect)
+  }
+
+  pub fn index(&self, path: &str) -> Option<String> {
+    let Self::App { paths, .. } = self else {
+      return None;
+    };
+
+    let path = path.trim_start_matches('/');
+
+    if !path.is_empty() && !path.ends_with('/') && paths.contains_key(path) {
+      return None;
+    }
+
+    let index = match path.trim_end_matches('/') {
+      "" => "index.html".into(),
+      directory => format!("{directory}/index.html"),
+    };
+
+    paths.contains_key(index.as_str()).then_some(index)
+  }
+
+  // resolves `path` to generated content or to the file with content returned
+  // by `read`, sniffing the content type of comic pages, since it may not
+  // match their extension
+  pub(crate) fn content<T: AsRef<[u8]> + From<Vec<u8>>, E>(
+    &self,
+    path: &str,
+    read: impl FnOnce(Hash) -> Result<Option<T>, E>,
+  ) -> Result<Option<(Mime, Hash, T)>, E> {
+    if let Some((content_type, content)) = self.generated(path) {
+      return Ok(Some((content_type, blake3::hash(&content), content.into())));
+    }
+
+    let Some((content_type, hash)) = self.file(path) else {
+      return Ok(None);
+    };
+
+    Ok(read(hash)?.map(|content| match self {
+      Self::Comic { .. } => (
+        sniff::image(content.as_ref()).unwrap_or(content_type),
+        hash,
+        content,
+      ),
+      _ => (content_type, hash, content),
+    }))
+  }
+
+  pub fn generated(&self, path: &str) -> Option<(Mime, Vec<u8>)> {
+    match self {
+      Self::Book { toc, .. } if path == "toc" => {
+        Some((mime::APPLICATION_JSON, serde_json::to_vec(toc).unwrap()))
+      }
+      Self::Gallery { captions, .. } if path == "captions" => Some((
+        mime::APPLICATION_JSON,
+        serde_json::to_vec(captions).unwrap(),
+      )),
+      _ => None,
+    }
+  }
+
+  pub fn metadata(&self) -> Metadata {
+    match self {
+      Self::App {
+        handles,
+        headers,
+        mime_overrides,
+        redirects,
+        ..
+      } => Metadata::App {
+        handles: handles.clone(),
+        headers: headers.clone(),
+        mime_overrides: mime_overrides.clone(),
+        redirects: redirects.clone(),
+      },
+      Self::Audio { titles, .. } => Metadata::Audio {
+        titles: titles.clone(),
+      },
+      Self::Book { .. } => Metadata::Book,
+      Self::Comic { .. } => Metadata::Comic,
+      Self::Gallery { .. } => Metadata::Gallery,
+      Self::Video { .. } => Metadata::Video,
+    }
+  }
+
+  pub fn paths(&self) -> Vec<(Utf8PathBuf, Hash)> {
+    match self {
+      Self::App { paths, .. } => paths
+        .iter()
+        .map(|(path, hash)| (path.into(), *hash))
+        .collect(),
+      Self::Audio { tracks, .. } => tracks
+        .iter()
+        .enumerate()
+        .map(|(i, hash)| (format!("{i}.mp3").into(), *hash))
+        .collect(),
+      Self::Book { chapters, .. } => chapters
+        .iter()
+        .enumerate()
+        .map(|(i, hash)| (format!("chapter-{i}.html").into(), *hash))
+        .collect(),
+      Self::Comic { pages, .. } => (0..pages.len())
+        .map(|i| {
+          let (content_type, hash) = self.page(i).unwrap();
+          (
+            format!("{i}.{}", Self::page_extension(&content_type)).into(),
+            hash,
+          )
+        })
+        .collect(),
+      Self::Gallery { images, .. } => images
+        .iter()
+        .map(|(path, hash)| (path.into(), *hash))
+        .collect(),
+      Self::Video {
+        captions, video, ..
+      } => [("video.mp4".into(), *video)]
+        .into_iter()
+        .chain(captions.map(|hash| ("captions.vtt".into(), hash)))
+        .collect(),
+    }
+  }
+
+  pub fn filename(&self, path: &str) -> Option<String> {
+    if self.generated(path).is_some() {
+      return Some(format!("{path}.json"));
+    }
+
+    let (content_type, _) = self.file(path)?;
+
+    if let (Self::Comic { .. } | Self::Gallery { .. }, Some(i)) =
+      (self, path.strip_prefix("thumb/"))
+    {
+      return Some(format!("thumb-{i}.{}", Self::page_extension(&content_type)));
+    }
+
+    Some(match self {
+      Self::App { .. } | Self::Gallery { .. } => Utf8Path::new(path).file_name()?.into(),
+      Self::Audio { .. } => format!("{path}.mp3"),
+      Self::Book { .. } => format!("chapter-{}.html", path.strip_prefix("chapter/")?),
+      Self::Comic { .. } => format!("{path}.{}", Self::page_extension(&content_type)),
+      Self::Video { .. } => match path {
+        "captions" => "captions.vtt".into(),
+        _ => "video.mp4".into(),
+      },
+    })
+  }
+
+  fn page_extension(content_type: &Mime) -> &'static str {
+    match content_type.essence_str() {
+      "image/avif" => "avif",
+      "image/png" => "png",
+      "image/webp" => "webp",
+      _ => "jpg",
+    }
+  }
+
+  fn page(&self, i: usize) -> Option<(Mime, Hash)> {
+    let Self::Comic {
+      content_types,
+      pages,
+      ..
+    } = self
+    else {
+      return None;
+    };
+
+    Some((
+      content_types
+        .get(i)
+        .and_then(|content_type| content_type.parse().ok())
+        .unwrap_or(mime::IMAGE_JPEG),
+      *pages.get(i)?,
+    ))
+  }
+
+  // thumbnails are PNGs, except for images that were already small enough,
+  // whose thumbnail is the image itself, and images that could not be decoded,
+  // which have none
+  fn thumbnail(&self, i: usize) -> Option<(Mime, Hash)> {
+    let (content_type, image) = match self {
+      Self::Comic { .. } => self.page(i)?,
+      Self::Gallery { images, .. } => {
+        let (path, hash) = images.iter().nth(i)?;
+        (mime_guess::from_path(path).first_or_octet_stream(), *hash)
+      }
+      _ => return None,
+    };
+
+    let (Self::Comic { thumbnails, .. } | Self::Gallery { thumbnails, .. }) = self else {
+      return None;
+    };
+
+    let thumbnail = (*thumbnails.get(i)?)?;
+
+    Some((
+      if thumbnail == image {
+        content_type
+      } else {
+        mime::IMAGE_PNG
+      },
+      thumbnail,
+    ))
+  }
+
+  // images that `thumbnails` is parallel to
+  pub fn images(&self) -> Vec<Hash> {
+    match self {
+      Self::Comic { pages, .. } => pages.clone(),
+      Self::Gallery { images, .. } => images.values().copied().collect(),
+      _ => Vec::new(),
+    }
+  }
+
+  pub fn with_thumbnails(mut self, thumbnails: Vec<Option<Hash>>) -> Self {
+    if let Self::Comic { thumbnails: t, .. } | Self::Gallery { thumbnails: t, .. } = &mut self {
+      *t = thumbnails;
+    }
+    self
+  }
+
+  pub fn sidecars(&self) -> Vec<(&'static str, String)> {
+    match self {
+      Self::Book { toc, .. } => vec![(
+        TocEntry::PATH,
+        serde_yaml::to_string(
+          &toc
+            .iter()
+            .map(|(title, chapter)| TocEntry {
+              title: title.clone(),
+              chapter: *chapter,
+            })
+            .collect::<Vec<TocEntry>>(),
+        )
+        .unwrap(),
+      )],
+      Self::Gallery { captions, .. } if !captions.is_empty() => vec![(
+        Metadata::CAPTIONS_PATH,
+        serde_yaml::to_string(captions).unwrap(),
+      )],
+      _ => Vec::new(),
+    }
+  }
+
+  pub fn provenance(&self) -> &Provenance {
+    match self {
+      Self::App { provenance, .. }
+      | Self::Audio { provenance, .. }
+      | Self::Book { provenance, .. }
+      | Self::Comic { provenance, .. }
+      | Self::Gallery { provenance, .. }
+      | Self::Video { provenance, .. } => provenance,
+    }
+  }
+
+  pub fn with_provenance(mut self, provenance: Provenance) -> Self {
+    match &mut self {
+      Self::App { provenance: p, .. }
+      | Self::Audio { provenance: p, .. }
+      | Self::Book { provenance: p, .. }
+      | Self::Comic { provenance: p, .. }
+      | Self::Gallery { provenance: p, .. }
+      | Self::Video { provenance: p, .. } => *p = provenance,
+    }
+    self
+  }
+
+  pub fn cbor(&self) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    ciborium::into_writer(self, &mut buffer).unwrap();
+    buffer
+  }
+
+  pub fn from_cbor(cbor: &[u8], hash: Hash) -> Result<Self, package::Error> {
+    #[derive(Deserialize)]
+    struct Header {
+      #[serde(default)]
+      schema: u64,
+      #[serde(rename = "type")]
+      ty: String,
+    }
+
+    let header: Header = ciborium::from_reader(cbor).context(package::ManifestInvalid { hash })?;
+
+    ensure!(
+      header.schema <= Self::SCHEMA,
+      package::ManifestSchema {
+        found: header.schema,
+        supported: Self::SCHEMA,
+      },
+    );
+
+    ensure!(
+      header.ty.parse::<Type>().is_ok(),
+      package::UnknownManifestType { found: header.ty },
+    );
+
+    ciborium::from_reader(cbor).context(package::ManifestInvalid { hash })
+  }
+
+  pub fn json(&self) -> Vec<u8> {
+    serde_json::to_vec(self).unwrap()
+  }
+
+  pub fn yaml(&self) -> Vec<u8> {
+    serde_yaml::to_string(self).unwrap().into_bytes()
+  }
+
+  pub fn summary(&self) -> Summary {
+    Summary {
+      ty: self.ty(),
+      chapters: match self {
+        Self::Book { chapters, .. } => Some(chapters.len().into_u64()),
+        _ => None,
+      },
+      duration_ms: match self {
+        // the total is only reported if every track's duration is known,
+        // and durations come from untrusted packages, so may overflow
+        Self::Audio {
+          durations_ms,
+          tracks,
+          ..
+        } if durations_ms.len() == tracks.len() => durations_ms
+          .iter()
+          .try_fold(0u64, |total, duration| total.checked_add((*duration)?)),
+        _ => None,
+      },
+      images: match self {
+        Self::Gallery { images, .. } => Some(images.len().into_u64()),
+        _ => None,
+      },
+      pages: match self {
+        Self::Comic { pages, .. } => Some(pages.len().into_u64()),
+        _ => None,
+      },
+      tracks: match self {
+        Self::Audio { tracks, .. } => Some(tracks.len().into_u64()),
+        _ => None,
+      },
+    }
+  }
+
   pub fn ty(&self) -> Type {
     match self {
       Self::App { .. } => Type::App,
+      Self::Audio { .. } => Type::Audio,
+      Self::Book { .. } => Type::Book,
       Self::Comic { .. } => Type::Comic,
+      Self::Gallery { .. } => Type::Gallery,
+      Self::Video { .. } => Type::Video,
     }
   }
 
-  pub fn verify(
-    &self,
-    manifest: Hash,
-    files: &HashMap<Hash, Vec<u8>>,
-  ) -> Result<(), package::Error> {
+  pub fn verify<T>(&self, manifest: Hash, files: &HashMap<Hash, T>) -> Result<(), package::Error> {
     let mut extra = 0u64;
-    let mut missing = 0u64;
 
-    let expected: HashSet<Hash> = match self {
-      Self::App { paths, .. } => paths.values().copied().collect(),
-      Self::Comic { pages } => pages.iter().copied().collect(),
-    };
+    let expected = self.referenced_hashes().collect::<HashSet<Hash>>();
+
+    let missing = expected
+      .iter()
+      .filter(|hash| !files.contains_key(hash))
+      .collect::<Vec<&Hash>>();
 
-    for hash in &expected {
-      if !files.contains_key(hash) {
-        missing += 1;
+    match missing.as_slice() {
+      [] => {}
+      [hash] => return package::ManifestReferencesMissingFile { hash: **hash }.fail(),
+      _ => {
+        return package::ManifestMissingFiles {
+          count: missing.len().into_u64(),
+        }
+        .fail()
       }
     }
 
-    ensure!(missing == 0, package::ManifestMissingFiles { missing });
-
     for hash in files.keys() {
       if *hash != manifest && !expected.contains(hash) {
         extra += 1;
@@ -52,3 +582,577 @@ impl Manifest {
     Ok(())
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn comic_content_types_default_to_jpeg() {
+    #[derive(Serialize)]
+    #[serde(tag = "type", rename = "comic")]
+    struct Legacy {
+      pages: Vec<Hash>,
+    }
+
+    let foo = blake3::hash(b"foo");
+
+    let mut buffer = Vec::new();
+    ciborium::into_writer(&Legacy { pages: vec![foo] }, &mut buffer).unwrap();
+
+    let manifest = Manifest::from_cbor(&buffer, blake3::hash(&buffer)).unwrap();
+
+    assert_eq!(
+      manifest,
+      Manifest::Comic {
+        content_types: Vec::new(),
+        dimensions: Vec::new(),
+        pages: vec![foo],
+        provenance: Provenance::default(),
+        thumbnails: Vec::new(),
+      },
+    );
+
+    assert_eq!(manifest.file("0"), Some((mime::IMAGE_JPEG, foo)));
+    assert_eq!(manifest.paths(), [("0.jpg".into(), foo)]);
+  }
+
+  #[test]
+  fn schema() {
+    let manifest = Manifest::Comic {
+      content_types: Vec::new(),
+      dimensions: Vec::new(),
+      pages: Vec::new(),
+      provenance: Provenance::default(),
+      thumbnails: Vec::new(),
+    };
+
+    assert!(manifest
+      .json()
+      .starts_with(br#"{"schema":1,"type":"comic""#));
+
+    let cbor = manifest.cbor();
+
+    assert_eq!(
+      Manifest::from_cbor(&cbor, blake3::hash(&cbor)).unwrap(),
+      manifest
+    );
+
+    #[derive(Serialize)]
+    struct Future {
+      schema: u64,
+      #[serde(rename = "type")]
+      ty: &'static str,
+    }
+
+    let mut cbor = Vec::new();
+    ciborium::into_writer(
+      &Future {
+        schema: 1,
+        ty: "hologram",
+      },
+      &mut cbor,
+    )
+    .unwrap();
+
+    assert_matches!(
+      Manifest::from_cbor(&cbor, blake3::hash(&cbor)).unwrap_err(),
+      package::Error::UnknownManifestType { found, .. } if found == "hologram",
+    );
+
+    let mut cbor = Vec::new();
+    ciborium::into_writer(
+      &Future {
+        schema: 2,
+        ty: "comic",
+      },
+      &mut cbor,
+    )
+    .unwrap();
+
+    assert_matches!(
+      Manifest::from_cbor(&cbor, blake3::hash(&cbor)).unwrap_err(),
+      package::Error::ManifestSchema {
+        found: 2,
+        supported: 1,
+        ..
+      },
+    );
+
+    let mut cbor = Vec::new();
+    ciborium::into_writer(
+      &Future {
+        schema: 2,
+        ty: "hologram",
+      },
+      &mut cbor,
+    )
+    .unwrap();
+
+    assert_matches!(
+      Manifest::from_cbor(&cbor, blake3::hash(&cbor)).unwrap_err(),
+      package::Error::ManifestSchema {
+        found: 2,
+        supported: 1,
+        ..
+      },
+    );
+  }
+
+  #[test]
+  fn referenced_hashes() {
+    let [a, b, c] = [b"a", b"b", b"c"].map(|content| blake3::hash(content));
+
+    for (manifest, expected) in [
+      (
+        Manifest::App {
+          handles: [Type::Comic].into(),
+          headers: BTreeMap::new(),
+          mime_overrides: BTreeMap::new(),
+          paths: [("index.html".into(), a), ("index.js".into(), b)].into(),
+          provenance: Provenance::default(),
+          redirects: BTreeMap::new(),
+        },
+        vec![a, b],
+      ),
+      (
+        Manifest::Audio {
+          durations_ms: Vec::new(),
+          titles: vec!["a".into(), "b".into()],
+          tracks: vec![a, b],
+          provenance: Provenance::default(),
+        },
+        vec![a, b],
+      ),
+      (
+        Manifest::Book {
+          chapters: vec![a, b],
+          toc: Vec::new(),
+          provenance: Provenance::default(),
+        },
+        vec![a, b],
+      ),
+      (
+        Manifest::Comic {
+          content_types: Vec::new(),
+          dimensions: Vec::new(),
+          pages: vec![a, b, c],
+          provenance: Provenance::default(),
+          thumbnails: Vec::new(),
+        },
+        vec![a, b, c],
+      ),
+      (
+        Manifest::Gallery {
+          captions: BTreeMap::new(),
+          images: [("a.png".into(), a)].into(),
+          provenance: Provenance::default(),
+          thumbnails: Vec::new(),
+        },
+        vec![a],
+      ),
+      (
+        Manifest::Video {
+          captions: Some(b),
+          video: a,
+          provenance: Provenance::default(),
+        },
+        vec![a, b],
+      ),
+      (
+        Manifest::Video {
+          captions: None,
+          video: a,
+          provenance: Provenance::default(),
+        },
+        vec![a],
+      ),
+    ] {
+      assert_eq!(
+        manifest.referenced_hashes().collect::<Vec<Hash>>(),
+        expected,
+        "{}",
+        manifest.ty(),
+      );
+    }
+  }
+
+  #[test]
+  fn entries() {
+    let foo = blake3::hash(b"foo");
+
+    for manifest in [
+      Manifest::App {
+        handles: [Type::Comic].into(),
+        headers: BTreeMap::new(),
+        mime_overrides: BTreeMap::new(),
+        paths: [("index.html".into(), foo), ("index.js".into(), foo)].into(),
+        provenance: Provenance::default(),
+        redirects: BTreeMap::new(),
+      },
+      Manifest::Audio {
+        durations_ms: Vec::new(),
+        titles: vec!["foo".into(), "bar".into()],
+        tracks: vec![foo, foo],
+        provenance: Provenance::default(),
+      },
+      Manifest::Book {
+        chapters: vec![foo, foo],
+        toc: vec![("foo".into(), 0)],
+        provenance: Provenance::default(),
+      },
+      Manifest::Comic {
+        content_types: Vec::new(),
+        dimensions: Vec::new(),
+        pages: vec![foo, foo, foo],
+        provenance: Provenance::default(),
+        thumbnails: Vec::new(),
+      },
+      Manifest::Gallery {
+        captions: BTreeMap::new(),
+        images: [("a.png".into(), foo)].into(),
+        provenance: Provenance::default(),
+        thumbnails: Vec::new(),
+      },
+      Manifest::Video {
+        captions: Some(foo),
+        video: foo,
+        provenance: Provenance::default(),
+      },
+      Manifest::Video {
+        captions: None,
+        video: foo,
+        provenance: Provenance::default(),
+      },
+    ] {
+      for entry in manifest.entries() {
+        assert!(
+          manifest.file(&entry).is_some() || manifest.generated(&entry).is_some(),
+          "{entry}",
+        );
+      }
+    }
+
+    assert_eq!(
+      Manifest::Comic {
+        content_types: Vec::new(),
+        dimensions: Vec::new(),
+        pages: vec![foo, foo, foo],
+        provenance: Provenance::default(),
+        thumbnails: Vec::new(),
+      }
+      .entries(),
+      ["0", "1", "2"],
+    );
+
+    assert_eq!(
+      Manifest::Book {
+        chapters: vec![foo, foo],
+        toc: Vec::new(),
+        provenance: Provenance::default(),
+      }
+      .entries(),
+      ["chapter/0", "chapter/1", "toc"],
+    );
+  }
+
+  #[test]
+  fn filename() {
+    let hash = blake3::hash(b"foo");
+
+    let comic = Manifest::Comic {
+      content_types: vec!["image/png".into()],
+      dimensions: Vec::new(),
+      pages: vec![hash],
+      provenance: Provenance::default(),
+      thumbnails: Vec::new(),
+    };
+
+    assert_eq!(comic.filename("0").unwrap(), "0.png");
+    assert_eq!(comic.filename("1"), None);
+
+    let gallery = Manifest::Gallery {
+      captions: BTreeMap::new(),
+      images: [("trips/beach.webp".into(), hash)].into(),
+      provenance: Provenance::default(),
+      thumbnails: Vec::new(),
+    };
+
+    assert_eq!(gallery.filename("trips/beach.webp").unwrap(), "beach.webp");
+    assert_eq!(gallery.filename("captions").unwrap(), "captions.json");
+  }
+
+  #[test]
+  fn thumbnails() {
+    let foo = blake3::hash(b"foo");
+    let bar = blake3::hash(b"bar");
+    let baz = blake3::hash(b"baz");
+    let thumbnail = blake3::hash(b"thumbnail");
+
+    let comic = Manifest::Comic {
+      content_types: vec![
+        "image/jpeg".into(),
+        "image/webp".into(),
+        "image/avif".into(),
+      ],
+      dimensions: Vec::new(),
+      pages: vec![foo, bar, baz],
+      provenance: Provenance::default(),
+      thumbnails: vec![Some(thumbnail), Some(bar), None],
+    };
+
+    let gallery = Manifest::Gallery {
+      captions: BTreeMap::new(),
+      images: [
+        ("a.jpg".into(), foo),
+        ("b.webp".into(), bar),
+        ("c.avif".into(), baz),
+      ]
+      .into(),
+      provenance: Provenance::default(),
+      thumbnails: vec![Some(thumbnail), Some(bar), None],
+    };
+
+    for manifest in [comic, gallery] {
+      assert_eq!(manifest.images(), [foo, bar, baz]);
+
+      assert!(manifest
+        .entries()
+        .ends_with(&["thumb/0".into(), "thumb/1".into()]));
+
+      assert_eq!(
+        manifest.file("thumb/0").unwrap(),
+        (mime::IMAGE_PNG, thumbnail)
+      );
+      assert_eq!(
+        manifest.file("thumb/1").unwrap(),
+        ("image/webp".parse().unwrap(), bar)
+      );
+      assert_eq!(manifest.file("thumb/2"), None);
+      assert_eq!(manifest.file("thumb/3"), None);
+
+      assert_eq!(manifest.filename("thumb/0").unwrap(), "thumb-0.png");
+      assert_eq!(manifest.filename("thumb/1").unwrap(), "thumb-1.webp");
+
+      assert_eq!(
+        manifest.referenced_hashes().collect::<HashSet<Hash>>(),
+        [foo, bar, baz, thumbnail].into(),
+      );
+    }
+
+    assert_eq!(
+      Manifest::Video {
+        captions: None,
+        video: foo,
+        provenance: Provenance::default(),
+      }
+      .with_thumbnails(vec![Some(thumbnail)])
+      .referenced_hashes()
+      .collect::<Vec<Hash>>(),
+      [foo],
+    );
+  }
+
+  #[test]
+  fn verify() {
+    let foo = blake3::hash(b"foo");
+    let bar = blake3::hash(b"bar");
+    let manifest = blake3::hash(b"manifest");
+
+    for referenced in [
+      Manifest::App {
+        handles: [Type::Comic].into(),
+        headers: BTreeMap::new(),
+        mime_overrides: BTreeMap::new(),
+        paths: [("index.html".into(), foo), ("index.js".into(), bar)].into(),
+        provenance: Provenance::default(),
+        redirects: BTreeMap::new(),
+      },
+      Manifest::Comic {
+        content_types: Vec::new(),
+        dimensions: Vec::new(),
+        pages: vec![foo, bar],
+        provenance: Provenance::default(),
+        thumbnails: Vec::new(),
+      },
+    ] {
+      assert!(referenced
+        .verify(manifest, &[(manifest, ()), (foo, ()), (bar, ())].into())
+        .is_ok());
+
+      assert_matches!(
+        referenced
+          .verify(manifest, &[(manifest, ()), (foo, ())].into())
+          .unwrap_err(),
+        package::Error::ManifestReferencesMissingFile { hash, .. } if hash == bar,
+      );
+
+      assert_matches!(
+        referenced
+          .verify(manifest, &[(manifest, ())].into())
+          .unwrap_err(),
+        package::Error::ManifestMissingFiles { count: 2, .. },
+      );
+
+      assert_matches!(
+        referenced
+          .verify(
+            manifest,
+            &[
+              (manifest, ()),
+              (foo, ()),
+              (bar, ()),
+              (blake3::hash(b"baz"), ())
+            ]
+            .into(),
+          )
+          .unwrap_err(),
+        package::Error::ManifestExtraFiles { extra: 1, .. },
+      );
+    }
+  }
+
+  #[test]
+  fn summary() {
+    assert_eq!(
+      Manifest::App {
+        handles: [Type::Comic].into(),
+        headers: BTreeMap::new(),
+        mime_overrides: BTreeMap::new(),
+        paths: BTreeMap::new(),
+        provenance: Provenance::default(),
+        redirects: BTreeMap::new(),
+      }
+      .summary(),
+      Summary {
+        ty: Type::App,
+        chapters: None,
+        duration_ms: None,
+        images: None,
+        pages: None,
+        tracks: None,
+      },
+    );
+
+    assert_eq!(
+      Manifest::Comic {
+        content_types: Vec::new(),
+        dimensions: Vec::new(),
+        pages: vec![blake3::hash(b"foo"), blake3::hash(b"bar")],
+        provenance: Provenance::default(),
+        thumbnails: Vec::new(),
+      }
+      .summary(),
+      Summary {
+        ty: Type::Comic,
+        chapters: None,
+        duration_ms: None,
+        images: None,
+        pages: Some(2),
+        tracks: None,
+      },
+    );
+
+    assert_eq!(
+      Manifest::Audio {
+        durations_ms: vec![Some(1500), Some(2250), Some(0)],
+        titles: vec!["foo".into(), "bar".into(), "baz".into()],
+        tracks: vec![
+          blake3::hash(b"foo"),
+          blake3::hash(b"bar"),
+          blake3::hash(b"baz"),
+        ],
+        provenance: Provenance::default(),
+      }
+      .summary(),
+      Summary {
+        ty: Type::Audio,
+        chapters: None,
+        duration_ms: Some(3750),
+        images: None,
+        pages: None,
+        tracks: Some(3),
+      },
+    );
+
+    for durations_ms in [
+      vec![Some(1500), None, Some(2250)],
+      vec![Some(u64::MAX), Some(1), Some(0)],
+      Vec::new(),
+    ] {
+      assert_eq!(
+        Manifest::Audio {
+          durations_ms: durations_ms.clone(),
+          titles: vec!["foo".into(), "bar".into(), "baz".into()],
+          tracks: vec![
+            blake3::hash(b"foo"),
+            blake3::hash(b"bar"),
+            blake3::hash(b"baz"),
+          ],
+          provenance: Provenance::default(),
+        }
+        .summary()
+        .duration_ms,
+        None,
+        "{durations_ms:?}",
+      );
+    }
+
+    assert_eq!(
+      Manifest::Book {
+        chapters: vec![blake3::hash(b"foo"), blake3::hash(b"bar")],
+        toc: vec![("foo".into(), 0)],
+        provenance: Provenance::default(),
+      }
+      .summary(),
+      Summary {
+        ty: Type::Book,
+        chapters: Some(2),
+        duration_ms: None,
+        images: None,
+        pages: None,
+        tracks: None,
+      },
+    );
+
+    assert_eq!(
+      Manifest::Gallery {
+        captions: BTreeMap::new(),
+        images: [
+          ("0.jpg".into(), blake3::hash(b"foo")),
+          ("1.jpg".into(), blake3::hash(b"bar")),
+          ("2.jpg".into(), blake3::hash(b"baz")),
+        ]
+        .into(),
+        provenance: Provenance::default(),
+        thumbnails: Vec::new(),
+      }
+      .summary(),
+      Summary {
+        ty: Type::Gallery,
+        chapters: None,
+        duration_ms: None,
+        images: Some(3),
+        pages: None,
+        tracks: None,
+      },
+    );
+
+    assert_eq!(
+      Manifest::Video {
+        captions: None,
+        video: blake3::hash(b"foo"),
+        provenance: Provenance::default(),
+      }
+      .summary(),
+      Summary {
+        ty: Type::Video,
+        chapters: None,
+        duration_ms: None,
+        images: None,
+        pages: None,
+        tracks: None,
+      },
+    );
+  }
+}
@@ -0,0 +1,243 @@
+use {super::*, memmap2::Mmap};
+
+#[derive(Debug)]
+pub struct MappedPackage {
+  files: HashMap<Hash, (usize, Entry)>,
+  manifest: Manifest,
+  mmap: Bytes,
+  verified: Mutex<HashSet<Hash>>,
+}
+
+impl MappedPackage {
+  pub fn map(path: &Utf8Path) -> Result<Self, package::Error> {
+    let file = File::open(path)?;
+
+    // Safety: the package must not be modified in place while it is mapped.
+    // `Package` saves by renaming a new file over the old one, which leaves
+    // existing mappings of the old file intact.
+    let mmap = Bytes::from_owner(unsafe { Mmap::map(&file)? });
+
+    let (manifest_hash, files) =
+      Package::read_index(&mut Cursor::new(&mmap[..]), mmap.len().into_u64())?;
+
+    let files = files
+      .into_iter()
+      .map(|(hash, (offset, entry))| (hash, (offset as usize, entry)))
+      .collect::<HashMap<Hash, (usize, Entry)>>();
+
+    let (offset, entry) = files[&manifest_hash];
+
+    let manifest = Package::decode_manifest(
+      manifest_hash,
+      entry,
+      mmap[offset..offset + entry.stored as usize].to_vec(),
+      &files,
+    )?;
+
+    Ok(Self {
+      files,
+      manifest,
+      mmap,
+      verified: Mutex::new([manifest_hash].into()),
+    })
+  }
+
+  // uncompressed files are slices of the mapping, and so are served without
+  // being copied
+  pub fn file(&self, hash: Hash) -> Result<Option<Bytes>, package::Error> {
+    let Some(&(offset, entry)) = self.files.get(&hash) else {
+      return Ok(None);
+    };
+
+    let stored = self.mmap.slice(offset..offset + entry.stored as usize);
+
+    let content = if entry.compressed {
+      entry.decode(stored.to_vec())?.into()
+    } else {
+      stored
+    };
+
+    let verified = self.verified.lock().unwrap().contains(&hash);
+
+    if !verified {
+      let actual = blake3::hash(&content);
+
+      ensure!(
+        actual == hash,
+        package::FileHashInvalid {
+          expected: hash,
+          actual,
+        }
+      );
+
+      self.verified.lock().unwrap().insert(hash);
+    }
+
+    Ok(Some(content))
+  }
+
+  pub fn manifest(&self) -> &Manifest {
+    &self.manifest
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn map() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: app\nhandles: comic").unwrap();
+    fs::write(root.join("index.html"), "foo").unwrap();
+    fs::write(root.join("index.js"), "let x = 0;\n".repeat(1000)).unwrap();
+
+    for compress in [false, true] {
+      subcommand::package::Package {
+        root: vec![root.clone()],
+        output: output.clone(),
+        compress,
+        ..Default::default()
+      }
+      .run()
+      .unwrap();
+
+      let mapped = Package::map(&output).unwrap();
+
+      assert_eq!(mapped.manifest(), &Package::load(&output).unwrap().manifest);
+
+      let html = mapped.file(blake3::hash(b"foo")).unwrap().unwrap();
+      assert_eq!(html, b"foo".as_slice());
+      assert!(mapped.mmap.as_ptr_range().contains(&html.as_ptr()));
+
+      let js = "let x = 0;\n".repeat(1000);
+      assert_eq!(
+        mapped.file(blake3::hash(js.as_bytes())).unwrap().unwrap(),
+        js.as_bytes(),
+      );
+
+      assert_eq!(mapped.file(blake3::hash(b"bar")).unwrap(), None);
+    }
+  }
+
+  #[test]
+  fn repackage_while_mapped() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("0.jpg"), jpeg(b"foo")).unwrap();
+
+    let package = || {
+      subcommand::package::Package {
+        root: vec![root.clone()],
+        output: output.clone(),
+        ..Default::default()
+      }
+      .run()
+      .unwrap();
+    };
+
+    package();
+
+    let mapped = Package::map(&output).unwrap();
+
+    fs::write(root.join("0.jpg"), jpeg(b"bar")).unwrap();
+
+    package();
+
+    assert_eq!(
+      mapped.file(blake3::hash(&jpeg(b"foo"))).unwrap().unwrap(),
+      jpeg(b"foo"),
+    );
+
+    assert_eq!(
+      Package::load(&output)
+        .unwrap()
+        .get(blake3::hash(&jpeg(b"bar"))),
+      Some(jpeg(b"bar").as_slice()),
+    );
+  }
+
+  #[test]
+  fn file_verifies_hash() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("0.jpg"), jpeg(b"foo")).unwrap();
+    fs::write(root.join("1.jpg"), jpeg(b"bar")).unwrap();
+
+    subcommand::package::Package {
+      root: vec![root],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    let mut bytes = fs::read(&output).unwrap();
+    let offset = bytes
+      .windows(3)
+      .position(|window| window == b"foo")
+      .unwrap();
+    bytes[offset] = b'g';
+    fs::write(&output, bytes).unwrap();
+
+    let mapped = Package::map(&output).unwrap();
+
+    assert_eq!(
+      mapped.file(blake3::hash(&jpeg(b"bar"))).unwrap().unwrap(),
+      jpeg(b"bar"),
+    );
+
+    assert_matches!(
+      mapped.file(blake3::hash(&jpeg(b"foo"))).unwrap_err(),
+      package::Error::FileHashInvalid { expected, actual, .. }
+      if expected == blake3::hash(&jpeg(b"foo")) && actual == blake3::hash(&jpeg(b"goo")),
+    );
+  }
+
+  #[test]
+  fn truncated() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("0.jpg"), jpeg(b"foo")).unwrap();
+
+    subcommand::package::Package {
+      root: vec![root],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    let bytes = fs::read(&output).unwrap();
+    fs::write(&output, &bytes[..bytes.len() - 1]).unwrap();
+
+    assert_matches!(
+      Package::map(&output).unwrap_err(),
+      package::Error::FileLengthMismatch { .. },
+    );
+  }
+}
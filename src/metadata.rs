@@ -1,71 +1,300 @@
 use super::*;
 
-#[derive(Copy, Clone, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum Metadata {
-  App { handles: Type },
+  App {
+    #[serde(with = "handles")]
+    handles: BTreeSet<Type>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    headers: BTreeMap<String, BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    mime_overrides: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    redirects: BTreeMap<String, Redirect>,
+  },
+  Audio {
+    titles: Vec<String>,
+  },
+  Book,
   Comic,
+  Gallery,
+  Video,
 }
 
 impl Metadata {
+  pub const CAPTIONS_PATH: &'static str = "captions.yaml";
+  pub const HEADER_PREFIX: u64 = 64 * 1024;
+  pub const IMAGE_EXTENSIONS: &'static [&'static str] = &["avif", "jpeg", "jpg", "png", "webp"];
   pub const PATH: &'static str = "metadata.yaml";
 
   pub fn load(path: &Utf8Path) -> Result<Self> {
-    serde_yaml::from_reader(&File::open(path).context(error::Io { path })?)
-      .context(error::DeserializeMetadata { path })
+    Self::parse(path, &fs::read(path).context(error::Io { path })?)
   }
 
-  pub fn template(self, root: &Utf8Path, paths: &HashSet<Utf8PathBuf>) -> Result<Template> {
+  pub fn parse(path: &Utf8Path, yaml: &[u8]) -> Result<Self> {
+    serde_yaml::from_slice(yaml).context(error::DeserializeMetadata { path })
+  }
+
+  pub fn template(
+    self,
+    root: &Utf8Path,
+    paths: &HashMap<Utf8PathBuf, (Hash, u64)>,
+    read: impl Fn(&Utf8Path, u64) -> Result<Vec<u8>>,
+    open: impl Fn(&Utf8Path) -> Result<Box<dyn MediaSource>>,
+  ) -> Result<Template> {
     match self {
-      Self::App { handles } => {
+      Self::App {
+        handles,
+        headers,
+        mime_overrides,
+        redirects,
+      } => {
         ensure!(
-          paths.contains(Utf8Path::new("index.html")),
+          paths.contains_key(Utf8Path::new("index.html")),
           error::Index { root }
         );
-        Ok(Template::App { handles })
+
+        for (glob, headers) in &headers {
+          Glob::new(glob.trim_start_matches('/')).context(error::InvalidHeaderGlob { glob })?;
+
+          for (name, value) in headers {
+            ensure!(
+              header::HeaderName::from_bytes(name.as_bytes()).is_ok(),
+              error::InvalidHeaderName { name }
+            );
+
+            ensure!(
+              header::HeaderValue::from_str(value).is_ok(),
+              error::InvalidHeaderValue { name, value }
+            );
+          }
+        }
+
+        for (from, redirect) in &redirects {
+          redirect.check(from)?;
+        }
+
+        Ok(Template::App {
+          handles,
+          headers,
+          mime_overrides,
+          redirects,
+        })
+      }
+      Self::Audio { titles } => {
+        let tracks = Self::numbered(root, paths.keys(), Type::Audio, "", "mp3", false)?;
+
+        ensure!(
+          titles.len() == tracks.len(),
+          error::TrackTitles {
+            titles: titles.len().into_u64(),
+            tracks: tracks.len().into_u64(),
+          }
+        );
+
+        // durations are best-effort, since tracks that can't be decoded are
+        // still playable by clients that can
+        let mut durations_ms = Vec::new();
+
+        for track in &tracks {
+          durations_ms.push(sniff::duration_ms(open(track)?));
+        }
+
+        Ok(Template::Audio {
+          durations_ms,
+          titles,
+          tracks,
+        })
+      }
+      Self::Book => {
+        let toc_path = Utf8Path::new(TocEntry::PATH);
+
+        ensure!(paths.contains_key(toc_path), error::TocMissing { root });
+
+        let chapters = Self::numbered(
+          root,
+          paths.keys().filter(|path| *path != toc_path),
+          Type::Book,
+          "chapter-",
+          "html",
+          false,
+        )?;
+
+        let mut toc = Vec::new();
+
+        for TocEntry { title, chapter } in
+          TocEntry::parse(&root.join(toc_path), &read(toc_path, u64::MAX)?)?
+        {
+          ensure!(
+            chapter < chapters.len(),
+            error::TocIndexOutOfBounds {
+              chapter,
+              chapters: chapters.len(),
+              title,
+            }
+          );
+
+          toc.push((title, chapter));
+        }
+
+        Ok(Template::Book { chapters, toc })
       }
       Self::Comic => {
-        let mut pages: Vec<(u64, Utf8PathBuf)> = Vec::new();
+        let pages = Self::numbered(
+          root,
+          paths.keys(),
+          Type::Comic,
+          "",
+          &format!("(?i:{})", Self::IMAGE_EXTENSIONS.join("|")),
+          true,
+        )?;
 
-        let page_re = Regex::new(r"^(\d+)\.jpg$").unwrap();
+        let mut dimensions = Vec::new();
 
-        for path in paths {
-          let captures = page_re
-            .captures(path.as_ref())
-            .context(error::UnexpectedFile {
+        for page in &pages {
+          dimensions.push(
+            sniff::dimensions(&read(page, Self::HEADER_PREFIX)?).context(error::ImageDecode {
+              path: root.join(page),
+            })?,
+          );
+        }
+
+        Ok(Template::Comic { dimensions, pages })
+      }
+      Self::Gallery => {
+        let captions_path = Utf8Path::new(Self::CAPTIONS_PATH);
+
+        let mut images = Vec::new();
+
+        for path in paths.keys() {
+          if path == captions_path {
+            continue;
+          }
+
+          ensure!(
+            path
+              .extension()
+              .is_some_and(|extension| Self::IMAGE_EXTENSIONS
+                .contains(&extension.to_ascii_lowercase().as_str())),
+            error::UnexpectedFile {
               file: path.clone(),
-              ty: self.ty(),
-            })?;
+              ty: Type::Gallery,
+            }
+          );
 
-          pages.push((
-            captures[1].parse().context(error::InvalidPage { path })?,
-            path.clone(),
-          ));
+          images.push(path.clone());
         }
 
-        ensure!(!pages.is_empty(), error::NoPages { root });
+        ensure!(
+          !images.is_empty(),
+          error::NoPages {
+            root,
+            ty: Type::Gallery,
+          }
+        );
+
+        let captions: BTreeMap<String, String> = if paths.contains_key(captions_path) {
+          serde_yaml::from_slice(&read(captions_path, u64::MAX)?).context(
+            error::DeserializeYaml {
+              path: root.join(captions_path),
+            },
+          )?
+        } else {
+          BTreeMap::new()
+        };
+
+        for image in captions.keys() {
+          ensure!(
+            images.iter().any(|path| path == image),
+            error::CaptionImageMissing { image }
+          );
+        }
 
-        pages.sort();
+        images.sort();
 
-        for (i, (page, _path)) in pages.iter().enumerate() {
-          let i = i.into_u64();
-          let page = *page;
+        Ok(Template::Gallery { captions, images })
+      }
+      Self::Video => {
+        let video = Utf8PathBuf::from("video.mp4");
+        let captions = Utf8PathBuf::from("captions.vtt");
 
-          ensure!(i >= page, error::PageMissing { page: i });
-          ensure!(i <= page, error::PageDuplicated { page });
+        for path in paths.keys() {
+          ensure!(
+            *path == video || *path == captions,
+            error::UnexpectedFile {
+              file: path.clone(),
+              ty: Type::Video,
+            }
+          );
         }
 
-        Ok(Template::Comic {
-          pages: pages.into_iter().map(|(_page, path)| path).collect(),
+        ensure!(paths.contains_key(&video), error::VideoMissing { root });
+
+        Ok(Template::Video {
+          captions: paths.contains_key(&captions).then_some(captions),
+          video,
         })
       }
     }
   }
 
-  pub fn ty(self) -> Type {
+  fn numbered<'a>(
+    root: &Utf8Path,
+    paths: impl IntoIterator<Item = &'a Utf8PathBuf>,
+    ty: Type,
+    prefix: &str,
+    extension: &str,
+    padded_one_based: bool,
+  ) -> Result<Vec<Utf8PathBuf>> {
+    let mut numbered: Vec<(u64, Utf8PathBuf)> = Vec::new();
+
+    let re = Regex::new(&format!(r"^{prefix}(\d+)\.{extension}$")).unwrap();
+
+    for path in paths {
+      let captures = re.captures(path.as_ref()).context(error::UnexpectedFile {
+        file: path.clone(),
+        ty,
+      })?;
+
+      numbered.push((
+        captures[1].parse().context(error::InvalidPage { path })?,
+        path.clone(),
+      ));
+    }
+
+    ensure!(!numbered.is_empty(), error::NoPages { root, ty });
+
+    numbered.sort();
+
+    let first = match numbered.first() {
+      Some((1, path))
+        if padded_one_based && re.captures(path.as_ref()).unwrap()[1].starts_with('0') =>
+      {
+        1
+      }
+      _ => 0,
+    };
+
+    for (i, (page, _path)) in numbered.iter().enumerate() {
+      let i = i.into_u64() + first;
+      let page = *page;
+
+      ensure!(i >= page, error::PageMissing { page: i });
+      ensure!(i <= page, error::PageDuplicated { page });
+    }
+
+    Ok(numbered.into_iter().map(|(_page, path)| path).collect())
+  }
+
+  pub fn ty(&self) -> Type {
     match self {
       Self::App { .. } => Type::App,
+      Self::Audio { .. } => Type::Audio,
+      Self::Book => Type::Book,
       Self::Comic => Type::Comic,
+      Self::Gallery => Type::Gallery,
+      Self::Video => Type::Video,
     }
   }
 }
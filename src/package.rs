@@ -1,12 +1,69 @@
 use super::*;
 
+pub(crate) type Index = HashMap<Hash, (u64, Entry)>;
+
+type Contents<'a> = Vec<(Utf8PathBuf, Cow<'a, [u8]>)>;
+
+// where the content of a package file comes from, if not copied verbatim from
+// its path
+#[derive(Debug)]
+pub(crate) enum Content {
+  Generated(Vec<u8>),
+  // re-stripped when written, so that stripped images need not be held in
+  // memory until the package is saved
+  Stripped,
+}
+
 #[derive(Debug, Snafu)]
 #[snafu(context(suffix(false)), visibility(pub))]
 pub enum Error {
-  #[snafu(display("failed to deserialize manifest"))]
-  DeserializeManifest {
+  #[snafu(display("delta package base fingerprint `{actual}` does not match `{expected}`"))]
+  BaseMismatch {
+    actual: Hash,
     backtrace: Option<Backtrace>,
-    source: ciborium::de::Error<io::Error>,
+    expected: Hash,
+  },
+  #[snafu(display("failed to compress package file `{hash}`"))]
+  Compress {
+    backtrace: Option<Backtrace>,
+    hash: Hash,
+    source: io::Error,
+  },
+  #[snafu(display("package file `{hash}` has invalid compression flag {flag}"))]
+  CompressionFlag {
+    backtrace: Option<Backtrace>,
+    flag: u8,
+    hash: Hash,
+  },
+  #[snafu(display("failed to decompress package file `{hash}`"))]
+  Decompress {
+    backtrace: Option<Backtrace>,
+    hash: Hash,
+    source: io::Error,
+  },
+  #[snafu(display(
+    "package file `{hash}` decompressed to {actual} bytes but expected {expected} bytes"
+  ))]
+  DecompressedLength {
+    actual: u64,
+    backtrace: Option<Backtrace>,
+    expected: u64,
+    hash: Hash,
+  },
+  #[snafu(display("package contains no files"))]
+  EmptyPackage { backtrace: Option<Backtrace> },
+  #[cfg(feature = "reqwest")]
+  #[snafu(display("failed to fetch package `{url}`"))]
+  Fetch {
+    backtrace: Option<Backtrace>,
+    source: reqwest::Error,
+    url: String,
+  },
+  #[snafu(display("I/O error writing `{path}`"))]
+  FileWrite {
+    backtrace: Option<Backtrace>,
+    path: Utf8PathBuf,
+    source: io::Error,
   },
   #[snafu(display("package file hash `{hash}` duplicated"))]
   FileHashDuplicated {
@@ -24,12 +81,30 @@ pub enum Error {
     hash: Hash,
     backtrace: Option<Backtrace>,
   },
+  #[snafu(display("package file `{hash}` declares {declared} bytes but package ends first"))]
+  FileLengthMismatch {
+    backtrace: Option<Backtrace>,
+    declared: u64,
+    hash: Hash,
+  },
   #[snafu(display("package file length `{len}` cannot be converted to usize"))]
   FileLengthRange {
     backtrace: Option<Backtrace>,
     len: u64,
     source: TryFromIntError,
   },
+  #[snafu(display("package file length {len} exceeds limit of {limit} bytes"))]
+  FileTooLarge {
+    backtrace: Option<Backtrace>,
+    len: u64,
+    limit: u64,
+  },
+  #[snafu(display("package declares {count} files which exceeds limit of {limit}"))]
+  FileCountTooLarge {
+    backtrace: Option<Backtrace>,
+    count: u64,
+    limit: u64,
+  },
   #[snafu(display("I/O error reading file `{path}`"))]
   FileIo {
     backtrace: Option<Backtrace>,
@@ -72,346 +147,2289 @@ pub enum Error {
     index: u64,
     source: TryFromIntError,
   },
-  #[snafu(display("package missing {missing} files from manifest"))]
+  #[snafu(display("package file `{hash}` at manifest index is not a valid manifest"))]
+  ManifestInvalid {
+    backtrace: Option<Backtrace>,
+    hash: Hash,
+    source: ciborium::de::Error<io::Error>,
+  },
+  #[snafu(display("manifest references {count} files missing from package"))]
   ManifestMissingFiles {
-    missing: u64,
     backtrace: Option<Backtrace>,
+    count: u64,
+  },
+  #[snafu(display("manifest references file `{hash}` missing from package"))]
+  ManifestReferencesMissingFile {
+    backtrace: Option<Backtrace>,
+    hash: Hash,
+  },
+  #[snafu(display(
+    "unsupported manifest schema {found}, only schema {supported} and earlier are supported, upgrade media to read this package"
+  ))]
+  ManifestSchema {
+    backtrace: Option<Backtrace>,
+    found: u64,
+    supported: u64,
+  },
+  #[snafu(display("refusing to extract `{path}` outside of destination"))]
+  PathUnsafe {
+    backtrace: Option<Backtrace>,
+    path: Utf8PathBuf,
+  },
+  #[snafu(display("failed to strip metadata from `{path}`"))]
+  StripMetadata {
+    backtrace: Option<Backtrace>,
+    path: Utf8PathBuf,
+  },
+  #[snafu(display("package signature invalid"))]
+  SignatureInvalid {
+    backtrace: Option<Backtrace>,
+    source: ed25519_dalek::SignatureError,
+  },
+  #[snafu(display("package too short to contain signature"))]
+  SignatureMissing { backtrace: Option<Backtrace> },
+  #[snafu(display("package files total {len} bytes which exceeds limit of {limit} bytes"))]
+  TotalLengthTooLarge {
+    backtrace: Option<Backtrace>,
+    len: u64,
+    limit: u64,
+  },
+  #[snafu(display("template references `{path}` which was not provided"))]
+  TemplateFileMissing {
+    backtrace: Option<Backtrace>,
+    path: Utf8PathBuf,
   },
   #[snafu(display("package has trailing {trailing} bytes"))]
   TrailingBytes {
     backtrace: Option<Backtrace>,
     trailing: u64,
   },
+  #[snafu(display("unknown manifest type `{found}`, upgrade media to read this package"))]
+  UnknownManifestType {
+    backtrace: Option<Backtrace>,
+    found: String,
+  },
+  #[snafu(display("unsupported package version {found}, only version {supported} is supported"))]
+  UnsupportedVersion {
+    backtrace: Option<Backtrace>,
+    found: u16,
+    supported: u16,
+  },
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug)]
 pub struct Package {
   pub files: HashMap<Hash, Vec<u8>>,
+  pub(crate) fingerprint: Option<Hash>,
   pub manifest: Manifest,
+  pub(crate) manifest_hash: Option<Hash>,
+}
+
+impl PartialEq for Package {
+  fn eq(&self, other: &Self) -> bool {
+    self.files == other.files && self.manifest == other.manifest
+  }
 }
 
 impl Package {
   pub const MAGIC_BYTES: &'static str = "MEDIA📦\0";
 
-  pub fn load(path: &Utf8Path) -> Result<Self, Error> {
-    let file = File::open(path)?;
-
-    let len = file.metadata()?.len();
+  pub const DELTA_VERSION: u16 = 2;
 
-    let mut package = BufReader::new(file);
+  const ENTRY_CAPACITY: u64 = 1 << 16;
 
-    let mut bytes = [0; Self::MAGIC_BYTES.len()];
+  pub const VERSION: u16 = 1;
 
-    let mut read = 0;
-    loop {
-      let n = package.read(&mut bytes[read..])?;
+  pub fn load(path: &Utf8Path) -> Result<Self, Error> {
+    Self::load_with_options(path, LoadOptions::default())
+  }
 
-      if n == 0 {
-        break;
-      }
+  pub fn load_with_options(path: &Utf8Path, options: LoadOptions) -> Result<Self, Error> {
+    Self::read(BufReader::new(File::open(path)?), options)
+  }
 
-      read += n;
-    }
+  #[cfg(feature = "reqwest")]
+  pub fn load_from_url(url: &str) -> Result<Self, Error> {
+    let response = reqwest::blocking::get(url)
+      .and_then(reqwest::blocking::Response::error_for_status)
+      .context(Fetch { url })?;
 
-    ensure!(
-      bytes == Self::MAGIC_BYTES.as_bytes(),
-      MagicBytes {
-        bytes: &bytes[..read],
-      }
-    );
+    Self::read(BufReader::new(response), LoadOptions::default())
+  }
 
-    let index = package.read_u64()?;
+  pub fn load_verified(path: &Utf8Path, verifying_key: &VerifyingKey) -> Result<Self, Error> {
+    let mut file = File::open(path)?;
 
-    let index = usize::try_from(index).context(ManifestIndexRange { index })?;
+    let len = file
+      .metadata()?
+      .len()
+      .checked_sub(SIGNATURE_LENGTH.into_u64())
+      .context(SignatureMissing)?;
 
-    let hash_count = package.read_u64()?;
+    let mut hasher = Hasher::new();
 
-    let mut hashes = Vec::<(Hash, u64)>::new();
+    io::copy(&mut (&mut file).take(len), &mut hasher)?;
 
-    for i in 0..hash_count {
-      let hash = package.read_hash()?;
-      let len = package.read_u64()?;
+    let mut signature = [0; SIGNATURE_LENGTH];
 
-      usize::try_from(len).context(FileLengthRange { len })?;
+    file.read_exact(&mut signature)?;
 
-      if let Some(last) = i.checked_sub(1) {
-        let last = hashes[last as usize].0;
-        ensure!(hash.as_bytes() >= last.as_bytes(), FileHashOrder { hash });
+    verifying_key
+      .verify(
+        hasher.finalize().as_bytes(),
+        &Signature::from_bytes(&signature),
+      )
+      .context(SignatureInvalid)?;
 
-        ensure!(
-          hash.as_bytes() != last.as_bytes(),
-          FileHashDuplicated { hash }
-        );
-      }
+    file.rewind()?;
 
-      hashes.push((hash, len));
-    }
+    let mut package = Self::read(BufReader::new(file).take(len), LoadOptions::default())?;
 
-    let manifest_hash = hashes
-      .get(index)
-      .context(ManifestIndexOutOfBounds { index })?
-      .0;
+    // the signature is part of the file, so include it in the fingerprint to
+    // match `Package::fingerprint`
+    hasher.update(&signature);
 
-    let mut files = HashMap::<Hash, Vec<u8>>::new();
+    package.fingerprint = Some(hasher.finalize());
 
-    for (expected, len) in hashes {
-      let mut buffer = vec![0; len as usize];
+    Ok(package)
+  }
 
-      package.read_exact(&mut buffer)?;
+  pub fn parse(bytes: &[u8]) -> Result<Self, Error> {
+    Self::from_reader(bytes)
+  }
 
-      let actual = blake3::hash(&buffer);
+  pub fn validate_bytes(bytes: &[u8]) -> Result<Self, Error> {
+    Self::read(bytes, LoadOptions::VALIDATE)
+  }
 
-      ensure!(actual == expected, FileHashInvalid { expected, actual });
+  pub fn from_reader(package: impl Read) -> Result<Self, Error> {
+    Self::read(package, LoadOptions::default())
+  }
 
-      files.insert(expected, buffer);
-    }
+  fn read(package: impl Read, options: LoadOptions) -> Result<Self, Error> {
+    let mut package = HashReader::new(package);
 
-    let position = package.stream_position()?;
+    let (manifest_hash, entries) = Self::read_header(&mut package, options.max_file_count)?;
 
-    ensure!(
-      position == len,
-      TrailingBytes {
-        trailing: len.saturating_sub(position),
-      }
-    );
+    let files = Self::read_files(&mut package, entries, options)?;
 
-    let manifest: Manifest = ciborium::from_reader(Cursor::new(files.get(&manifest_hash).unwrap()))
-      .context(DeserializeManifest)?;
+    let manifest = Self::deserialize_manifest(&files, manifest_hash)?;
 
     manifest.verify(manifest_hash, &files)?;
 
-    Ok(Self { manifest, files })
+    Ok(Self {
+      files,
+      fingerprint: Some(package.finalize()),
+      manifest,
+      manifest_hash: Some(manifest_hash),
+    })
   }
 
-  pub fn save(
-    hashes: HashMap<Utf8PathBuf, (Hash, u64)>,
-    manifest: &Manifest,
-    output: &Utf8Path,
-    root: &Utf8Path,
-  ) -> Result<(), Error> {
-    let mut package = BufWriter::new(File::create(output)?);
-
-    package.write_all(super::Package::MAGIC_BYTES.as_bytes())?;
+  pub fn load_delta(path: &Utf8Path, base: &Package) -> Result<Self, Error> {
+    let mut package = HashReader::new(BufReader::new(File::open(path)?));
 
-    let paths = hashes
-      .iter()
-      .map(|(path, (hash, _len))| (*hash, path.clone()))
-      .collect::<HashMap<Hash, Utf8PathBuf>>();
+    let (actual, manifest_hash, entries) = Self::read_delta_header(&mut package)?;
 
-    let mut hashes = hashes.values().copied().collect::<Vec<(Hash, u64)>>();
+    let expected = base.base_fingerprint();
 
-    let manifest = {
-      let mut buffer = Vec::new();
-      ciborium::into_writer(&manifest, &mut buffer).unwrap();
-      buffer
-    };
+    ensure!(actual == expected, BaseMismatch { actual, expected });
 
-    let manifest_hash = blake3::hash(&manifest);
+    let mut files = Self::read_files(&mut package, entries, LoadOptions::default())?;
 
-    hashes.push((manifest_hash, manifest.len().into_u64()));
+    let manifest = Self::deserialize_manifest(&files, manifest_hash)?;
 
-    hashes.sort_by_key(|hash| *hash.0.as_bytes());
+    for hash in manifest.referenced_hashes() {
+      if let Some(content) = base.get(hash) {
+        files.entry(hash).or_insert_with(|| content.to_vec());
+      }
+    }
 
-    let index = hashes
-      .iter()
-      .position(|(hash, _len)| *hash == manifest_hash)
-      .unwrap()
-      .into_u64();
+    manifest.verify(manifest_hash, &files)?;
 
-    package.write_u64(index)?;
+    Ok(Self {
+      files,
+      fingerprint: Some(package.finalize()),
+      manifest,
+      manifest_hash: Some(manifest_hash),
+    })
+  }
 
-    package.write_u64(hashes.len().into_u64())?;
+  fn deserialize_manifest(
+    files: &HashMap<Hash, Vec<u8>>,
+    manifest_hash: Hash,
+  ) -> Result<Manifest, Error> {
+    let manifest = files
+      .get(&manifest_hash)
+      .context(ManifestReferencesMissingFile {
+        hash: manifest_hash,
+      })?;
+
+    Manifest::from_cbor(manifest, manifest_hash)
+  }
 
-    for (hash, len) in &hashes {
-      package.write_hash(*hash)?;
-      package.write_u64(*len)?;
+  fn read_files(
+    package: &mut impl Read,
+    entries: Vec<Entry>,
+    options: LoadOptions,
+  ) -> Result<HashMap<Hash, Vec<u8>>, Error> {
+    let mut total = 0u64;
+
+    for entry in &entries {
+      let len = entry.len.max(entry.stored);
+
+      ensure!(
+        len <= options.max_file_len,
+        FileTooLarge {
+          len,
+          limit: options.max_file_len,
+        }
+      );
+
+      total = total.saturating_add(len);
     }
 
-    for (hash, _len) in hashes {
-      if hash == manifest_hash {
-        package.write_all(&manifest)?;
-      } else {
-        let path = root.join(paths.get(&hash).unwrap());
+    ensure!(
+      total <= options.max_total_len,
+      TotalLengthTooLarge {
+        len: total,
+        limit: options.max_total_len,
+      }
+    );
 
-        let mut file = File::open(&path).context(FileIo { path: &path })?;
+    let mut files = HashMap::<Hash, Vec<u8>>::with_capacity(entries.len());
 
-        io::copy(&mut file, &mut package).context(IoCopy { path: &path })?;
-      }
-    }
+    for entry in entries {
+      let buffer = entry.decode(Self::read_stored(package, &entry)?)?;
 
-    Ok(())
-  }
+      let actual = blake3::hash(&buffer);
 
-  pub fn file(&self, path: &str) -> Option<(Mime, Vec<u8>)> {
-    match &self.manifest {
-      Manifest::App { paths, .. } => Some((
-        mime_guess::from_path(path).first_or_octet_stream(),
-        self.files.get(paths.get(path)?).unwrap().clone(),
-      )),
-      Manifest::Comic { pages } => Some((
-        mime::IMAGE_JPEG,
-        self
-          .files
-          .get(pages.get(path.parse::<usize>().ok()?)?)
-          .unwrap()
-          .clone(),
-      )),
+      ensure!(
+        actual == entry.hash,
+        FileHashInvalid {
+          expected: entry.hash,
+          actual,
+        }
+      );
+
+      files.insert(entry.hash, buffer);
     }
-  }
-}
 
-#[cfg(test)]
-mod tests {
-  use super::*;
+    Self::read_trailing(package)?;
 
-  #[test]
-  fn load_bad_magic_bytes() {
-    let tempdir = tempdir();
+    Ok(files)
+  }
 
-    let package = tempdir.path_utf8().join("package.package");
+  fn read_stored(package: &mut impl Read, entry: &Entry) -> Result<Vec<u8>, Error> {
+    let mut buffer = Vec::new();
 
-    fs::write(&package, "this-is-not-a-package").unwrap();
+    package.take(entry.stored).read_to_end(&mut buffer)?;
 
-    assert_matches!(
-      Package::load(&package).unwrap_err(),
-      Error::MagicBytes { bytes, .. }
-      if bytes == *b"this-is-no"
+    ensure!(
+      buffer.len().into_u64() == entry.stored,
+      FileLengthMismatch {
+        declared: entry.stored,
+        hash: entry.hash,
+      }
     );
-  }
 
-  #[test]
-  fn load_truncated_magic_bytes() {
-    let tempdir = tempdir();
+    Ok(buffer)
+  }
 
-    let package = tempdir.path_utf8().join("package.package");
+  fn read_trailing(package: &mut impl Read) -> Result<(), Error> {
+    let trailing = io::copy(package, &mut io::sink())?;
 
-    fs::write(&package, "MEDIA").unwrap();
+    ensure!(trailing == 0, TrailingBytes { trailing });
 
-    assert_matches!(
-      Package::load(&package).unwrap_err(),
-      Error::MagicBytes { bytes, .. }
-      if bytes == *b"MEDIA"
-    );
+    Ok(())
   }
 
-  #[test]
-  fn manifest_index_out_of_bounds() {
-    let tempdir = tempdir();
+  pub fn verify(path: &Utf8Path) -> Result<Manifest, Error> {
+    let mut package = BufReader::new(File::open(path)?);
 
-    let package = tempdir.path_utf8().join("package.package");
+    let (manifest_hash, entries) = Self::read_header(&mut package, u64::MAX)?;
 
-    let mut bytes = Vec::new();
+    let mut manifest = Vec::new();
 
-    bytes.extend_from_slice(Package::MAGIC_BYTES.as_bytes());
-    bytes.extend_from_slice(&0u64.to_le_bytes());
-    bytes.extend_from_slice(&0u64.to_le_bytes());
+    let mut files = HashMap::<Hash, u64>::new();
 
-    fs::write(&package, bytes).unwrap();
+    for entry in entries {
+      let mut stored = (&mut package).take(entry.stored);
 
-    assert_matches!(
-      Package::load(&package).unwrap_err(),
-      Error::ManifestIndexOutOfBounds { index: 0, .. },
-    );
-  }
+      let (actual, len) = if entry.hash == manifest_hash {
+        manifest = entry.decode(Self::read_stored(&mut stored, &entry)?)?;
+        (blake3::hash(&manifest), manifest.len().into_u64())
+      } else if entry.compressed {
+        let mut hasher = Hasher::new();
+        let len = io::copy(
+          &mut zstd::Decoder::new(&mut stored).context(Decompress { hash: entry.hash })?,
+          &mut hasher,
+        )
+        .context(Decompress { hash: entry.hash })?;
+        (hasher.finalize(), len)
+      } else {
+        let mut hasher = Hasher::new();
+        let len = io::copy(&mut stored, &mut hasher)?;
+        (hasher.finalize(), len)
+      };
+
+      ensure!(
+        stored.limit() == 0,
+        FileLengthMismatch {
+          declared: entry.stored,
+          hash: entry.hash,
+        }
+      );
+
+      ensure!(
+        len == entry.len,
+        DecompressedLength {
+          actual: len,
+          expected: entry.len,
+          hash: entry.hash,
+        }
+      );
+
+      ensure!(
+        actual == entry.hash,
+        FileHashInvalid {
+          expected: entry.hash,
+          actual,
+        }
+      );
+
+      files.insert(entry.hash, len);
+    }
 
-  #[test]
-  fn file_hashes_out_of_order() {
-    let tempdir = tempdir();
+    Self::read_trailing(&mut package)?;
 
-    let package = tempdir.path_utf8().join("package.package");
+    let manifest = Manifest::from_cbor(&manifest, manifest_hash)?;
 
-    let mut bytes = Vec::new();
+    manifest.verify(manifest_hash, &files)?;
 
-    bytes.extend_from_slice(Package::MAGIC_BYTES.as_bytes());
-    bytes.extend_from_slice(&0u64.to_le_bytes());
-    bytes.extend_from_slice(&2u64.to_le_bytes());
-    bytes.extend_from_slice(&[1; 32]);
-    bytes.extend_from_slice(&0u64.to_le_bytes());
-    bytes.extend_from_slice(&[0; 32]);
-    bytes.extend_from_slice(&0u64.to_le_bytes());
+    Ok(manifest)
+  }
 
-    fs::write(&package, bytes).unwrap();
+  pub fn fingerprint(path: &Utf8Path) -> Result<Hash, Error> {
+    let mut hasher = Hasher::new();
 
-    assert_matches!(
-      Package::load(&package).unwrap_err(),
-      Error::FileHashOrder { hash, .. }
-      if hash.as_bytes() == &[0; 32],
-    );
-  }
+    hasher.update_reader(File::open(path)?)?;
 
-  #[test]
-  fn file_hash_duplicated() {
-    let tempdir = tempdir();
+    Ok(hasher.finalize())
+  }
 
-    let package = tempdir.path_utf8().join("package.package");
+  pub fn manifest_json(&self) -> Vec<u8> {
+    self.manifest.json()
+  }
 
-    let mut bytes = Vec::new();
+  pub fn manifest_hash(&self) -> Hash {
+    self
+      .manifest_hash
+      .unwrap_or_else(|| blake3::hash(&self.manifest.cbor()))
+  }
 
-    bytes.extend_from_slice(Package::MAGIC_BYTES.as_bytes());
-    bytes.extend_from_slice(&0u64.to_le_bytes());
-    bytes.extend_from_slice(&2u64.to_le_bytes());
-    bytes.extend_from_slice(&[0; 32]);
-    bytes.extend_from_slice(&0u64.to_le_bytes());
-    bytes.extend_from_slice(&[0; 32]);
-    bytes.extend_from_slice(&0u64.to_le_bytes());
+  // loaded packages use the fingerprint of the file they were loaded from, the
+  // same as `Package::fingerprint`, and packages built in memory have no file,
+  // so use the fingerprint of the file they would be saved as
+  fn base_fingerprint(&self) -> Hash {
+    self.fingerprint.unwrap_or_else(|| {
+      let mut package = Vec::new();
+      self.serialize_files(&mut package, false).unwrap();
+      blake3::hash(&package)
+    })
+  }
 
-    fs::write(&package, bytes).unwrap();
+  pub fn map(path: &Utf8Path) -> Result<MappedPackage, Error> {
+    MappedPackage::map(path)
+  }
 
-    assert_matches!(
-      Package::load(&package).unwrap_err(),
-      Error::FileHashDuplicated { hash, .. }
-      if hash.as_bytes() == &[0; 32],
-    );
+  pub fn open(path: &Utf8Path) -> Result<PackageReader, Error> {
+    PackageReader::open(path)
   }
 
-  #[test]
-  fn file_hash_invalid() {
-    let tempdir = tempdir();
+  pub(crate) fn decode_manifest<T>(
+    hash: Hash,
+    entry: Entry,
+    stored: Vec<u8>,
+    files: &HashMap<Hash, T>,
+  ) -> Result<Manifest, Error> {
+    let buffer = entry.decode(stored)?;
 
-    let package = tempdir.path_utf8().join("package.package");
+    let actual = blake3::hash(&buffer);
 
-    let mut bytes = Vec::new();
+    ensure!(
+      actual == hash,
+      FileHashInvalid {
+        expected: hash,
+        actual,
+      }
+    );
 
-    bytes.extend_from_slice(Package::MAGIC_BYTES.as_bytes());
-    bytes.extend_from_slice(&0u64.to_le_bytes());
-    bytes.extend_from_slice(&1u64.to_le_bytes());
-    bytes.extend_from_slice(&[0; 32]);
-    bytes.extend_from_slice(&0u64.to_le_bytes());
+    let manifest = Manifest::from_cbor(&buffer, hash)?;
 
-    fs::write(&package, bytes).unwrap();
+    manifest.verify(hash, files)?;
+
+    Ok(manifest)
+  }
+
+  pub(crate) fn read_index(
+    package: &mut (impl Read + Seek),
+    len: u64,
+  ) -> Result<(Hash, Index), Error> {
+    let (manifest_hash, entries) = Self::read_header(package, u64::MAX)?;
+
+    let mut offset = package.stream_position()?;
+
+    let mut files = Index::new();
+
+    for entry in entries {
+      let max = entry.len.max(entry.stored);
+
+      // files are decoded on demand, so only the per-file limit applies
+      ensure!(
+        max <= LoadOptions::DEFAULT_MAX_FILE_LEN,
+        FileTooLarge {
+          len: max,
+          limit: LoadOptions::DEFAULT_MAX_FILE_LEN,
+        }
+      );
+
+      files.insert(entry.hash, (offset, entry));
+      offset = offset
+        .checked_add(entry.stored)
+        .filter(|&end| end <= len)
+        .context(FileLengthMismatch {
+          declared: entry.stored,
+          hash: entry.hash,
+        })?;
+    }
+
+    ensure!(
+      offset == len,
+      TrailingBytes {
+        trailing: len - offset,
+      }
+    );
+
+    Ok((manifest_hash, files))
+  }
+
+  pub(crate) fn read_header(
+    package: &mut impl Read,
+    max_file_count: u64,
+  ) -> Result<(Hash, Vec<Entry>), Error> {
+    Self::read_version(package, Self::VERSION)?;
+    Self::read_entries(package, max_file_count)
+  }
+
+  fn read_delta_header(package: &mut impl Read) -> Result<(Hash, Hash, Vec<Entry>), Error> {
+    Self::read_version(package, Self::DELTA_VERSION)?;
+    let base = package.read_hash()?;
+    let (manifest_hash, entries) = Self::read_entries(package, u64::MAX)?;
+    Ok((base, manifest_hash, entries))
+  }
+
+  fn read_version(package: &mut impl Read, supported: u16) -> Result<(), Error> {
+    let mut bytes = [0; Self::MAGIC_BYTES.len()];
+
+    let mut read = 0;
+    loop {
+      let n = package.read(&mut bytes[read..])?;
+
+      if n == 0 {
+        break;
+      }
+
+      read += n;
+    }
+
+    ensure!(
+      bytes == Self::MAGIC_BYTES.as_bytes(),
+      MagicBytes {
+        bytes: &bytes[..read],
+      }
+    );
+
+    let version = package.read_u16()?;
+
+    ensure!(
+      version == supported,
+      UnsupportedVersion {
+        found: version,
+        supported,
+      }
+    );
+
+    Ok(())
+  }
+
+  fn read_entries(
+    package: &mut impl Read,
+    max_file_count: u64,
+  ) -> Result<(Hash, Vec<Entry>), Error> {
+    let index = package.read_u64()?;
+
+    let index = usize::try_from(index).context(ManifestIndexRange { index })?;
+
+    let hash_count = package.read_u64()?;
+
+    ensure!(hash_count > 0, EmptyPackage);
+
+    ensure!(
+      hash_count <= max_file_count,
+      FileCountTooLarge {
+        count: hash_count,
+        limit: max_file_count,
+      }
+    );
+
+    let mut entries = Vec::<Entry>::with_capacity(hash_count.min(Self::ENTRY_CAPACITY) as usize);
+
+    for i in 0..hash_count {
+      let entry = Entry::read(package)?;
+
+      let hash = entry.hash;
+
+      if let Some(last) = i.checked_sub(1) {
+        let last = entries[last as usize].hash;
+        ensure!(hash.as_bytes() >= last.as_bytes(), FileHashOrder { hash });
+
+        ensure!(
+          hash.as_bytes() != last.as_bytes(),
+          FileHashDuplicated { hash }
+        );
+      }
+
+      entries.push(entry);
+    }
+
+    let manifest_hash = entries
+      .get(index)
+      .context(ManifestIndexOutOfBounds { index })?
+      .hash;
+
+    Ok((manifest_hash, entries))
+  }
+
+  pub fn save(
+    hashes: HashMap<Utf8PathBuf, (Hash, u64)>,
+    manifest: &Manifest,
+    output: &Utf8Path,
+    root: &Utf8Path,
+    compress: bool,
+  ) -> Result<(), Error> {
+    Self::create(output, |package| {
+      Self::write(hashes, manifest, package, root, compress, None)
+    })
+  }
+
+  pub(crate) fn save_with_contents(
+    hashes: HashMap<Utf8PathBuf, (Hash, u64)>,
+    contents: HashMap<Hash, Content>,
+    manifest: &Manifest,
+    output: &Utf8Path,
+    root: &Utf8Path,
+    compress: bool,
+  ) -> Result<(), Error> {
+    Self::create(output, |package| {
+      Self::write_with_contents(hashes, &contents, manifest, package, root, compress, None)
+    })
+  }
+
+  pub fn save_delta(
+    base: &Package,
+    mut hashes: HashMap<Utf8PathBuf, (Hash, u64)>,
+    manifest: &Manifest,
+    output: &Utf8Path,
+    root: &Utf8Path,
+    compress: bool,
+  ) -> Result<(), Error> {
+    hashes.retain(|_path, (hash, _len)| !base.contains(*hash));
+
+    Self::create(output, |package| {
+      Self::write(
+        hashes,
+        manifest,
+        package,
+        root,
+        compress,
+        Some(base.base_fingerprint()),
+      )
+    })
+  }
+
+  // packages are written to a temporary file next to `output` and renamed
+  // into place, so that a server with the previous package mapped into memory
+  // never sees it truncated
+  fn create(
+    output: &Utf8Path,
+    write: impl FnOnce(&mut BufWriter<NamedTempFile>) -> Result<(), Error>,
+  ) -> Result<(), Error> {
+    let directory = match output.parent() {
+      Some(parent) if !parent.as_str().is_empty() => parent,
+      _ => Utf8Path::new("."),
+    };
+
+    let mut builder = tempfile::Builder::new();
+
+    #[cfg(unix)]
+    builder.permissions(std::os::unix::fs::PermissionsExt::from_mode(0o666));
+
+    let mut package = BufWriter::new(builder.tempfile_in(directory)?);
+
+    write(&mut package)?;
+
+    package
+      .into_inner()
+      .map_err(io::IntoInnerError::into_error)?
+      .persist(output)
+      .map_err(|err| err.error)?;
+
+    Ok(())
+  }
+
+  fn write(
+    hashes: HashMap<Utf8PathBuf, (Hash, u64)>,
+    manifest: &Manifest,
+    package: &mut impl Write,
+    root: &Utf8Path,
+    compress: bool,
+    base: Option<Hash>,
+  ) -> Result<(), Error> {
+    Self::write_with_contents(
+      hashes,
+      &HashMap::new(),
+      manifest,
+      package,
+      root,
+      compress,
+      base,
+    )
+  }
+
+  fn write_with_contents(
+    hashes: HashMap<Utf8PathBuf, (Hash, u64)>,
+    contents: &HashMap<Hash, Content>,
+    manifest: &Manifest,
+    package: &mut impl Write,
+    root: &Utf8Path,
+    compress: bool,
+    base: Option<Hash>,
+  ) -> Result<(), Error> {
+    let paths = hashes
+      .iter()
+      .map(|(path, (hash, _len))| (*hash, root.join(path)))
+      .collect::<HashMap<Hash, Utf8PathBuf>>();
+
+    Self::serialize(
+      package,
+      hashes
+        .values()
+        .copied()
+        .chain(contents.iter().filter_map(|(hash, content)| match content {
+          Content::Generated(content) => Some((*hash, content.len().into_u64())),
+          Content::Stripped => None,
+        })),
+      manifest,
+      compress,
+      base,
+      |hash, writer| {
+        match contents.get(&hash) {
+          Some(Content::Generated(content)) => writer.write_all(content)?,
+          Some(Content::Stripped) => {
+            let path = &paths[&hash];
+            let content = fs::read(path).context(FileIo { path })?;
+            writer.write_all(&strip::metadata(content).context(StripMetadata { path })?)?;
+          }
+          None => {
+            let path = &paths[&hash];
+            let mut file = File::open(path).context(FileIo { path })?;
+            io::copy(&mut file, writer).context(IoCopy { path })?;
+          }
+        }
+        Ok(())
+      },
+    )
+  }
+
+  pub fn repack(&self, output: &Utf8Path, compress: bool) -> Result<(), Error> {
+    Self::create(output, |package| self.serialize_files(package, compress))
+  }
+
+  fn serialize_files(&self, package: &mut impl Write, compress: bool) -> Result<(), Error> {
+    Self::serialize(
+      package,
+      self
+        .files
+        .iter()
+        .map(|(hash, content)| (*hash, content.len().into_u64())),
+      &self.manifest,
+      compress,
+      None,
+      |hash, writer| {
+        writer.write_all(&self.files[&hash])?;
+        Ok(())
+      },
+    )
+  }
+
+  pub fn build(files: HashMap<Utf8PathBuf, Vec<u8>>, template: Template) -> Result<Vec<u8>, Error> {
+    let hashes = files
+      .iter()
+      .map(|(path, content)| {
+        (
+          path.clone(),
+          (blake3::hash(content), content.len().into_u64()),
+        )
+      })
+      .collect::<HashMap<Utf8PathBuf, (Hash, u64)>>();
+
+    for path in template.paths() {
+      ensure!(hashes.contains_key(path), TemplateFileMissing { path });
+    }
+
+    let contents = files
+      .values()
+      .map(|content| (blake3::hash(content), content.as_slice()))
+      .collect::<HashMap<Hash, &[u8]>>();
+
+    let manifest = template.manifest(&hashes);
+
+    let mut package = Vec::new();
+
+    Self::serialize(
+      &mut package,
+      hashes.values().copied(),
+      &manifest,
+      false,
+      None,
+      |hash, writer| {
+        writer.write_all(contents[&hash])?;
+        Ok(())
+      },
+    )?;
+
+    Ok(package)
+  }
+
+  fn serialize(
+    package: &mut impl Write,
+    files: impl Iterator<Item = (Hash, u64)>,
+    manifest: &Manifest,
+    compress: bool,
+    base: Option<Hash>,
+    mut copy: impl FnMut(Hash, &mut dyn Write) -> Result<(), Error>,
+  ) -> Result<(), Error> {
+    package.write_all(super::Package::MAGIC_BYTES.as_bytes())?;
+
+    match base {
+      Some(base) => {
+        package.write_u16(Self::DELTA_VERSION)?;
+        package.write_hash(base)?;
+      }
+      None => package.write_u16(Self::VERSION)?,
+    }
+
+    let manifest = {
+      let mut buffer = Vec::new();
+      ciborium::into_writer(&manifest, &mut buffer).unwrap();
+      buffer
+    };
+
+    let manifest_hash = blake3::hash(&manifest);
+
+    let mut entries = files
+      .chain([(manifest_hash, manifest.len().into_u64())])
+      .collect::<HashMap<Hash, u64>>()
+      .into_iter()
+      .map(|(hash, len)| Entry {
+        compressed: false,
+        hash,
+        len,
+        stored: len,
+      })
+      .collect::<Vec<Entry>>();
+
+    // entries are written in hash order, never in `HashMap` iteration order, so
+    // that packaging the same files always produces byte-identical output
+    entries.sort_by_key(|entry| *entry.hash.as_bytes());
+
+    debug_assert!(entries
+      .windows(2)
+      .all(|pair| pair[0].hash.as_bytes() < pair[1].hash.as_bytes()));
+
+    let mut content = |hash: Hash, writer: &mut dyn Write| {
+      if hash == manifest_hash {
+        writer.write_all(&manifest)?;
+        Ok(())
+      } else {
+        copy(hash, writer)
+      }
+    };
+
+    // compressed entries are staged in a temporary file so that their stored
+    // length is known before the hash table is written, without holding them
+    // in memory
+    let mut staged = compress.then(tempfile::tempfile).transpose()?;
+
+    if let Some(staged) = &mut staged {
+      for entry in &mut entries {
+        let hash = entry.hash;
+
+        let start = staged.stream_position()?;
+
+        let mut encoder = zstd::Encoder::new(&mut *staged, 0).context(Compress { hash })?;
+
+        content(hash, &mut encoder)?;
+
+        encoder.finish().context(Compress { hash })?;
+
+        let stored = staged.stream_position()? - start;
+
+        if stored < entry.len {
+          entry.compressed = true;
+          entry.stored = stored;
+        } else {
+          staged.set_len(start)?;
+          staged.seek(SeekFrom::Start(start))?;
+        }
+      }
+
+      staged.rewind()?;
+    }
+
+    let index = entries
+      .iter()
+      .position(|entry| entry.hash == manifest_hash)
+      .unwrap()
+      .into_u64();
+
+    package.write_u64(index)?;
+
+    package.write_u64(entries.len().into_u64())?;
+
+    for entry in &entries {
+      entry.write(package)?;
+    }
+
+    for entry in entries {
+      match &mut staged {
+        Some(staged) if entry.compressed => {
+          io::copy(&mut (&mut *staged).take(entry.stored), package)?;
+        }
+        _ => content(entry.hash, package)?,
+      }
+    }
+
+    Ok(())
+  }
+
+  pub fn save_signed(
+    hashes: HashMap<Utf8PathBuf, (Hash, u64)>,
+    manifest: &Manifest,
+    output: &Utf8Path,
+    root: &Utf8Path,
+    compress: bool,
+    signing_key: &SigningKey,
+  ) -> Result<(), Error> {
+    Self::create(output, |package| {
+      Self::write(hashes, manifest, package, root, compress, None)?;
+
+      package.flush()?;
+
+      let file = package.get_mut();
+
+      file.rewind()?;
+
+      let mut hasher = Hasher::new();
+
+      hasher.update_reader(&mut *file)?;
+
+      let signature = signing_key.sign(hasher.finalize().as_bytes());
+
+      package.write_all(&signature.to_bytes())?;
+
+      Ok(())
+    })
+  }
+
+  pub fn extract(&self, destination: &Utf8Path) -> Result<(), Error> {
+    for (path, content) in self.contents()? {
+      let path = destination.join(path);
+
+      if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context(FileWrite { path: parent })?;
+      }
+
+      fs::write(&path, content).context(FileWrite { path: &path })?;
+    }
+
+    Ok(())
+  }
+
+  pub fn export(&self, output: &Utf8Path) -> Result<(), Error> {
+    let contents = self.contents()?;
+
+    Self::create(output, |package| {
+      let mut writer = ZipWriter::new(package);
+
+      for (path, content) in contents {
+        let options = SimpleFileOptions::default()
+          .compression_method(CompressionMethod::Stored)
+          .large_file(content.len().into_u64() >= u32::MAX.into());
+
+        writer
+          .start_file(path.as_str(), options)
+          .map_err(io::Error::from)
+          .context(FileWrite { path: output })?;
+
+        writer
+          .write_all(&content)
+          .context(FileWrite { path: output })?;
+      }
+
+      writer
+        .finish()
+        .map_err(io::Error::from)
+        .context(FileWrite { path: output })?;
+
+      Ok(())
+    })
+  }
+
+  // files are borrowed from the package, and only generated files are owned
+  fn contents(&self) -> Result<Contents<'_>, Error> {
+    let paths = self.manifest.paths();
+
+    for (path, _hash) in &paths {
+      ensure!(
+        path
+          .components()
+          .all(|component| matches!(component, Utf8Component::Normal(_))),
+        PathUnsafe { path }
+      );
+    }
+
+    let mut contents = paths
+      .into_iter()
+      .map(|(path, hash)| {
+        let content = self
+          .files
+          .get(&hash)
+          .context(ManifestReferencesMissingFile { hash })?;
+        Ok((path, Cow::Borrowed(content.as_slice())))
+      })
+      .collect::<Result<Contents, Error>>()?;
+
+    let mut metadata = serde_yaml::to_string(&self.manifest.metadata()).unwrap();
+
+    if !self.manifest.provenance().is_empty() {
+      metadata.push_str(&serde_yaml::to_string(self.manifest.provenance()).unwrap());
+    }
+
+    contents.push((Metadata::PATH.into(), metadata.into_bytes().into()));
+
+    for (path, content) in self.manifest.sidecars() {
+      contents.push((path.into(), content.into_bytes().into()));
+    }
+
+    Ok(contents)
+  }
+
+  pub fn contains(&self, hash: Hash) -> bool {
+    self.files.contains_key(&hash)
+  }
+
+  pub fn entries(&self) -> Vec<String> {
+    self.manifest.entries()
+  }
+
+  pub fn get(&self, hash: Hash) -> Option<&[u8]> {
+    self.files.get(&hash).map(Vec::as_slice)
+  }
+
+  pub fn file(&self, path: &str) -> Option<(Mime, Vec<u8>)> {
+    let Ok(content) = self.manifest.content(path, |hash| {
+      Ok::<_, Infallible>(self.get(hash).map(<[u8]>::to_vec))
+    });
+
+    content.map(|(content_type, _hash, content)| (content_type, content))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn load_bad_magic_bytes() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("package.package");
+
+    fs::write(&package, "this-is-not-a-package").unwrap();
+
+    assert_matches!(
+      Package::load(&package).unwrap_err(),
+      Error::MagicBytes { bytes, .. }
+      if bytes == *b"this-is-no"
+    );
+  }
+
+  #[test]
+  fn load_truncated_magic_bytes() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("package.package");
+
+    fs::write(&package, "MEDIA").unwrap();
+
+    assert_matches!(
+      Package::load(&package).unwrap_err(),
+      Error::MagicBytes { bytes, .. }
+      if bytes == *b"MEDIA"
+    );
+  }
+
+  #[test]
+  fn empty_package() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("package.package");
+
+    let mut bytes = Vec::new();
+
+    bytes.extend_from_slice(Package::MAGIC_BYTES.as_bytes());
+    bytes.extend_from_slice(&Package::VERSION.to_le_bytes());
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+
+    fs::write(&package, bytes).unwrap();
+
+    assert_matches!(
+      Package::load(&package).unwrap_err(),
+      Error::EmptyPackage { .. },
+    );
+  }
+
+  #[test]
+  fn manifest_index_out_of_bounds() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("package.package");
+
+    let mut bytes = Vec::new();
+
+    bytes.extend_from_slice(Package::MAGIC_BYTES.as_bytes());
+    bytes.extend_from_slice(&Package::VERSION.to_le_bytes());
+    bytes.extend_from_slice(&1u64.to_le_bytes());
+    bytes.extend_from_slice(&1u64.to_le_bytes());
+    bytes.extend_from_slice(blake3::hash(b"foo").as_bytes());
+    bytes.extend_from_slice(&3u64.to_le_bytes());
+    bytes.push(0);
+    bytes.extend_from_slice(&3u64.to_le_bytes());
+    bytes.extend_from_slice(b"foo");
+
+    fs::write(&package, bytes).unwrap();
+
+    assert_matches!(
+      Package::load(&package).unwrap_err(),
+      Error::ManifestIndexOutOfBounds { index: 1, .. },
+    );
+  }
+
+  #[test]
+  fn file_hashes_out_of_order() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("package.package");
+
+    let mut bytes = Vec::new();
+
+    bytes.extend_from_slice(Package::MAGIC_BYTES.as_bytes());
+    bytes.extend_from_slice(&Package::VERSION.to_le_bytes());
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.extend_from_slice(&2u64.to_le_bytes());
+    bytes.extend_from_slice(&[1; 32]);
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.push(0);
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.extend_from_slice(&[0; 32]);
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.push(0);
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+
+    fs::write(&package, bytes).unwrap();
+
+    assert_matches!(
+      Package::load(&package).unwrap_err(),
+      Error::FileHashOrder { hash, .. }
+      if hash.as_bytes() == &[0; 32],
+    );
+  }
+
+  #[test]
+  fn file_hash_duplicated() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("package.package");
+
+    let mut bytes = Vec::new();
+
+    bytes.extend_from_slice(Package::MAGIC_BYTES.as_bytes());
+    bytes.extend_from_slice(&Package::VERSION.to_le_bytes());
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.extend_from_slice(&2u64.to_le_bytes());
+    bytes.extend_from_slice(&[0; 32]);
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.push(0);
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.extend_from_slice(&[0; 32]);
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.push(0);
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+
+    fs::write(&package, bytes).unwrap();
+
+    assert_matches!(
+      Package::load(&package).unwrap_err(),
+      Error::FileHashDuplicated { hash, .. }
+      if hash.as_bytes() == &[0; 32],
+    );
+  }
+
+  #[test]
+  fn file_hash_invalid() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("package.package");
+
+    let mut bytes = Vec::new();
+
+    bytes.extend_from_slice(Package::MAGIC_BYTES.as_bytes());
+    bytes.extend_from_slice(&Package::VERSION.to_le_bytes());
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.extend_from_slice(&1u64.to_le_bytes());
+    bytes.extend_from_slice(&[0; 32]);
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.push(0);
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+
+    fs::write(&package, bytes).unwrap();
+
+    assert_matches!(
+      Package::load(&package).unwrap_err(),
+      Error::FileHashInvalid { actual, expected, .. }
+      if actual == blake3::hash(&[]) && expected.as_bytes() == &[0; 32],
+    );
+  }
+
+  #[test]
+  fn file_truncated() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("package.package");
+
+    let mut bytes = Vec::new();
+
+    bytes.extend_from_slice(Package::MAGIC_BYTES.as_bytes());
+    bytes.extend_from_slice(&Package::VERSION.to_le_bytes());
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.extend_from_slice(&1u64.to_le_bytes());
+    bytes.extend_from_slice(&[0; 32]);
+    bytes.extend_from_slice(&1u64.to_le_bytes());
+    bytes.push(0);
+    bytes.extend_from_slice(&1u64.to_le_bytes());
+
+    fs::write(&package, bytes).unwrap();
+
+    assert_matches!(
+      Package::load(&package).unwrap_err(),
+      Error::FileLengthMismatch { declared: 1, hash, .. } if hash.as_bytes() == &[0; 32],
+    );
+  }
+
+  #[test]
+  fn trailing_bytes() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("package.package");
+
+    let mut bytes = Vec::new();
+
+    bytes.extend_from_slice(Package::MAGIC_BYTES.as_bytes());
+    bytes.extend_from_slice(&Package::VERSION.to_le_bytes());
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.extend_from_slice(&1u64.to_le_bytes());
+    bytes.extend_from_slice(blake3::hash(&[]).as_bytes());
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.push(0);
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.extend_from_slice(&[0]);
+
+    fs::write(&package, bytes).unwrap();
+
+    assert_matches!(
+      Package::load(&package).unwrap_err(),
+      Error::TrailingBytes { trailing: 1, .. },
+    );
+  }
+
+  #[test]
+  fn manifest_deserialize_error() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("package.package");
+
+    let mut bytes = Vec::new();
+
+    bytes.extend_from_slice(Package::MAGIC_BYTES.as_bytes());
+    bytes.extend_from_slice(&Package::VERSION.to_le_bytes());
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.extend_from_slice(&1u64.to_le_bytes());
+    bytes.extend_from_slice(blake3::hash(&[]).as_bytes());
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.push(0);
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+
+    fs::write(&package, bytes).unwrap();
+
+    assert_matches!(
+      Package::load(&package).unwrap_err(),
+      Error::ManifestInvalid { hash, .. } if hash == blake3::hash(&[]),
+    );
+  }
+
+  #[test]
+  fn manifest_index_points_at_non_manifest() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("package.package");
+
+    let root = tempdir.path_utf8().join("root");
+
+    fs::create_dir(&root).unwrap();
+    fs::write(root.join("0.jpg"), "foo").unwrap();
+
+    let foo = blake3::hash(b"foo");
+
+    Package::save(
+      vec![("0.jpg".into(), (foo, 3))].into_iter().collect(),
+      &Manifest::Comic {
+        content_types: vec!["image/jpeg".into()],
+        dimensions: Vec::new(),
+        pages: vec![foo],
+        provenance: Provenance::default(),
+        thumbnails: Vec::new(),
+      },
+      &package,
+      &root,
+      false,
+    )
+    .unwrap();
+
+    let mut bytes = fs::read(&package).unwrap();
+
+    let offset = Package::MAGIC_BYTES.len() + 2;
+
+    let index = u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+
+    bytes[offset..offset + 8].copy_from_slice(&(1 - index).to_le_bytes());
+
+    fs::write(&package, bytes).unwrap();
+
+    for error in [
+      Package::load(&package).unwrap_err(),
+      Package::verify(&package).unwrap_err(),
+      Package::open(&package).unwrap_err(),
+      Package::map(&package).unwrap_err(),
+    ] {
+      assert_matches!(error, Error::ManifestInvalid { hash, .. } if hash == foo);
+    }
+  }
+
+  #[test]
+  fn save_and_load() {
+    let tempdir = tempdir();
+
+    let output = tempdir.path_utf8().join("package.package");
+
+    let root = tempdir.path_utf8().join("root");
+
+    fs::create_dir(&root).unwrap();
+    fs::write(root.join("index.html"), "html").unwrap();
+    fs::write(root.join("index.js"), "js").unwrap();
+
+    let html = blake3::hash(b"html");
+    let js = blake3::hash(b"js");
+
+    let manifest = Manifest::App {
+      handles: [Type::Comic].into(),
+      headers: BTreeMap::new(),
+      mime_overrides: BTreeMap::new(),
+      paths: vec![("index.html".into(), html), ("index.js".into(), js)]
+        .into_iter()
+        .collect(),
+      provenance: Provenance::default(),
+      redirects: BTreeMap::new(),
+    };
+
+    let manifest_bytes = {
+      let mut buffer = Vec::new();
+      ciborium::into_writer(&manifest, &mut buffer).unwrap();
+      buffer
+    };
+
+    let hashes = vec![
+      ("index.html".into(), (html, 4)),
+      ("index.js".into(), (js, 2)),
+    ]
+    .into_iter()
+    .collect();
+
+    Package::save(hashes, &manifest, &output, &root, false).unwrap();
+
+    assert_eq!(
+      Package::load(&output).unwrap(),
+      Package {
+        files: vec![
+          (html, b"html".into()),
+          (js, b"js".into()),
+          (blake3::hash(&manifest_bytes), manifest_bytes)
+        ]
+        .into_iter()
+        .collect(),
+        fingerprint: None,
+        manifest,
+        manifest_hash: None,
+      },
+    );
+  }
+
+  #[test]
+  fn extract() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+    let destination = tempdir.path_utf8().join("destination");
+
+    fs::create_dir_all(root.join("assets")).unwrap();
+    fs::write(root.join("metadata.yaml"), "type: app\nhandles: comic").unwrap();
+    fs::write(root.join("index.html"), "html").unwrap();
+    fs::write(root.join("assets/index.js"), "js").unwrap();
+
+    subcommand::package::Package {
+      root: vec![root],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    let package = Package::load(&output).unwrap();
+
+    package.extract(&destination).unwrap();
+
+    assert_eq!(fs::read(destination.join("index.html")).unwrap(), b"html");
+    assert_eq!(
+      fs::read(destination.join("assets/index.js")).unwrap(),
+      b"js"
+    );
+    assert_eq!(
+      Metadata::load(&destination.join("metadata.yaml")).unwrap(),
+      Metadata::App {
+        handles: [Type::Comic].into(),
+        headers: BTreeMap::new(),
+        mime_overrides: BTreeMap::new(),
+        redirects: BTreeMap::new(),
+      },
+    );
+
+    let repackaged = tempdir.path_utf8().join("repackaged.package");
+
+    subcommand::package::Package {
+      root: vec![destination],
+      output: repackaged.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    assert_eq!(Package::load(&repackaged).unwrap(), package);
+  }
+
+  #[test]
+  fn contains_and_get() {
+    let foo = blake3::hash(b"foo");
+    let bar = blake3::hash(b"bar");
+
+    let package = Package {
+      files: [(foo, b"foo".to_vec())].into(),
+      fingerprint: None,
+      manifest: Manifest::Comic {
+        content_types: vec!["image/jpeg".into()],
+        dimensions: Vec::new(),
+        pages: vec![foo],
+        provenance: Provenance::default(),
+        thumbnails: Vec::new(),
+      },
+      manifest_hash: None,
+    };
+
+    assert!(package.contains(foo));
+    assert!(!package.contains(bar));
+    assert_eq!(package.get(foo), Some(b"foo".as_slice()));
+    assert_eq!(package.get(bar), None);
+  }
+
+  #[test]
+  fn extract_comic() {
+    let tempdir = tempdir();
+
+    let destination = tempdir.path_utf8().join("destination");
+
+    Package {
+      files: vec![(blake3::hash(b"foo"), b"foo".into())]
+        .into_iter()
+        .collect(),
+      fingerprint: None,
+      manifest: Manifest::Comic {
+        content_types: vec!["image/jpeg".into()],
+        dimensions: Vec::new(),
+        pages: vec![blake3::hash(b"foo")],
+        provenance: Provenance::default(),
+        thumbnails: Vec::new(),
+      },
+      manifest_hash: None,
+    }
+    .extract(&destination)
+    .unwrap();
+
+    assert_eq!(fs::read(destination.join("0.jpg")).unwrap(), b"foo");
+    assert_eq!(
+      Metadata::load(&destination.join("metadata.yaml")).unwrap(),
+      Metadata::Comic,
+    );
+  }
+
+  #[test]
+  fn extract_unsafe_path() {
+    let tempdir = tempdir();
+
+    let destination = tempdir.path_utf8().join("destination");
+
+    assert_matches!(
+      Package {
+        files: vec![(blake3::hash(b"foo"), b"foo".into())]
+          .into_iter()
+          .collect(),
+        fingerprint: None,
+        manifest: Manifest::App {
+          handles: [Type::Comic].into(),
+          headers: BTreeMap::new(),
+          mime_overrides: BTreeMap::new(),
+          paths: vec![("../foo".into(), blake3::hash(b"foo"))]
+            .into_iter()
+            .collect(),
+          provenance: Provenance::default(),
+          redirects: BTreeMap::new(),
+        },
+        manifest_hash: None,
+      }
+      .extract(&destination)
+      .unwrap_err(),
+      Error::PathUnsafe { path, .. }
+      if path == "../foo",
+    );
+
+    assert!(!destination.exists());
+  }
+
+  #[test]
+  fn extract_and_export_missing_payload() {
+    let tempdir = tempdir();
+
+    let destination = tempdir.path_utf8().join("destination");
+    let output = tempdir.path_utf8().join("output.zip");
+
+    let package = Package {
+      files: HashMap::new(),
+      fingerprint: None,
+      manifest: Manifest::Comic {
+        content_types: Vec::new(),
+        dimensions: Vec::new(),
+        pages: vec![blake3::hash(b"foo")],
+        provenance: Provenance::default(),
+        thumbnails: Vec::new(),
+      },
+      manifest_hash: None,
+    };
+
+    assert_matches!(
+      package.extract(&destination).unwrap_err(),
+      Error::ManifestReferencesMissingFile { hash, .. } if hash == blake3::hash(b"foo"),
+    );
+
+    assert!(!destination.exists());
+
+    fs::write(&output, "existing").unwrap();
+
+    assert_matches!(
+      package.export(&output).unwrap_err(),
+      Error::ManifestReferencesMissingFile { hash, .. } if hash == blake3::hash(b"foo"),
+    );
+
+    assert_eq!(fs::read_to_string(&output).unwrap(), "existing");
+
+    assert_eq!(fs::read_dir(tempdir.path()).unwrap().count(), 1);
+  }
+
+  #[test]
+  fn export_replaces_existing_file() {
+    let tempdir = tempdir();
+
+    let output = tempdir.path_utf8().join("output.zip");
+
+    let package = Package {
+      files: [(blake3::hash(b"foo"), b"foo".to_vec())].into(),
+      fingerprint: None,
+      manifest: Manifest::Comic {
+        content_types: Vec::new(),
+        dimensions: Vec::new(),
+        pages: vec![blake3::hash(b"foo")],
+        provenance: Provenance::default(),
+        thumbnails: Vec::new(),
+      },
+      manifest_hash: None,
+    };
+
+    fs::write(&output, "existing").unwrap();
+
+    package.export(&output).unwrap();
+
+    assert_eq!(
+      unzip(&fs::read(&output).unwrap())[0],
+      ("0.jpg".into(), b"foo".to_vec()),
+    );
+
+    assert_eq!(fs::read_dir(tempdir.path()).unwrap().count(), 1);
+  }
+
+  #[test]
+  fn verify() {
+    let tempdir = tempdir();
+
+    let output = tempdir.path_utf8().join("content.package");
+
+    subcommand::package::Package {
+      root: vec!["content/comic".into()],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    assert_eq!(
+      Package::verify(&output).unwrap(),
+      Package::load(&output).unwrap().manifest,
+    );
+  }
+
+  #[test]
+  fn from_reader() {
+    let tempdir = tempdir();
+
+    let output = tempdir.path_utf8().join("content.package");
+
+    subcommand::package::Package {
+      root: vec!["content/comic".into()],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    let package = fs::read(&output).unwrap();
+
+    let mut bytes = b"prefix".to_vec();
+    bytes.extend_from_slice(&package);
+    bytes.extend_from_slice(b"suffix");
+
+    assert_eq!(
+      Package::from_reader((&bytes[6..]).take(package.len().into_u64())).unwrap(),
+      Package::load(&output).unwrap(),
+    );
+
+    assert_matches!(
+      Package::from_reader(&bytes[6..]).unwrap_err(),
+      Error::TrailingBytes { trailing: 6, .. },
+    );
+  }
+
+  #[cfg(feature = "reqwest")]
+  #[test]
+  fn load_from_url() {
+    use std::{net::TcpListener, thread};
+
+    let tempdir = tempdir();
+
+    let output = tempdir.path_utf8().join("content.package");
+
+    subcommand::package::Package {
+      root: vec!["content/comic".into()],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    let package = fs::read(&output).unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+    let port = listener.local_addr().unwrap().port();
+
+    let server = thread::spawn(move || {
+      for (status, body) in [("200 OK", package), ("404 Not Found", Vec::new())] {
+        let (mut stream, _address) = listener.accept().unwrap();
+        let mut request = [0; 1024];
+        let _ = stream.read(&mut request).unwrap();
+        write!(
+          stream,
+          "HTTP/1.1 {status}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+          body.len(),
+        )
+        .unwrap();
+        stream.write_all(&body).unwrap();
+      }
+    });
+
+    let url = format!("http://127.0.0.1:{port}/content.package");
+
+    assert_eq!(
+      Package::load_from_url(&url).unwrap(),
+      Package::load(&output).unwrap(),
+    );
+
+    assert_matches!(
+      Package::load_from_url(&url).unwrap_err(),
+      Error::Fetch { url: actual, .. } if actual == url,
+    );
+
+    server.join().unwrap();
+  }
+
+  #[test]
+  fn file_too_large() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("package.package");
+
+    let mut bytes = Vec::new();
+
+    bytes.extend_from_slice(Package::MAGIC_BYTES.as_bytes());
+    bytes.extend_from_slice(&Package::VERSION.to_le_bytes());
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.extend_from_slice(&1u64.to_le_bytes());
+    bytes.extend_from_slice(blake3::hash(b"foo").as_bytes());
+    bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+    bytes.push(0);
+    bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+
+    fs::write(&package, bytes).unwrap();
+
+    assert_matches!(
+      Package::load(&package).unwrap_err(),
+      Error::FileTooLarge {
+        len: u64::MAX,
+        limit: LoadOptions::DEFAULT_MAX_FILE_LEN,
+        ..
+      },
+    );
+
+    assert_matches!(
+      Package::open(&package).unwrap_err(),
+      Error::FileTooLarge {
+        len: u64::MAX,
+        limit: LoadOptions::DEFAULT_MAX_FILE_LEN,
+        ..
+      },
+    );
+
+    assert_matches!(
+      Package::map(&package).unwrap_err(),
+      Error::FileTooLarge {
+        len: u64::MAX,
+        limit: LoadOptions::DEFAULT_MAX_FILE_LEN,
+        ..
+      },
+    );
+
+    assert_matches!(
+      Package::load_with_options(
+        &package,
+        LoadOptions {
+          max_file_len: 2,
+          ..Default::default()
+        }
+      )
+      .unwrap_err(),
+      Error::FileTooLarge {
+        len: u64::MAX,
+        limit: 2,
+        ..
+      },
+    );
+  }
+
+  #[test]
+  fn validate_bytes_rejects_decompression_bomb() {
+    let stored = zstd::encode_all(b"foo".as_slice(), 0).unwrap();
+
+    let mut bytes = Vec::new();
+
+    bytes.extend_from_slice(Package::MAGIC_BYTES.as_bytes());
+    bytes.extend_from_slice(&Package::VERSION.to_le_bytes());
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.extend_from_slice(&1u64.to_le_bytes());
+    bytes.extend_from_slice(blake3::hash(b"foo").as_bytes());
+    bytes.extend_from_slice(&LoadOptions::DEFAULT_MAX_FILE_LEN.to_le_bytes());
+    bytes.push(1);
+    bytes.extend_from_slice(&stored.len().into_u64().to_le_bytes());
+    bytes.extend_from_slice(&stored);
+
+    assert_matches!(
+      Package::validate_bytes(&bytes).unwrap_err(),
+      Error::FileTooLarge {
+        len: LoadOptions::DEFAULT_MAX_FILE_LEN,
+        limit,
+        ..
+      }
+      if limit == LoadOptions::VALIDATE.max_file_len,
+    );
+  }
+
+  #[test]
+  fn load_with_options() {
+    let tempdir = tempdir();
+
+    let output = tempdir.path_utf8().join("content.package");
+
+    subcommand::package::Package {
+      root: vec!["content/comic".into()],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    let package = Package::load(&output).unwrap();
+
+    let count = package.files.len().into_u64();
+
+    let total = package
+      .files
+      .values()
+      .map(|file| file.len().into_u64())
+      .sum::<u64>();
+
+    assert_eq!(
+      Package::load_with_options(
+        &output,
+        LoadOptions {
+          max_file_count: count,
+          max_total_len: total,
+          ..Default::default()
+        }
+      )
+      .unwrap(),
+      package,
+    );
+
+    assert_matches!(
+      Package::load_with_options(
+        &output,
+        LoadOptions {
+          max_file_count: count - 1,
+          ..Default::default()
+        }
+      )
+      .unwrap_err(),
+      Error::FileCountTooLarge { count: c, limit, .. } if c == count && limit == count - 1,
+    );
+
+    assert_matches!(
+      Package::load_with_options(
+        &output,
+        LoadOptions {
+          max_total_len: total - 1,
+          ..Default::default()
+        }
+      )
+      .unwrap_err(),
+      Error::TotalLengthTooLarge { len, limit, .. } if len == total && limit == total - 1,
+    );
+  }
+
+  #[test]
+  fn fingerprint() {
+    let tempdir = tempdir();
+
+    let output = tempdir.path_utf8().join("content.package");
+
+    subcommand::package::Package {
+      root: vec!["content/comic".into()],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    assert_eq!(
+      Package::fingerprint(&output).unwrap(),
+      blake3::hash(&fs::read(&output).unwrap()),
+    );
+  }
+
+  #[test]
+  fn delta() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+
+    fs::create_dir(&root).unwrap();
+    fs::write(root.join("0.jpg"), "foo").unwrap();
+    fs::write(root.join("1.jpg"), "bar").unwrap();
+
+    let foo = blake3::hash(b"foo");
+    let bar = blake3::hash(b"bar");
+    let baz = blake3::hash(b"baz");
+
+    let base = tempdir.path_utf8().join("base.package");
+
+    Package::save(
+      [("0.jpg".into(), (foo, 3)), ("1.jpg".into(), (bar, 3))].into(),
+      &Manifest::Comic {
+        content_types: vec!["image/jpeg".into(); 2],
+        dimensions: Vec::new(),
+        pages: vec![foo, bar],
+        provenance: Provenance::default(),
+        thumbnails: Vec::new(),
+      },
+      &base,
+      &root,
+      false,
+    )
+    .unwrap();
+
+    let base_fingerprint = Package::fingerprint(&base).unwrap();
+
+    let base = Package::load(&base).unwrap();
+
+    fs::write(root.join("1.jpg"), "baz").unwrap();
+
+    let manifest = Manifest::Comic {
+      content_types: vec!["image/jpeg".into(); 2],
+      dimensions: Vec::new(),
+      pages: vec![foo, baz],
+      provenance: Provenance::default(),
+      thumbnails: Vec::new(),
+    };
+
+    let delta = tempdir.path_utf8().join("delta.package");
+
+    Package::save_delta(
+      &base,
+      [("0.jpg".into(), (foo, 3)), ("1.jpg".into(), (baz, 3))].into(),
+      &manifest,
+      &delta,
+      &root,
+      false,
+    )
+    .unwrap();
+
+    let bytes = fs::read(&delta).unwrap();
+    assert!(!bytes.windows(3).any(|window| window == b"foo"));
+    assert!(bytes.windows(3).any(|window| window == b"baz"));
+
+    let header = Package::MAGIC_BYTES.len() + 2;
+    assert_eq!(
+      &bytes[header..header + blake3::OUT_LEN],
+      base_fingerprint.as_bytes(),
+    );
+
+    let package = Package::load_delta(&delta, &base).unwrap();
+
+    assert_eq!(package.manifest, manifest);
+    assert_eq!(package.files[&foo], b"foo");
+    assert_eq!(package.files[&baz], b"baz");
+    assert!(!package.files.contains_key(&bar));
 
     assert_matches!(
-      Package::load(&package).unwrap_err(),
-      Error::FileHashInvalid { actual, expected, .. }
-      if actual == blake3::hash(&[]) && expected.as_bytes() == &[0; 32],
+      Package::load_delta(&delta, &package).unwrap_err(),
+      Error::BaseMismatch { actual, expected, .. }
+      if actual == base_fingerprint && expected == Package::fingerprint(&delta).unwrap(),
+    );
+
+    assert_matches!(
+      Package::load(&delta).unwrap_err(),
+      Error::UnsupportedVersion {
+        found: Package::DELTA_VERSION,
+        supported: Package::VERSION,
+        ..
+      },
     );
   }
 
   #[test]
-  fn file_truncated() {
+  fn delta_base_with_legacy_manifest() {
     let tempdir = tempdir();
 
-    let package = tempdir.path_utf8().join("package.package");
+    let root = tempdir.path_utf8().join("root");
+
+    fs::create_dir(&root).unwrap();
+    fs::write(root.join("0.jpg"), "foo").unwrap();
+    fs::write(root.join("1.jpg"), "bar").unwrap();
+
+    let foo = blake3::hash(b"foo");
+    let bar = blake3::hash(b"bar");
+
+    let manifest = Manifest::Comic {
+      content_types: vec!["image/jpeg".into()],
+      dimensions: Vec::new(),
+      pages: vec![foo],
+      provenance: Provenance::default(),
+      thumbnails: Vec::new(),
+    };
+
+    let legacy = {
+      let ciborium::Value::Map(mut map) = ciborium::Value::serialized(&manifest).unwrap() else {
+        panic!("manifest is not a map");
+      };
+      map.retain(|(key, _value)| key.as_text() != Some("schema"));
+      let mut buffer = Vec::new();
+      ciborium::into_writer(&ciborium::Value::Map(map), &mut buffer).unwrap();
+      buffer
+    };
+
+    let legacy_hash = blake3::hash(&legacy);
+
+    let mut files = vec![(foo, b"foo".to_vec()), (legacy_hash, legacy)];
+
+    files.sort_by_key(|(hash, _content)| *hash.as_bytes());
+
+    let mut bytes = Vec::new();
+
+    bytes.extend_from_slice(Package::MAGIC_BYTES.as_bytes());
+    bytes.extend_from_slice(&Package::VERSION.to_le_bytes());
+    bytes
+      .write_u64(
+        files
+          .iter()
+          .position(|(hash, _content)| *hash == legacy_hash)
+          .unwrap()
+          .into_u64(),
+      )
+      .unwrap();
+    bytes.write_u64(files.len().into_u64()).unwrap();
+
+    for (hash, content) in &files {
+      Entry {
+        compressed: false,
+        hash: *hash,
+        len: content.len().into_u64(),
+        stored: content.len().into_u64(),
+      }
+      .write(&mut bytes)
+      .unwrap();
+    }
+
+    for (_hash, content) in &files {
+      bytes.extend_from_slice(content);
+    }
+
+    let base = tempdir.path_utf8().join("base.package");
+
+    fs::write(&base, bytes).unwrap();
+
+    let base = Package::load(&base).unwrap();
+
+    assert_eq!(base.manifest, manifest);
+    assert_eq!(base.manifest_hash(), legacy_hash);
+
+    let delta = tempdir.path_utf8().join("delta.package");
+
+    let manifest = Manifest::Comic {
+      content_types: vec!["image/jpeg".into(); 2],
+      dimensions: Vec::new(),
+      pages: vec![foo, bar],
+      provenance: Provenance::default(),
+      thumbnails: Vec::new(),
+    };
+
+    Package::save_delta(
+      &base,
+      [("0.jpg".into(), (foo, 3)), ("1.jpg".into(), (bar, 3))].into(),
+      &manifest,
+      &delta,
+      &root,
+      false,
+    )
+    .unwrap();
+
+    assert_eq!(
+      Package::load_delta(&delta, &base).unwrap().manifest,
+      manifest
+    );
+  }
+
+  #[test]
+  fn file_missing_payload() {
+    let package = Package {
+      files: HashMap::new(),
+      fingerprint: None,
+      manifest: Manifest::Comic {
+        content_types: Vec::new(),
+        dimensions: Vec::new(),
+        pages: vec![blake3::hash(b"foo")],
+        provenance: Provenance::default(),
+        thumbnails: Vec::new(),
+      },
+      manifest_hash: None,
+    };
+
+    assert_eq!(package.file("0"), None);
+  }
+
+  #[test]
+  fn file_comic_page_content_type() {
+    let png = b"\x89PNG\r\n\x1a\n".to_vec();
+
+    let package = Package {
+      files: [
+        (blake3::hash(b"foo"), b"foo".to_vec()),
+        (blake3::hash(&png), png.clone()),
+      ]
+      .into(),
+      fingerprint: None,
+      manifest: Manifest::Comic {
+        content_types: vec!["image/avif".into(), "image/jpeg".into()],
+        dimensions: Vec::new(),
+        pages: vec![blake3::hash(b"foo"), blake3::hash(&png)],
+        provenance: Provenance::default(),
+        thumbnails: Vec::new(),
+      },
+      manifest_hash: None,
+    };
+
+    assert_eq!(
+      package.file("0"),
+      Some(("image/avif".parse().unwrap(), b"foo".to_vec())),
+    );
+
+    assert_eq!(package.file("1"), Some((mime::IMAGE_PNG, png)));
+  }
+
+  #[test]
+  fn build() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+
+    fs::create_dir(&root).unwrap();
+    fs::write(root.join("0.jpg"), "foo").unwrap();
+    fs::write(root.join("1.png"), "bar").unwrap();
+
+    let template = || Template::Comic {
+      dimensions: Vec::new(),
+      pages: vec!["0.jpg".into(), "1.png".into()],
+    };
+
+    let bytes = Package::build(
+      [
+        ("0.jpg".into(), b"foo".to_vec()),
+        ("1.png".into(), b"bar".to_vec()),
+      ]
+      .into(),
+      template(),
+    )
+    .unwrap();
+
+    let hashes: HashMap<Utf8PathBuf, (Hash, u64)> = [
+      ("0.jpg".into(), (blake3::hash(b"foo"), 3)),
+      ("1.png".into(), (blake3::hash(b"bar"), 3)),
+    ]
+    .into();
+
+    let output = tempdir.path_utf8().join("package.package");
+
+    let manifest = template().manifest(&hashes);
+
+    Package::save(hashes, &manifest, &output, &root, false).unwrap();
+
+    assert_eq!(bytes, fs::read(&output).unwrap());
+
+    let package = Package::parse(&bytes).unwrap();
+
+    assert_eq!(
+      package.file("1").unwrap(),
+      (mime::IMAGE_PNG, b"bar".to_vec())
+    );
+
+    assert_matches!(
+      Package::build([("0.jpg".into(), b"foo".to_vec())].into(), template()).unwrap_err(),
+      Error::TemplateFileMissing { path, .. } if path == "1.png",
+    );
+  }
+
+  #[test]
+  fn manifest_json() {
+    let package = Package::parse(
+      &Package::build(
+        [
+          ("index.html".into(), b"foo".to_vec()),
+          ("index.js".into(), b"bar".to_vec()),
+        ]
+        .into(),
+        Template::App {
+          handles: [Type::Comic].into(),
+          headers: BTreeMap::new(),
+          mime_overrides: BTreeMap::new(),
+          redirects: BTreeMap::new(),
+        },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+
+    let json = package.manifest_json();
+
+    assert_eq!(json, package.manifest_json());
+    assert!(
+      json.starts_with(br#"{"schema":1,"type":"app","handles":"comic","paths":{"index.html":"#)
+    );
+    assert_eq!(
+      serde_json::from_slice::<Manifest>(&json).unwrap(),
+      package.manifest,
+    );
+  }
+
+  #[test]
+  fn validate_bytes() {
+    let bytes = Package::build(
+      [
+        ("index.html".into(), b"foo".to_vec()),
+        ("index.js".into(), b"bar".to_vec()),
+      ]
+      .into(),
+      Template::App {
+        handles: [Type::Comic].into(),
+        headers: BTreeMap::new(),
+        mime_overrides: BTreeMap::new(),
+        redirects: BTreeMap::new(),
+      },
+    )
+    .unwrap();
+
+    assert!(Package::validate_bytes(&bytes).is_ok());
+
+    for len in 0..bytes.len() {
+      assert!(Package::validate_bytes(&bytes[..len]).is_err(), "{len}");
+    }
+
+    for i in 0..bytes.len() {
+      for mask in [0x01, 0x80, 0xFF] {
+        let mut bytes = bytes.clone();
+        bytes[i] ^= mask;
+        Package::validate_bytes(&bytes).ok();
+      }
+    }
 
     let mut bytes = Vec::new();
 
     bytes.extend_from_slice(Package::MAGIC_BYTES.as_bytes());
+    bytes.extend_from_slice(&Package::VERSION.to_le_bytes());
     bytes.extend_from_slice(&0u64.to_le_bytes());
     bytes.extend_from_slice(&1u64.to_le_bytes());
     bytes.extend_from_slice(&[0; 32]);
-    bytes.extend_from_slice(&1u64.to_le_bytes());
+    bytes.extend_from_slice(&u64::from(u32::MAX).to_le_bytes());
+    bytes.push(1);
+    bytes.extend_from_slice(&u64::from(u32::MAX).to_le_bytes());
 
-    fs::write(&package, bytes).unwrap();
+    assert_matches!(
+      Package::validate_bytes(&bytes).unwrap_err(),
+      Error::FileTooLarge { len, .. } if len == u64::from(u32::MAX),
+    );
+  }
+
+  #[test]
+  fn manifest_file_mismatch() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+
+    fs::create_dir(&root).unwrap();
+    fs::write(root.join("0.jpg"), "foo").unwrap();
+    fs::write(root.join("1.jpg"), "bar").unwrap();
+
+    let foo = blake3::hash(b"foo");
+    let bar = blake3::hash(b"bar");
+
+    let output = tempdir.path_utf8().join("package.package");
+
+    Package::save(
+      [("0.jpg".into(), (foo, 3)), ("1.jpg".into(), (bar, 3))].into(),
+      &Manifest::Comic {
+        content_types: Vec::new(),
+        dimensions: Vec::new(),
+        pages: vec![foo],
+        provenance: Provenance::default(),
+        thumbnails: Vec::new(),
+      },
+      &output,
+      &root,
+      false,
+    )
+    .unwrap();
 
     assert_matches!(
-      Package::load(&package).unwrap_err(),
-      Error::Io { source, .. }
-      if source.kind() == io::ErrorKind::UnexpectedEof,
+      Package::load(&output).unwrap_err(),
+      Error::ManifestExtraFiles { extra: 1, .. },
+    );
+
+    assert_matches!(
+      Package::verify(&output).unwrap_err(),
+      Error::ManifestExtraFiles { extra: 1, .. },
+    );
+
+    Package::save(
+      [("0.jpg".into(), (foo, 3))].into(),
+      &Manifest::Comic {
+        content_types: Vec::new(),
+        dimensions: Vec::new(),
+        pages: vec![foo, bar],
+        provenance: Provenance::default(),
+        thumbnails: Vec::new(),
+      },
+      &output,
+      &root,
+      false,
+    )
+    .unwrap();
+
+    assert_matches!(
+      Package::load(&output).unwrap_err(),
+      Error::ManifestReferencesMissingFile { hash, .. } if hash == bar,
+    );
+
+    assert_matches!(
+      Package::verify(&output).unwrap_err(),
+      Error::ManifestReferencesMissingFile { hash, .. } if hash == bar,
     );
   }
 
   #[test]
-  fn trailing_bytes() {
+  fn file_length_exceeds_package() {
     let tempdir = tempdir();
 
     let package = tempdir.path_utf8().join("package.package");
@@ -419,22 +2437,56 @@ mod tests {
     let mut bytes = Vec::new();
 
     bytes.extend_from_slice(Package::MAGIC_BYTES.as_bytes());
+    bytes.extend_from_slice(&Package::VERSION.to_le_bytes());
     bytes.extend_from_slice(&0u64.to_le_bytes());
     bytes.extend_from_slice(&1u64.to_le_bytes());
-    bytes.extend_from_slice(blake3::hash(&[]).as_bytes());
+    bytes.extend_from_slice(blake3::hash(b"foo").as_bytes());
+    bytes.extend_from_slice(&100u64.to_le_bytes());
+    bytes.push(0);
+    bytes.extend_from_slice(&100u64.to_le_bytes());
+    bytes.extend_from_slice(b"foo");
+
+    fs::write(&package, bytes).unwrap();
+
+    for err in [
+      Package::load(&package).unwrap_err(),
+      Package::verify(&package).unwrap_err(),
+      Package::map(&package).unwrap_err(),
+    ] {
+      assert_matches!(
+        err,
+        Error::FileLengthMismatch { declared: 100, hash, .. } if hash == blake3::hash(b"foo"),
+      );
+    }
+  }
+
+  #[test]
+  fn verify_file_truncated() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("package.package");
+
+    let mut bytes = Vec::new();
+
+    bytes.extend_from_slice(Package::MAGIC_BYTES.as_bytes());
+    bytes.extend_from_slice(&Package::VERSION.to_le_bytes());
     bytes.extend_from_slice(&0u64.to_le_bytes());
-    bytes.extend_from_slice(&[0]);
+    bytes.extend_from_slice(&1u64.to_le_bytes());
+    bytes.extend_from_slice(&[0; 32]);
+    bytes.extend_from_slice(&1u64.to_le_bytes());
+    bytes.push(0);
+    bytes.extend_from_slice(&1u64.to_le_bytes());
 
     fs::write(&package, bytes).unwrap();
 
     assert_matches!(
-      Package::load(&package).unwrap_err(),
-      Error::TrailingBytes { trailing: 1, .. },
+      Package::verify(&package).unwrap_err(),
+      Error::FileLengthMismatch { declared: 1, hash, .. } if hash.as_bytes() == &[0; 32],
     );
   }
 
   #[test]
-  fn manifest_deserialize_error() {
+  fn verify_file_hash_invalid() {
     let tempdir = tempdir();
 
     let package = tempdir.path_utf8().join("package.package");
@@ -442,68 +2494,185 @@ mod tests {
     let mut bytes = Vec::new();
 
     bytes.extend_from_slice(Package::MAGIC_BYTES.as_bytes());
+    bytes.extend_from_slice(&Package::VERSION.to_le_bytes());
     bytes.extend_from_slice(&0u64.to_le_bytes());
     bytes.extend_from_slice(&1u64.to_le_bytes());
-    bytes.extend_from_slice(blake3::hash(&[]).as_bytes());
+    bytes.extend_from_slice(&[0; 32]);
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.push(0);
     bytes.extend_from_slice(&0u64.to_le_bytes());
 
     fs::write(&package, bytes).unwrap();
 
     assert_matches!(
-      Package::load(&package).unwrap_err(),
-      Error::DeserializeManifest { .. },
+      Package::verify(&package).unwrap_err(),
+      Error::FileHashInvalid { actual, expected, .. }
+      if actual == blake3::hash(&[]) && expected.as_bytes() == &[0; 32],
     );
   }
 
   #[test]
-  fn save_and_load() {
+  fn unsupported_version() {
     let tempdir = tempdir();
 
-    let output = tempdir.path_utf8().join("package.package");
+    let package = tempdir.path_utf8().join("package.package");
+
+    for version in [0, Package::VERSION + 1] {
+      let mut bytes = Vec::new();
+
+      bytes.extend_from_slice(Package::MAGIC_BYTES.as_bytes());
+      bytes.extend_from_slice(&version.to_le_bytes());
+      bytes.extend_from_slice(&0u64.to_le_bytes());
+      bytes.extend_from_slice(&0u64.to_le_bytes());
+
+      fs::write(&package, &bytes).unwrap();
+
+      assert_matches!(
+        Package::load(&package).unwrap_err(),
+        Error::UnsupportedVersion { found, supported, .. }
+        if found == version && supported == Package::VERSION,
+      );
+
+      assert_matches!(
+        Package::parse(&bytes).unwrap_err(),
+        Error::UnsupportedVersion { found, .. } if found == version,
+      );
+
+      assert_matches!(
+        Package::open(&package).unwrap_err(),
+        Error::UnsupportedVersion { found, .. } if found == version,
+      );
+
+      assert_matches!(
+        Package::verify(&package).unwrap_err(),
+        Error::UnsupportedVersion { found, .. } if found == version,
+      );
+    }
+  }
+
+  #[test]
+  fn compressed() {
+    let tempdir = tempdir();
 
     let root = tempdir.path_utf8().join("root");
+    let compressed = tempdir.path_utf8().join("compressed.package");
+    let uncompressed = tempdir.path_utf8().join("uncompressed.package");
+
+    let js = "let x = 0;\n".repeat(1000);
 
     fs::create_dir(&root).unwrap();
+    fs::write(root.join("metadata.yaml"), "type: app\nhandles: comic").unwrap();
     fs::write(root.join("index.html"), "html").unwrap();
-    fs::write(root.join("index.js"), "js").unwrap();
+    fs::write(root.join("index.js"), &js).unwrap();
 
-    let html = blake3::hash(b"html");
-    let js = blake3::hash(b"js");
+    subcommand::package::Package {
+      root: vec![root.clone()],
+      output: uncompressed.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    subcommand::package::Package {
+      root: vec![root],
+      output: compressed.clone(),
+      compress: true,
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
 
-    let manifest = Manifest::App {
-      handles: Type::Comic,
-      paths: vec![("index.html".into(), html), ("index.js".into(), js)]
-        .into_iter()
-        .collect(),
-    };
+    assert!(fs::metadata(&compressed).unwrap().len() < fs::metadata(&uncompressed).unwrap().len());
 
-    let manifest_bytes = {
-      let mut buffer = Vec::new();
-      ciborium::into_writer(&manifest, &mut buffer).unwrap();
-      buffer
+    let package = Package::load(&compressed).unwrap();
+
+    assert_eq!(package, Package::load(&uncompressed).unwrap());
+
+    assert_eq!(Package::verify(&compressed).unwrap(), package.manifest);
+
+    let reader = Package::open(&compressed).unwrap();
+
+    assert_eq!(reader.file("index.js").unwrap().unwrap().1, js.as_bytes(),);
+
+    assert_eq!(
+      reader.len(blake3::hash(js.as_bytes())),
+      Some(js.len().into_u64()),
+    );
+  }
+
+  #[test]
+  fn signed() {
+    let tempdir = tempdir();
+
+    let output = tempdir.path_utf8().join("package.package");
+
+    let root = tempdir.path_utf8().join("root");
+
+    fs::create_dir(&root).unwrap();
+    fs::write(root.join("0.jpg"), "foo").unwrap();
+
+    let foo = blake3::hash(b"foo");
+
+    let manifest = Manifest::Comic {
+      content_types: vec!["image/jpeg".into()],
+      dimensions: Vec::new(),
+      pages: vec![foo],
+      provenance: Provenance::default(),
+      thumbnails: Vec::new(),
     };
 
-    let hashes = vec![
-      ("index.html".into(), (html, 4)),
-      ("index.js".into(), (js, 2)),
-    ]
-    .into_iter()
-    .collect();
+    let hashes = vec![("0.jpg".into(), (foo, 3))].into_iter().collect();
+
+    let signing_key = SigningKey::from_bytes(&[1; 32]);
+
+    Package::save_signed(hashes, &manifest, &output, &root, false, &signing_key).unwrap();
+
+    let package = Package::load_verified(&output, &signing_key.verifying_key()).unwrap();
 
-    Package::save(hashes, &manifest, &output, &root).unwrap();
+    assert_eq!(package.manifest, manifest);
 
     assert_eq!(
-      Package::load(&output).unwrap(),
-      Package {
-        files: vec![
-          (html, b"html".into()),
-          (js, b"js".into()),
-          (blake3::hash(&manifest_bytes), manifest_bytes)
-        ]
-        .into_iter()
-        .collect(),
-        manifest,
-      },
+      package.base_fingerprint(),
+      Package::fingerprint(&output).unwrap(),
+    );
+
+    assert_matches!(
+      Package::load_verified(&output, &SigningKey::from_bytes(&[2; 32]).verifying_key())
+        .unwrap_err(),
+      Error::SignatureInvalid { .. },
+    );
+
+    assert_matches!(
+      Package::load(&output).unwrap_err(),
+      Error::TrailingBytes { trailing: 64, .. },
+    );
+
+    let mut bytes = fs::read(&output).unwrap();
+    let offset = bytes
+      .windows(3)
+      .position(|window| window == b"foo")
+      .unwrap();
+    bytes[offset] = b'g';
+    fs::write(&output, bytes).unwrap();
+
+    assert_matches!(
+      Package::load_verified(&output, &signing_key.verifying_key()).unwrap_err(),
+      Error::SignatureInvalid { .. },
+    );
+  }
+
+  #[test]
+  fn signature_missing() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("package.package");
+
+    fs::write(&package, "MEDIA").unwrap();
+
+    assert_matches!(
+      Package::load_verified(&package, &SigningKey::from_bytes(&[1; 32]).verifying_key())
+        .unwrap_err(),
+      Error::SignatureMissing { .. },
     );
   }
 }
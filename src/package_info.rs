@@ -0,0 +1,19 @@
+use super::*;
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PackageInfo {
+  #[serde(rename = "type")]
+  pub ty: Type,
+  pub files: u64,
+  pub size: u64,
+  pub manifest: String,
+  #[serde(flatten)]
+  pub provenance: Provenance,
+  pub entries: Vec<EntryInfo>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct EntryInfo {
+  pub hash: String,
+  pub len: u64,
+}
@@ -0,0 +1,416 @@
+use super::*;
+
+#[derive(Debug)]
+pub struct PackageReader {
+  cache: Option<Mutex<FileCache>>,
+  file: Mutex<BufReader<File>>,
+  files: HashMap<Hash, (u64, Entry)>,
+  manifest: Manifest,
+  manifest_hash: Hash,
+  verified: Mutex<HashSet<Hash>>,
+}
+
+impl PackageReader {
+  pub fn open(path: &Utf8Path) -> Result<Self, package::Error> {
+    let file = File::open(path)?;
+
+    let len = file.metadata()?.len();
+
+    let mut file = BufReader::new(file);
+
+    let (manifest_hash, files) = Package::read_index(&mut file, len)?;
+
+    let (offset, entry) = files[&manifest_hash];
+
+    let mut buffer = vec![0; entry.stored as usize];
+
+    file.seek(SeekFrom::Start(offset))?;
+
+    file.read_exact(&mut buffer)?;
+
+    let manifest = Package::decode_manifest(manifest_hash, entry, buffer, &files)?;
+
+    Ok(Self {
+      cache: None,
+      file: Mutex::new(file),
+      files,
+      manifest,
+      manifest_hash,
+      verified: Mutex::new([manifest_hash].into()),
+    })
+  }
+
+  pub fn file(&self, path: &str) -> Result<Option<(Mime, Bytes)>, package::Error> {
+    Ok(
+      self
+        .manifest
+        .content(path, |hash| self.read_file(hash))?
+        .map(|(content_type, _hash, content)| (content_type, content)),
+    )
+  }
+
+  pub fn info(&self) -> PackageInfo {
+    let mut entries = self
+      .files
+      .values()
+      .map(|(_offset, entry)| *entry)
+      .collect::<Vec<Entry>>();
+
+    entries.sort_by_key(|entry| *entry.hash.as_bytes());
+
+    PackageInfo {
+      ty: self.manifest.ty(),
+      files: entries.len().into_u64(),
+      size: entries.iter().map(|entry| entry.len).sum(),
+      manifest: self.manifest_hash.to_hex().to_string(),
+      provenance: self.manifest.provenance().clone(),
+      entries: entries
+        .into_iter()
+        .map(|entry| EntryInfo {
+          hash: entry.hash.to_hex().to_string(),
+          len: entry.len,
+        })
+        .collect(),
+    }
+  }
+
+  pub fn len(&self, hash: Hash) -> Option<u64> {
+    self.files.get(&hash).map(|(_offset, entry)| entry.len)
+  }
+
+  pub fn manifest(&self) -> &Manifest {
+    &self.manifest
+  }
+
+  pub fn read_file(&self, hash: Hash) -> Result<Option<Bytes>, package::Error> {
+    let Some(&(offset, entry)) = self.files.get(&hash) else {
+      return Ok(None);
+    };
+
+    if let Some(cache) = &self.cache {
+      if let Some(content) = cache.lock().unwrap().get(hash) {
+        return Ok(Some(content));
+      }
+    }
+
+    let mut buffer = vec![0; entry.stored as usize];
+
+    {
+      let mut file = self.file.lock().unwrap();
+      file.seek(SeekFrom::Start(offset))?;
+      file.read_exact(&mut buffer)?;
+    }
+
+    let buffer = Bytes::from(entry.decode(buffer)?);
+
+    let verified = self.verified.lock().unwrap().contains(&hash);
+
+    if !verified {
+      let actual = blake3::hash(&buffer);
+
+      ensure!(
+        actual == hash,
+        package::FileHashInvalid {
+          expected: hash,
+          actual,
+        }
+      );
+
+      self.verified.lock().unwrap().insert(hash);
+    }
+
+    if let Some(cache) = &self.cache {
+      cache.lock().unwrap().insert(hash, buffer.clone());
+    }
+
+    Ok(Some(buffer))
+  }
+
+  pub fn with_cache(self, budget: u64) -> Self {
+    Self {
+      cache: Some(Mutex::new(FileCache::new(budget))),
+      ..self
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn len() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("0.jpg"), jpeg(b"foo")).unwrap();
+    fs::write(root.join("1.jpg"), jpeg(b"quux")).unwrap();
+
+    subcommand::package::Package {
+      root: vec![root],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    let reader = Package::open(&output).unwrap();
+
+    assert_eq!(
+      reader.len(blake3::hash(&jpeg(b"foo"))),
+      Some(jpeg(b"foo").len().into_u64()),
+    );
+    assert_eq!(
+      reader.len(blake3::hash(&jpeg(b"quux"))),
+      Some(jpeg(b"quux").len().into_u64()),
+    );
+    assert_eq!(reader.len(blake3::hash(&jpeg(b"bar"))), None);
+
+    assert_eq!(
+      reader.manifest(),
+      &Manifest::Comic {
+        content_types: vec!["image/jpeg".into(), "image/jpeg".into()],
+        dimensions: vec![(1, 1), (1, 1)],
+        pages: vec![blake3::hash(&jpeg(b"foo")), blake3::hash(&jpeg(b"quux"))],
+        provenance: Provenance::default(),
+        thumbnails: Vec::new(),
+      },
+    );
+  }
+
+  #[test]
+  fn info() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("0.jpg"), jpeg(b"foo")).unwrap();
+    fs::write(root.join("1.jpg"), jpeg(b"quux")).unwrap();
+
+    subcommand::package::Package {
+      root: vec![root],
+      output: output.clone(),
+      compress: true,
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    let package = Package::load(&output).unwrap();
+
+    let manifest = package
+      .files
+      .keys()
+      .copied()
+      .find(|hash| *hash != blake3::hash(&jpeg(b"foo")) && *hash != blake3::hash(&jpeg(b"quux")))
+      .unwrap();
+
+    let mut entries = package
+      .files
+      .iter()
+      .map(|(hash, content)| EntryInfo {
+        hash: hash.to_hex().to_string(),
+        len: content.len().into_u64(),
+      })
+      .collect::<Vec<EntryInfo>>();
+
+    entries.sort_by(|a, b| a.hash.cmp(&b.hash));
+
+    assert_eq!(
+      Package::open(&output).unwrap().info(),
+      PackageInfo {
+        ty: Type::Comic,
+        files: 3,
+        size: (jpeg(b"foo").len() + jpeg(b"quux").len() + package.files[&manifest].len())
+          .into_u64(),
+        manifest: manifest.to_hex().to_string(),
+        entries,
+        provenance: Provenance::default(),
+      },
+    );
+  }
+
+  #[test]
+  fn read_file_verifies_hash() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("0.jpg"), jpeg(b"foo")).unwrap();
+    fs::write(root.join("1.jpg"), jpeg(b"bar")).unwrap();
+
+    subcommand::package::Package {
+      root: vec![root],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    let mut bytes = fs::read(&output).unwrap();
+    let offset = bytes
+      .windows(3)
+      .position(|window| window == b"foo")
+      .unwrap();
+    bytes[offset] = b'g';
+    fs::write(&output, bytes).unwrap();
+
+    let reader = Package::open(&output).unwrap();
+
+    assert_eq!(
+      reader
+        .read_file(blake3::hash(&jpeg(b"bar")))
+        .unwrap()
+        .unwrap(),
+      jpeg(b"bar"),
+    );
+
+    assert_eq!(reader.read_file(blake3::hash(&jpeg(b"baz"))).unwrap(), None);
+
+    assert_matches!(
+      reader.read_file(blake3::hash(&jpeg(b"foo"))).unwrap_err(),
+      package::Error::FileHashInvalid { expected, actual, .. }
+      if expected == blake3::hash(&jpeg(b"foo")) && actual == blake3::hash(&jpeg(b"goo")),
+    );
+  }
+
+  #[test]
+  fn read_file_uses_cache() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("0.jpg"), jpeg(b"foo")).unwrap();
+    fs::write(root.join("1.jpg"), jpeg(b"bar")).unwrap();
+
+    subcommand::package::Package {
+      root: vec![root],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    let reader = Package::open(&output)
+      .unwrap()
+      .with_cache(jpeg(b"foo").len().into_u64());
+
+    let first = reader
+      .read_file(blake3::hash(&jpeg(b"foo")))
+      .unwrap()
+      .unwrap();
+
+    assert_eq!(first, jpeg(b"foo"));
+
+    let mut bytes = fs::read(&output).unwrap();
+    for content in [b"foo", b"bar"] {
+      let offset = bytes
+        .windows(3)
+        .position(|window| window == content)
+        .unwrap();
+      bytes[offset] = b'g';
+    }
+    fs::write(&output, bytes).unwrap();
+
+    let cached = reader
+      .read_file(blake3::hash(&jpeg(b"foo")))
+      .unwrap()
+      .unwrap();
+
+    assert_eq!(cached, jpeg(b"foo"));
+
+    assert_eq!(cached.as_ptr(), first.as_ptr());
+
+    assert_matches!(
+      reader.read_file(blake3::hash(&jpeg(b"bar"))).unwrap_err(),
+      package::Error::FileHashInvalid { expected, .. }
+      if expected == blake3::hash(&jpeg(b"bar")),
+    );
+  }
+
+  #[test]
+  fn file_comic_page_content_type() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+    fs::write(root.join("0.avif"), b"foo").unwrap();
+    fs::write(root.join("1.avif"), png(1, 1)).unwrap();
+
+    let hashes: HashMap<Utf8PathBuf, (Hash, u64)> = [
+      ("0.avif".into(), (blake3::hash(b"foo"), 3)),
+      (
+        "1.avif".into(),
+        (blake3::hash(&png(1, 1)), png(1, 1).len().into_u64()),
+      ),
+    ]
+    .into();
+
+    let manifest = Manifest::Comic {
+      content_types: vec!["image/avif".into(), "image/avif".into()],
+      dimensions: Vec::new(),
+      pages: vec![blake3::hash(b"foo"), blake3::hash(&png(1, 1))],
+      provenance: Provenance::default(),
+      thumbnails: Vec::new(),
+    };
+
+    Package::save(hashes, &manifest, &output, &root, false).unwrap();
+
+    let reader = Package::open(&output).unwrap();
+
+    assert_eq!(
+      reader.file("0").unwrap(),
+      Some(("image/avif".parse().unwrap(), Bytes::from_static(b"foo"))),
+    );
+
+    assert_eq!(
+      reader.file("1").unwrap(),
+      Some((mime::IMAGE_PNG, png(1, 1).into())),
+    );
+  }
+
+  #[test]
+  fn trailing_bytes() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("package.package");
+
+    let mut bytes = Vec::new();
+
+    bytes.extend_from_slice(Package::MAGIC_BYTES.as_bytes());
+    bytes.extend_from_slice(&Package::VERSION.to_le_bytes());
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.extend_from_slice(&1u64.to_le_bytes());
+    bytes.extend_from_slice(blake3::hash(&[]).as_bytes());
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.push(0);
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.extend_from_slice(&[0]);
+
+    fs::write(&package, bytes).unwrap();
+
+    assert_matches!(
+      Package::open(&package).unwrap_err(),
+      package::Error::TrailingBytes { trailing: 1, .. },
+    );
+  }
+}
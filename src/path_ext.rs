@@ -10,6 +10,21 @@ impl PathExt for Path {
   }
 }
 
+pub trait Utf8PathExt {
+  fn to_slash(&self) -> String;
+}
+
+impl Utf8PathExt for Utf8Path {
+  fn to_slash(&self) -> String {
+    self
+      .components()
+      .filter(|component| *component != Utf8Component::CurDir)
+      .map(|component| component.as_str())
+      .collect::<Vec<&str>>()
+      .join("/")
+  }
+}
+
 pub trait PathBufExt {
   #[allow(unused)]
   fn try_into_utf8(self) -> Result<Utf8PathBuf>;
@@ -23,3 +38,21 @@ impl PathBufExt for PathBuf {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn to_slash() {
+    assert_eq!(Utf8Path::new("index.html").to_slash(), "index.html");
+    assert_eq!(
+      Utf8Path::new("assets/js/app.js").to_slash(),
+      "assets/js/app.js"
+    );
+    assert_eq!(
+      Utf8Path::new("./assets//app.js").to_slash(),
+      "assets/app.js"
+    );
+  }
+}
@@ -0,0 +1,88 @@
+use super::*;
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Provenance {
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub created_at: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub creator: Option<String>,
+}
+
+impl Provenance {
+  pub fn load(path: &Utf8Path) -> Result<Self> {
+    Self::parse(path, &fs::read(path).context(error::Io { path })?)
+  }
+
+  pub fn parse(path: &Utf8Path, yaml: &[u8]) -> Result<Self> {
+    let provenance: Self =
+      serde_yaml::from_slice(yaml).context(error::DeserializeMetadata { path })?;
+
+    if let Some(created_at) = &provenance.created_at {
+      ensure!(
+        created_at.parse::<jiff::Timestamp>().is_ok(),
+        error::CreatedAt { created_at, path }
+      );
+    }
+
+    Ok(provenance)
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.created_at.is_none() && self.creator.is_none()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn load() {
+    let tempdir = tempdir();
+
+    let path = tempdir.path_utf8().join("metadata.yaml");
+
+    fs::write(&path, "type: comic").unwrap();
+
+    assert_eq!(Provenance::load(&path).unwrap(), Provenance::default());
+
+    fs::write(
+      &path,
+      "type: comic\ncreated_at: 2024-06-01T12:30:00Z\ncreator: Alice\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+      Provenance::load(&path).unwrap(),
+      Provenance {
+        created_at: Some("2024-06-01T12:30:00Z".into()),
+        creator: Some("Alice".into()),
+      },
+    );
+
+    fs::write(&path, "type: comic\ncreated_at: yesterday\n").unwrap();
+
+    assert_matches!(
+      Provenance::load(&path).unwrap_err(),
+      Error::CreatedAt { created_at, .. } if created_at == "yesterday",
+    );
+
+    fs::write(&path, "type: comic\ncreated_at: 2024-13-45T99:99:99Z\n").unwrap();
+
+    assert_matches!(
+      Provenance::load(&path).unwrap_err(),
+      Error::CreatedAt { created_at, .. } if created_at == "2024-13-45T99:99:99Z",
+    );
+
+    fs::write(
+      &path,
+      "type: comic\ncreated_at: 2024-02-30T12:30:00+02:00\n",
+    )
+    .unwrap();
+
+    assert_matches!(
+      Provenance::load(&path).unwrap_err(),
+      Error::CreatedAt { created_at, .. } if created_at == "2024-02-30T12:30:00+02:00",
+    );
+  }
+}
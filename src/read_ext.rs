@@ -1,9 +1,16 @@
 use super::*;
 
+/// Integers are read little-endian regardless of target, matching `WriteExt`.
 pub trait ReadExt {
   fn read_hash(&mut self) -> io::Result<Hash>;
 
+  /// Read a little-endian `u16`.
+  fn read_u16(&mut self) -> io::Result<u16>;
+
+  /// Read a little-endian `u64`.
   fn read_u64(&mut self) -> io::Result<u64>;
+
+  fn read_u8(&mut self) -> io::Result<u8>;
 }
 
 impl<T: Read> ReadExt for T {
@@ -15,6 +22,14 @@ impl<T: Read> ReadExt for T {
     Ok(array.into())
   }
 
+  fn read_u16(&mut self) -> io::Result<u16> {
+    let mut array = [0u8; 2];
+
+    self.read_exact(&mut array)?;
+
+    Ok(u16::from_le_bytes(array))
+  }
+
   fn read_u64(&mut self) -> io::Result<u64> {
     let mut array = [0u8; 8];
 
@@ -22,4 +37,12 @@ impl<T: Read> ReadExt for T {
 
     Ok(u64::from_le_bytes(array))
   }
+
+  fn read_u8(&mut self) -> io::Result<u8> {
+    let mut array = [0u8; 1];
+
+    self.read_exact(&mut array)?;
+
+    Ok(array[0])
+  }
 }
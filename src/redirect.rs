@@ -0,0 +1,91 @@
+use super::*;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Redirect {
+  Location(String),
+  Status { location: String, status: u16 },
+}
+
+impl Redirect {
+  const DEFAULT_STATUS: u16 = 302;
+
+  pub fn location(&self) -> &str {
+    match self {
+      Self::Location(location) | Self::Status { location, .. } => location,
+    }
+  }
+
+  pub fn status(&self) -> u16 {
+    match self {
+      Self::Location(_) => Self::DEFAULT_STATUS,
+      Self::Status { status, .. } => *status,
+    }
+  }
+
+  pub(crate) fn check(&self, from: &str) -> Result {
+    ensure!(
+      matches!(self.status(), 301 | 302 | 303 | 307 | 308),
+      error::RedirectStatus {
+        from,
+        status: self.status(),
+      }
+    );
+
+    ensure!(
+      !self.location().is_empty() && header::HeaderValue::from_str(self.location()).is_ok(),
+      error::RedirectLocation {
+        from,
+        location: self.location(),
+      }
+    );
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn deserialize() {
+    assert_eq!(
+      serde_yaml::from_str::<BTreeMap<String, Redirect>>(
+        "/old: /app/new\n/moved:\n  location: https://example.com/\n  status: 301\n"
+      )
+      .unwrap(),
+      [
+        ("/old".into(), Redirect::Location("/app/new".into())),
+        (
+          "/moved".into(),
+          Redirect::Status {
+            location: "https://example.com/".into(),
+            status: 301,
+          }
+        ),
+      ]
+      .into(),
+    );
+  }
+
+  #[test]
+  fn check() {
+    Redirect::Location("/app/new".into()).check("/old").unwrap();
+
+    assert_matches!(
+      Redirect::Status {
+        location: "/app/new".into(),
+        status: 200,
+      }
+      .check("/old")
+      .unwrap_err(),
+      Error::RedirectStatus { from, status: 200, .. } if from == "/old",
+    );
+
+    assert_matches!(
+      Redirect::Location("/app/\nnew".into()).check("/old").unwrap_err(),
+      Error::RedirectLocation { from, .. } if from == "/old",
+    );
+  }
+}
@@ -0,0 +1,146 @@
+use {super::*, image::ImageReader};
+
+pub(crate) fn image(content: &[u8]) -> Option<Mime> {
+  match content {
+    [0xFF, 0xD8, 0xFF, ..] => Some(mime::IMAGE_JPEG),
+    [0x89, b'P', b'N', b'G', ..] => Some(mime::IMAGE_PNG),
+    [b'G', b'I', b'F', b'8', ..] => Some(mime::IMAGE_GIF),
+    [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => {
+      Some("image/webp".parse().unwrap())
+    }
+    [_, _, _, _, b'f', b't', b'y', b'p', b'a', b'v', b'i', b'f' | b's', ..] => {
+      Some("image/avif".parse().unwrap())
+    }
+    _ => None,
+  }
+}
+
+// `prefix` need only contain the image header
+pub(crate) fn dimensions(prefix: &[u8]) -> Option<(u32, u32)> {
+  ImageReader::new(Cursor::new(prefix))
+    .with_guessed_format()
+    .ok()?
+    .into_dimensions()
+    .ok()
+}
+
+pub(crate) fn duration_ms(source: Box<dyn MediaSource>) -> Option<u64> {
+  use symphonia::core::{
+    formats::FormatOptions, io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
+  };
+
+  let mut format = symphonia::default::get_probe()
+    .format(
+      Hint::new().with_extension("mp3"),
+      MediaSourceStream::new(source, Default::default()),
+      &FormatOptions::default(),
+      &MetadataOptions::default(),
+    )
+    .ok()?
+    .format;
+
+  let track = format.default_track()?;
+
+  let id = track.id;
+
+  let sample_rate = u64::from(track.codec_params.sample_rate?);
+
+  let frames = match track.codec_params.n_frames {
+    Some(frames) => frames,
+    None => {
+      let mut frames = 0;
+
+      while let Ok(packet) = format.next_packet() {
+        if packet.track_id() == id {
+          frames = packet.dur.saturating_add(frames);
+        }
+      }
+
+      frames
+    }
+  };
+
+  milliseconds(frames, sample_rate)
+}
+
+// frame counts come from untrusted headers, so overflow yields no duration
+// rather than a panic
+fn milliseconds(frames: u64, sample_rate: u64) -> Option<u64> {
+  if frames == 0 {
+    return None;
+  }
+
+  frames.checked_mul(1000)?.checked_div(sample_rate)
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    super::*,
+    image::{DynamicImage, ImageFormat},
+  };
+
+  #[test]
+  fn milliseconds() {
+    assert_eq!(super::milliseconds(44_100, 44_100), Some(1000));
+    assert_eq!(super::milliseconds(0, 44_100), None);
+    assert_eq!(super::milliseconds(44_100, 0), None);
+    assert_eq!(super::milliseconds(u64::MAX, 44_100), None);
+  }
+
+  #[test]
+  fn image() {
+    #[track_caller]
+    fn case(content: &[u8], expected: Option<&str>) {
+      assert_eq!(
+        super::image(content).as_ref().map(Mime::essence_str),
+        expected
+      );
+    }
+
+    case(&[0xFF, 0xD8, 0xFF, 0xE0], Some("image/jpeg"));
+    case(b"\x89PNG\r\n\x1a\n", Some("image/png"));
+    case(b"GIF89a", Some("image/gif"));
+    case(b"RIFF\0\0\0\0WEBPVP8 ", Some("image/webp"));
+    case(b"\0\0\0\x1cftypavif", Some("image/avif"));
+    case(b"\0\0\0\x1cftypavis", Some("image/avif"));
+    case(b"RIFF\0\0\0\0WAVE", None);
+    case(b"", None);
+  }
+
+  #[test]
+  fn dimensions() {
+    #[track_caller]
+    fn case(content: &[u8], expected: Option<(u32, u32)>) {
+      assert_eq!(super::dimensions(content), expected);
+    }
+
+    fn encode(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+      let mut encoded = Vec::new();
+      DynamicImage::new_luma8(width, height)
+        .write_to(&mut Cursor::new(&mut encoded), format)
+        .unwrap();
+      encoded
+    }
+
+    case(&encode(3, 2, ImageFormat::Jpeg), Some((3, 2)));
+    case(&encode(256, 128, ImageFormat::Png), Some((256, 128)));
+    case(&encode(64, 32, ImageFormat::WebP), Some((64, 32)));
+    case(&jpeg(b"foo"), Some((1, 1)));
+    case(&png(7, 8), Some((7, 8)));
+    case(&webp(5, 6), Some((5, 6)));
+
+    case(&gif(9, 10), Some((9, 10)));
+
+    let png = png(300, 200);
+    case(&png[..png.len() - 12], Some((300, 200)));
+    case(&png[..20], None);
+
+    let jpeg = encode(300, 200, ImageFormat::Jpeg);
+    case(&jpeg[..jpeg.len() / 2], Some((300, 200)));
+
+    case(&avif(5, 6), None);
+    case(b"\xFF\xD8\xFF", None);
+    case(b"foo", None);
+  }
+}
@@ -0,0 +1,149 @@
+use img_parts::{
+  jpeg::markers,
+  riff::{RiffChunk, RiffContent},
+  webp::{CHUNK_EXIF, CHUNK_VP8X, CHUNK_XMP},
+  Bytes, DynImage,
+};
+
+// JPEG segments containing EXIF and XMP, IPTC, and comments
+const JPEG_SEGMENTS: [u8; 3] = [markers::APP1, markers::APP13, markers::COM];
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const PNG_CHUNKS: [[u8; 4]; 5] = [*b"eXIf", *b"iTXt", *b"tEXt", *b"tIME", *b"zTXt"];
+
+const WEBP_VP8X_EXIF: u8 = 0x08;
+const WEBP_VP8X_XMP: u8 = 0x04;
+
+// returns `None` if `content` is not a well-formed JPEG, PNG, or WebP image
+pub(crate) fn metadata(content: Vec<u8>) -> Option<Vec<u8>> {
+  let mut image = DynImage::from_bytes(Bytes::from(content)).ok()??;
+
+  match &mut image {
+    DynImage::Jpeg(jpeg) => jpeg
+      .segments_mut()
+      .retain(|segment| !JPEG_SEGMENTS.contains(&segment.marker())),
+    DynImage::Png(png) => png
+      .chunks_mut()
+      .retain(|chunk| !PNG_CHUNKS.contains(&chunk.kind())),
+    DynImage::WebP(webp) => {
+      webp.remove_chunks_by_id(CHUNK_EXIF);
+      webp.remove_chunks_by_id(CHUNK_XMP);
+
+      // img-parts doesn't update the VP8X flags when chunks are removed
+      for chunk in webp.chunks_mut() {
+        if chunk.id() == CHUNK_VP8X {
+          let mut data = chunk.content().data()?.to_vec();
+          *data.first_mut()? &= !(WEBP_VP8X_EXIF | WEBP_VP8X_XMP);
+          *chunk = RiffChunk::new(CHUNK_VP8X, RiffContent::Data(data.into()));
+        }
+      }
+    }
+  }
+
+  Some(image.encoder().bytes().into())
+}
+
+// whether `prefix`, the first twelve bytes of a file, begins an image that
+// `metadata` can strip
+pub(crate) fn supported(prefix: &[u8]) -> bool {
+  prefix.starts_with(b"\xFF\xD8")
+    || prefix.starts_with(PNG_SIGNATURE)
+    || (prefix.starts_with(b"RIFF") && prefix.get(8..12) == Some(b"WEBP"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn jpeg() {
+    let image = b"\xFF\xC0\x00\x0B\x08\x00\x01\x00\x01\x01\x01\x11\x00\xFF\xDA\x00\x08\x01\x01\x00\x00\x3F\x00foo\xFF\xD9";
+
+    let mut content = b"\xFF\xD8".to_vec();
+    content.extend_from_slice(b"\xFF\xE0\x00\x04JF");
+    content.extend_from_slice(b"\xFF\xE1\x00\x08Exif\0\0");
+    content.extend_from_slice(b"\xFF\xED\x00\x04PS");
+    content.extend_from_slice(b"\xFF\xFE\x00\x05bar");
+    content.extend_from_slice(image);
+
+    let mut expected = b"\xFF\xD8".to_vec();
+    expected.extend_from_slice(b"\xFF\xE0\x00\x04JF");
+    expected.extend_from_slice(image);
+
+    assert_eq!(metadata(content).unwrap(), expected);
+  }
+
+  #[test]
+  fn png() {
+    #[track_caller]
+    fn chunk(ty: [u8; 4], data: &[u8]) -> Vec<u8> {
+      img_parts::png::PngChunk::new(ty, Bytes::copy_from_slice(data))
+        .encoder()
+        .bytes()
+        .into()
+    }
+
+    let content = [
+      PNG_SIGNATURE.to_vec(),
+      chunk(*b"IHDR", &[0; 13]),
+      chunk(*b"eXIf", b"MM\0*"),
+      chunk(*b"tEXt", b"Author\0foo"),
+      chunk(*b"IDAT", b"bar"),
+      chunk(*b"IEND", &[]),
+    ]
+    .concat();
+
+    let expected = [
+      PNG_SIGNATURE.to_vec(),
+      chunk(*b"IHDR", &[0; 13]),
+      chunk(*b"IDAT", b"bar"),
+      chunk(*b"IEND", &[]),
+    ]
+    .concat();
+
+    assert_eq!(metadata(content).unwrap(), expected);
+  }
+
+  #[test]
+  fn webp() {
+    #[track_caller]
+    fn riff(chunks: &[&[u8]]) -> Vec<u8> {
+      let chunks = chunks.concat();
+      let mut riff = b"RIFF".to_vec();
+      riff.extend_from_slice(&u32::try_from(chunks.len() + 4).unwrap().to_le_bytes());
+      riff.extend_from_slice(b"WEBP");
+      riff.extend_from_slice(&chunks);
+      riff
+    }
+
+    let content = riff(&[
+      b"VP8X\x0a\0\0\0\x0c\0\0\0\0\0\0\0\0\0",
+      b"VP8 \x03\0\0\0foo\0",
+      b"EXIF\x04\0\0\0MM\0*",
+      b"XMP \x01\0\0\0x\0",
+    ]);
+
+    let expected = riff(&[
+      b"VP8X\x0a\0\0\0\0\0\0\0\0\0\0\0\0\0",
+      b"VP8 \x03\0\0\0foo\0",
+    ]);
+
+    assert_eq!(metadata(content).unwrap(), expected);
+  }
+
+  #[test]
+  fn supported() {
+    assert!(super::supported(b"\xFF\xD8\xFF\xE1"));
+    assert!(super::supported(PNG_SIGNATURE));
+    assert!(super::supported(b"RIFF\0\0\0\0WEBP"));
+    assert!(!super::supported(b"RIFF\0\0\0\0WAVE"));
+    assert!(!super::supported(b"<html>"));
+  }
+
+  #[test]
+  fn other() {
+    assert_eq!(metadata(b"foo".to_vec()), None);
+    assert_eq!(metadata(b"\xFF\xD8\xFF\xE1\xFF".to_vec()), None);
+    assert_eq!(metadata([PNG_SIGNATURE, b"\0\0\0\x0dIHDR"].concat()), None);
+  }
+}
@@ -1,8 +1,8 @@
 use {
   super::*,
   axum::{
-    extract::{Extension, Path},
-    http::StatusCode,
+    extract::{Extension, Path, Query},
+    http::{Method, StatusCode},
     response::{IntoResponse, Response},
     routing::get,
     Router,
@@ -14,8 +14,17 @@ use {
   tokio::runtime::Runtime,
 };
 
-mod package;
-mod server;
+mod cat;
+mod diff;
+mod export;
+mod extract;
+mod info;
+mod list;
+mod manifest;
+pub mod package;
+mod repack;
+pub mod server;
+mod verify;
 
 #[derive(Parser)]
 #[command(
@@ -27,15 +36,36 @@ mod server;
     .placeholder(AnsiColor::Cyan.on_default()))
 ]
 pub enum Subcommand {
+  Cat(cat::Cat),
+  Diff(diff::Diff),
+  Export(export::Export),
+  Extract(extract::Extract),
+  Info(info::Info),
+  List(list::List),
+  Manifest(manifest::Manifest),
   Package(package::Package),
+  Repack(repack::Repack),
   Server(server::Server),
+  Verify(verify::Verify),
 }
 
 impl Subcommand {
   pub fn run(self) -> Result {
     match self {
+      Self::Cat(cat) => cat.run(),
+      Self::Diff(diff) => diff.run(),
+      Self::Export(export) => export.run(),
+      Self::Extract(extract) => extract.run(),
+      Self::Info(info) => info.run(),
+      Self::List(list) => list.run(),
+      Self::Manifest(manifest) => manifest.run(),
       Self::Package(package) => package.run(),
-      Self::Server(server) => server.run(),
+      Self::Repack(repack) => repack.run(),
+      Self::Server(server) => {
+        server.init_tracing()?;
+        server.run()
+      }
+      Self::Verify(verify) => verify.run(),
     }
   }
 }
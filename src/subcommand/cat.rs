@@ -0,0 +1,76 @@
+use super::*;
+
+#[derive(Parser)]
+pub struct Cat {
+  #[arg(help = "Read file from <PACKAGE>.")]
+  pub package: Utf8PathBuf,
+  #[arg(help = "Write file at <PATH> to stdout.")]
+  pub path: String,
+}
+
+impl Cat {
+  pub fn run(self) -> Result {
+    let content = self.content()?;
+
+    io::stdout()
+      .lock()
+      .write_all(&content)
+      .context(error::Stdout)?;
+
+    Ok(())
+  }
+
+  fn content(&self) -> Result<Vec<u8>> {
+    let package = Package::load(&self.package).context(error::PackageLoad {
+      path: &self.package,
+    })?;
+
+    let (_content_type, content) = package.file(&self.path).context(error::FileNotFound {
+      package: &self.package,
+      path: &self.path,
+    })?;
+
+    Ok(content)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn cat() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("app.package");
+
+    subcommand::package::Package {
+      root: vec!["apps/comic".into()],
+      output: package.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    assert_eq!(
+      Cat {
+        package: package.clone(),
+        path: "index.js".into(),
+      }
+      .content()
+      .unwrap(),
+      fs::read("apps/comic/index.js").unwrap(),
+    );
+
+    assert_matches!(
+      Cat {
+        package: package.clone(),
+        path: "foo.js".into(),
+      }
+      .run()
+      .unwrap_err(),
+      Error::FileNotFound { package: p, path, .. }
+      if p == package && path == "foo.js",
+    );
+  }
+}
@@ -0,0 +1,151 @@
+use super::*;
+
+#[derive(Parser)]
+pub struct Diff {
+  #[arg(help = "Compare <OLD> package…")]
+  pub old: Utf8PathBuf,
+  #[arg(help = "…to <NEW> package.")]
+  pub new: Utf8PathBuf,
+  #[arg(long, help = "Print differences as JSON.")]
+  pub json: bool,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct Changes {
+  added: Vec<String>,
+  removed: Vec<String>,
+  changed: Vec<String>,
+  shared: Vec<String>,
+}
+
+impl Diff {
+  pub fn run(self) -> Result {
+    let changes = self.changes()?;
+
+    if self.json {
+      println!("{}", serde_json::to_string_pretty(&changes).unwrap());
+    } else {
+      for path in &changes.added {
+        println!("+ {path}");
+      }
+
+      for path in &changes.removed {
+        println!("- {path}");
+      }
+
+      for path in &changes.changed {
+        println!("~ {path}");
+      }
+
+      println!("{} shared files", changes.shared.len());
+    }
+
+    Ok(())
+  }
+
+  fn changes(&self) -> Result<Changes> {
+    let old = Package::load(&self.old).context(error::PackageLoad { path: &self.old })?;
+    let new = Package::load(&self.new).context(error::PackageLoad { path: &self.new })?;
+
+    let old_paths = Self::paths(&old);
+    let new_paths = Self::paths(&new);
+
+    let mut changes = Changes::default();
+
+    for (path, hash) in &new_paths {
+      match old_paths.get(path) {
+        None => changes.added.push(path.clone()),
+        Some(old) if old != hash => changes.changed.push(path.clone()),
+        Some(_) => {}
+      }
+    }
+
+    for path in old_paths.keys() {
+      if !new_paths.contains_key(path) {
+        changes.removed.push(path.clone());
+      }
+    }
+
+    let mut shared = old
+      .manifest
+      .referenced_hashes()
+      .filter(|hash| new.contains(*hash))
+      .collect::<HashSet<Hash>>()
+      .into_iter()
+      .map(|hash| hash.to_hex().to_string())
+      .collect::<Vec<String>>();
+
+    shared.sort();
+
+    changes.shared = shared;
+
+    Ok(changes)
+  }
+
+  fn paths(package: &Package) -> BTreeMap<String, Hash> {
+    package
+      .entries()
+      .into_iter()
+      .filter_map(|entry| {
+        let hash = match package.manifest.file(&entry) {
+          Some((_content_type, hash)) => hash,
+          None => blake3::hash(&package.manifest.generated(&entry)?.1),
+        };
+        Some((entry, hash))
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn diff() {
+    let tempdir = tempdir();
+
+    let old = tempdir.path_utf8().join("old");
+    let new = tempdir.path_utf8().join("new");
+
+    fs::create_dir(&old).unwrap();
+    fs::create_dir(&new).unwrap();
+
+    fs::write(old.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(old.join("0.jpg"), jpeg(b"foo")).unwrap();
+    fs::write(old.join("1.jpg"), jpeg(b"bar")).unwrap();
+
+    fs::write(new.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(new.join("0.jpg"), jpeg(b"foo")).unwrap();
+    fs::write(new.join("1.jpg"), jpeg(b"baz")).unwrap();
+    fs::write(new.join("2.jpg"), jpeg(b"qux")).unwrap();
+
+    for root in [&old, &new] {
+      subcommand::package::Package {
+        root: vec![root.clone()],
+        output: root.with_extension("package"),
+        ..Default::default()
+      }
+      .run()
+      .unwrap();
+    }
+
+    let diff = Diff {
+      old: old.with_extension("package"),
+      new: new.with_extension("package"),
+      json: false,
+    };
+
+    assert_eq!(
+      diff.changes().unwrap(),
+      Changes {
+        added: vec!["2".into()],
+        removed: Vec::new(),
+        changed: vec!["1".into()],
+        shared: vec![blake3::hash(&jpeg(b"foo")).to_hex().to_string()],
+      },
+    );
+
+    diff.run().unwrap();
+  }
+}
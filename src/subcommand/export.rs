@@ -0,0 +1,83 @@
+use super::*;
+
+#[derive(Parser)]
+pub struct Export {
+  #[arg(help = "Export contents of <PACKAGE>…")]
+  pub package: Utf8PathBuf,
+  #[arg(help = "…to zip archive <OUTPUT>.")]
+  pub output: Utf8PathBuf,
+}
+
+impl Export {
+  pub fn run(self) -> Result {
+    let package = Package::load(&self.package).context(error::PackageLoad {
+      path: &self.package,
+    })?;
+
+    package
+      .export(&self.output)
+      .context(error::PackageExport { path: &self.output })?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn export() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("content.package");
+    let output = tempdir.path_utf8().join("content.zip");
+
+    subcommand::package::Package {
+      root: vec!["content/comic".into()],
+      output: package.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    Export {
+      package,
+      output: output.clone(),
+    }
+    .run()
+    .unwrap();
+
+    let mut expected = ["0.jpg", "1.jpg", "2.jpg"]
+      .into_iter()
+      .map(|page| {
+        (
+          Utf8PathBuf::from(page),
+          fs::read(Utf8Path::new("content/comic").join(page)).unwrap(),
+        )
+      })
+      .collect::<Vec<(Utf8PathBuf, Vec<u8>)>>();
+
+    expected.push(("metadata.yaml".into(), b"type: comic\n".to_vec()));
+
+    assert_eq!(unzip(&fs::read(output).unwrap()), expected);
+  }
+
+  #[test]
+  fn package_load_error() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("content.package");
+
+    assert_matches!(
+      Export {
+        package: package.clone(),
+        output: tempdir.path_utf8().join("content.zip"),
+      }
+      .run()
+      .unwrap_err(),
+      Error::PackageLoad { path, .. }
+      if path == package,
+    );
+  }
+}
@@ -0,0 +1,116 @@
+use super::*;
+
+#[derive(Parser)]
+pub struct Extract {
+  #[arg(long, help = "Extract contents of <PACKAGE>.", value_name = "PACKAGE")]
+  pub package: Utf8PathBuf,
+  #[arg(long, help = "Extract contents to directory <OUTPUT>.")]
+  pub output: Utf8PathBuf,
+}
+
+impl Extract {
+  pub fn run(self) -> Result {
+    if self.output.is_dir() {
+      ensure!(
+        self
+          .output
+          .read_dir_utf8()
+          .context(error::Io { path: &self.output })?
+          .next()
+          .is_none(),
+        error::OutputNotEmpty {
+          output: self.output
+        },
+      );
+    }
+
+    let package = Package::load(&self.package).context(error::PackageLoad {
+      path: &self.package,
+    })?;
+
+    package
+      .extract(&self.output)
+      .context(error::PackageExtract { path: &self.output })?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn extract() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("content.package");
+    let output = tempdir.path_utf8().join("output");
+
+    subcommand::package::Package {
+      root: vec!["content/comic".into()],
+      output: package.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    Extract {
+      package,
+      output: output.clone(),
+    }
+    .run()
+    .unwrap();
+
+    for page in ["0.jpg", "1.jpg", "2.jpg"] {
+      assert_eq!(
+        fs::read(output.join(page)).unwrap(),
+        fs::read(Utf8Path::new("content/comic").join(page)).unwrap(),
+      );
+    }
+
+    assert_eq!(
+      fs::read_to_string(output.join("metadata.yaml")).unwrap(),
+      "type: comic\n",
+    );
+  }
+
+  #[test]
+  fn output_not_empty_error() {
+    let tempdir = tempdir();
+
+    let output_dir = tempdir.path_utf8().join("output");
+
+    fs::create_dir(&output_dir).unwrap();
+    fs::write(output_dir.join("foo"), "").unwrap();
+
+    assert_matches!(
+      Extract {
+        package: tempdir.path_utf8().join("content.package"),
+        output: output_dir.clone(),
+      }
+      .run()
+      .unwrap_err(),
+      Error::OutputNotEmpty { output, .. }
+      if output == output_dir,
+    );
+  }
+
+  #[test]
+  fn package_load_error() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("content.package");
+
+    assert_matches!(
+      Extract {
+        package: package.clone(),
+        output: tempdir.path_utf8().join("output"),
+      }
+      .run()
+      .unwrap_err(),
+      Error::PackageLoad { path, .. }
+      if path == package,
+    );
+  }
+}
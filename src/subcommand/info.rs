@@ -0,0 +1,63 @@
+use super::*;
+
+#[derive(Parser)]
+pub struct Info {
+  #[arg(help = "Print information about <PACKAGE>.")]
+  pub package: Utf8PathBuf,
+}
+
+impl Info {
+  pub fn run(self) -> Result {
+    let reader = Package::open(&self.package).context(error::PackageLoad {
+      path: &self.package,
+    })?;
+
+    println!("{}", serde_json::to_string_pretty(&reader.info()).unwrap());
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn info() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("content.package");
+
+    subcommand::package::Package {
+      root: vec!["content/comic".into()],
+      output: package.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    Info { package }.run().unwrap();
+  }
+
+  #[test]
+  fn info_error() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("content.package");
+
+    fs::write(&package, "this-is-not-a-package").unwrap();
+
+    assert_matches!(
+      Info {
+        package: package.clone(),
+      }
+      .run()
+      .unwrap_err(),
+      Error::PackageLoad {
+        path,
+        source: crate::package::Error::MagicBytes { .. },
+      }
+      if path == package,
+    );
+  }
+}
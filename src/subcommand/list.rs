@@ -0,0 +1,57 @@
+use super::*;
+
+#[derive(Parser)]
+pub struct List {
+  #[arg(help = "List paths that may be requested from <PACKAGE>.")]
+  pub package: Utf8PathBuf,
+}
+
+impl List {
+  pub fn run(self) -> Result {
+    let reader = Package::open(&self.package).context(error::PackageLoad {
+      path: &self.package,
+    })?;
+
+    for entry in reader.manifest().entries() {
+      println!("{entry}");
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn list() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("app.package");
+
+    subcommand::package::Package {
+      root: vec!["apps/comic".into()],
+      output: package.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    List {
+      package: package.clone(),
+    }
+    .run()
+    .unwrap();
+
+    let package = Package::load(&package).unwrap();
+
+    let entries = package.entries();
+
+    assert!(entries.contains(&"index.html".into()));
+
+    for entry in entries {
+      assert!(package.file(&entry).is_some(), "{entry}");
+    }
+  }
+}
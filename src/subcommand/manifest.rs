@@ -0,0 +1,86 @@
+use super::*;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+pub enum Format {
+  Json,
+  #[default]
+  Yaml,
+}
+
+#[derive(Parser)]
+pub struct Manifest {
+  #[arg(
+    long,
+    default_value_t,
+    help = "Print manifest as <FORMAT>.",
+    value_enum
+  )]
+  pub format: Format,
+  #[arg(help = "Print manifest of <PACKAGE>.")]
+  pub package: Utf8PathBuf,
+}
+
+impl Manifest {
+  pub fn run(self) -> Result {
+    print!("{}", self.render()?);
+    Ok(())
+  }
+
+  fn render(&self) -> Result<String> {
+    let package = Package::load(&self.package).context(error::PackageLoad {
+      path: &self.package,
+    })?;
+
+    Ok(match self.format {
+      Format::Json => format!("{}\n", String::from_utf8(package.manifest_json()).unwrap()),
+      Format::Yaml => serde_yaml::to_string(&package.manifest).unwrap(),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn manifest() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("content.package");
+
+    subcommand::package::Package {
+      root: vec!["content/comic".into()],
+      output: package.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    let expected = Package::load(&package).unwrap().manifest;
+
+    let json = Manifest {
+      format: Format::Json,
+      package: package.clone(),
+    }
+    .render()
+    .unwrap();
+
+    assert_eq!(
+      serde_json::from_str::<crate::Manifest>(&json).unwrap(),
+      expected
+    );
+
+    let yaml = Manifest {
+      format: Format::Yaml,
+      package,
+    }
+    .render()
+    .unwrap();
+
+    assert!(yaml.starts_with("schema: 1\ntype: comic\n"), "{yaml}");
+    assert_eq!(
+      serde_yaml::from_str::<crate::Manifest>(&yaml).unwrap(),
+      expected
+    );
+  }
+}
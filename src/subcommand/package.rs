@@ -1,102 +1,703 @@
-use super::*;
+use {
+  super::*,
+  ignore::gitignore::{Gitignore, GitignoreBuilder},
+  rayon::prelude::*,
+  std::sync::atomic::{AtomicU64, Ordering},
+  thumbnail::Thumbnail,
+};
 
-#[derive(Parser)]
+pub use self::progress::Progress;
+
+mod progress;
+
+type Hashes = HashMap<Utf8PathBuf, (Hash, u64)>;
+
+type Roots<'a> = HashMap<Utf8PathBuf, &'a Utf8Path>;
+
+#[derive(Default, Parser)]
 pub struct Package {
-  #[arg(long, help = "Package contents of directory <ROOT>.")]
-  pub root: Utf8PathBuf,
+  #[arg(
+    long,
+    help = "Package contents of directory <ROOT>. May be repeated, in which case later roots \
+            are overlaid on earlier ones.",
+    required_unless_present = "tar"
+  )]
+  pub root: Vec<Utf8PathBuf>,
+  #[arg(
+    long,
+    help = "Allow later roots to replace files at the same path in earlier roots, instead of \
+            failing."
+  )]
+  pub overlay: bool,
+  #[arg(
+    long,
+    conflicts_with = "root",
+    help = "Package contents of tar archive <ARCHIVE>, or `-` to read it from standard input.",
+    value_name = "ARCHIVE"
+  )]
+  pub tar: Option<Utf8PathBuf>,
   #[arg(long, help = "Save package to <OUTPUT>.")]
   pub output: Utf8PathBuf,
+  #[arg(long, help = "Compress package files with zstd.")]
+  pub compress: bool,
+  #[arg(
+    long,
+    help = "Print files that would be packaged and manifest summary without saving package."
+  )]
+  pub dry_run: bool,
+  #[arg(
+    long,
+    help = "Follow symlinks in <ROOT>, rejecting any that point outside of it. By default symlinks are skipped."
+  )]
+  pub follow_symlinks: bool,
+  #[arg(
+    long,
+    help = "Print progress to standard error as newline-delimited JSON events."
+  )]
+  pub progress_json: bool,
+  #[arg(
+    long,
+    help = "Strip EXIF, XMP, and other metadata from JPEG, PNG, and WebP images before hashing."
+  )]
+  pub strip_metadata: bool,
+  #[arg(
+    long,
+    help = "Generate PNG thumbnails no larger than 256 pixels on either side for comic pages and \
+            gallery images."
+  )]
+  pub thumbnails: bool,
 }
 
 impl Package {
+  const IGNORE_PATH: &'static str = ".mediaignore";
+
   pub fn run(self) -> Result {
-    ensure!(
-      !self.output.starts_with(&self.root),
-      error::OutputInRoot {
-        output: self.output,
-        root: self.root,
-      }
-    );
+    if self.progress_json {
+      self.run_with_progress(&|progress| eprintln!("{}", progress.json()))
+    } else {
+      self.package(None)
+    }
+  }
+
+  pub fn run_with_progress(self, progress: &(dyn Fn(Progress) + Sync)) -> Result {
+    self.package(Some(progress))
+  }
+
+  fn package(self, progress: Option<&(dyn Fn(Progress) + Sync)>) -> Result {
+    if let Some(tar) = &self.tar {
+      return self.run_tar(tar, progress);
+    }
+
+    for root in &self.root {
+      ensure!(
+        !self.output.starts_with(root),
+        error::OutputInRoot {
+          output: &self.output,
+          root,
+        }
+      );
+    }
 
     ensure!(
       !self.output.is_dir(),
       error::OutputIsDir {
-        output: self.output
+        output: &self.output
       },
     );
 
-    let metadata = self.root.join(Metadata::PATH);
+    let mut metadata = None::<&Utf8Path>;
 
-    ensure!(
-      metadata.exists(),
-      error::MetadataMissing { root: &self.root },
-    );
+    for root in &self.root {
+      if root.join(Metadata::PATH).exists() {
+        if let Some(previous) = metadata.filter(|_| !self.overlay) {
+          return error::PathConflict {
+            path: Metadata::PATH,
+            roots: vec![previous.to_owned(), root.clone()],
+          }
+          .fail();
+        }
+
+        metadata = Some(root.as_path());
+      }
+    }
+
+    let root = metadata.context(error::MetadataMissing {
+      root: self.root.last().unwrap(),
+    })?;
+
+    self.package_roots(&self.root, root, progress)
+  }
+
+  fn package_roots(
+    &self,
+    roots: &[Utf8PathBuf],
+    root: &Utf8Path,
+    progress: Option<&(dyn Fn(Progress) + Sync)>,
+  ) -> Result {
+    let metadata = root.join(Metadata::PATH);
+
+    let provenance = Provenance::load(&metadata)?;
 
     let metadata = Metadata::load(&metadata)?;
 
-    let paths = self.paths()?;
+    // walking the tree a second time to count files is only worth it if
+    // progress is being reported
+    let total = match progress {
+      Some(_) => self
+        .tree(roots)?
+        .try_fold(0, |total, result| result.map(|_| total + 1))?,
+      None => 0,
+    };
+
+    let (hashes, contents, roots) = self.hashes(self.tree(roots)?, total, progress)?;
+
+    let template = metadata.template(
+      root,
+      &hashes,
+      |path, limit| {
+        let path = roots.get(path).copied().unwrap_or(root).join(path);
+        let mut content = Vec::new();
+        File::open(&path)
+          .and_then(|file| file.take(limit).read_to_end(&mut content))
+          .context(error::Io { path })?;
+        Ok(content)
+      },
+      |path| {
+        let path = roots.get(path).copied().unwrap_or(root).join(path);
+        Ok(Box::new(File::open(&path).context(error::Io { path })?))
+      },
+    )?;
+
+    self.save(
+      template,
+      hashes,
+      provenance,
+      contents,
+      |path| roots[path].join(path),
+      progress,
+    )
+  }
+
+  // writes the package, reading files whose content isn't in `contents` from
+  // the location returned by `path`
+  fn save(
+    &self,
+    template: Template,
+    mut hashes: Hashes,
+    provenance: Provenance,
+    mut contents: HashMap<Hash, Content>,
+    path: impl Fn(&Utf8Path) -> Utf8PathBuf + Sync,
+    progress: Option<&(dyn Fn(Progress) + Sync)>,
+  ) -> Result {
+    for sidecar in template.sidecars() {
+      if let Some((hash, _len)) = hashes.remove(Utf8Path::new(sidecar)) {
+        if !hashes.values().any(|(other, _len)| *other == hash) {
+          contents.remove(&hash);
+        }
+      }
+    }
+
+    let manifest = template.manifest(&hashes).with_provenance(provenance);
 
-    let template = metadata.template(&self.root, &paths)?;
+    if self.dry_run {
+      print!("{}", Self::dry_run(&hashes, &manifest));
+      return Ok(());
+    }
+
+    let manifest = if self.thumbnails {
+      let paths = hashes
+        .iter()
+        .map(|(path, (hash, _len))| (*hash, path))
+        .collect::<HashMap<Hash, &Utf8PathBuf>>();
+
+      let (manifest, thumbnails) = Self::thumbnails(manifest, |hash| match contents.get(&hash) {
+        Some(Content::Generated(content)) => Ok(content.clone()),
+        // stripped images are thumbnailed from the original file, since
+        // stripping metadata doesn't change pixels
+        Some(Content::Stripped) | None => {
+          let path = path(paths[&hash]);
+          fs::read(&path).context(error::Io { path })
+        }
+      })?;
 
-    let hashes = self.hashes(paths)?;
+      contents.extend(
+        thumbnails
+          .into_iter()
+          .map(|(hash, content)| (hash, Content::Generated(content))),
+      );
 
-    let manifest = template.manifest(&hashes);
+      manifest
+    } else {
+      manifest
+    };
 
-    super::Package::save(hashes, &manifest, &self.output, &self.root)
-      .context(error::PackageSave { path: &self.output })?;
+    let saved = Self::saved(&self.output, &hashes);
+
+    let hashes = hashes
+      .into_iter()
+      .map(|(relative, hash)| (path(&relative), hash))
+      .collect();
+
+    super::Package::save_with_contents(
+      hashes,
+      contents,
+      &manifest,
+      &self.output,
+      Utf8Path::new(""),
+      self.compress,
+    )
+    .context(error::PackageSave { path: &self.output })?;
+
+    if let Some(progress) = progress {
+      progress(saved);
+    }
 
     Ok(())
   }
 
-  fn hashes(&self, paths: HashSet<Utf8PathBuf>) -> Result<HashMap<Utf8PathBuf, (Hash, u64)>> {
-    let mut hashes = HashMap::new();
+  fn saved(output: &Utf8Path, hashes: &HashMap<Utf8PathBuf, (Hash, u64)>) -> Progress {
+    Progress::Saved {
+      output: output.to_string(),
+      files: hashes.len().into_u64(),
+      bytes: hashes.values().map(|(_hash, len)| len).sum(),
+    }
+  }
+
+  // reads the archive entry by entry, hashing each entry as it is read, and
+  // packages the entries from memory
+  fn run_tar(&self, tar: &Utf8Path, progress: Option<&(dyn Fn(Progress) + Sync)>) -> Result {
+    ensure!(
+      !self.output.is_dir(),
+      error::OutputIsDir {
+        output: &self.output
+      },
+    );
+
+    let reader: Box<dyn Read> = if tar == "-" {
+      Box::new(io::stdin().lock())
+    } else {
+      Box::new(File::open(tar).context(error::Tar { path: tar })?)
+    };
+
+    let mut archive = tar::Archive::new(reader);
+
+    let mut files = BTreeMap::new();
+
+    for entry in archive.entries().context(error::Tar { path: tar })? {
+      let entry = entry.context(error::Tar { path: tar })?;
+
+      let ty = entry.header().entry_type();
+
+      if !ty.is_file() && !ty.is_contiguous() {
+        continue;
+      }
+
+      let path = entry.path().context(error::Tar { path: tar })?;
 
-    for relative in paths {
-      let path = self.root.join(&relative);
+      let path = path.try_into_utf8()?;
 
-      let context = error::Io { path: &path };
+      let relative = path
+        .components()
+        .filter(|component| *component != Utf8Component::CurDir)
+        .map(|component| match component {
+          Utf8Component::Normal(component) => Some(component),
+          _ => None,
+        })
+        .collect::<Option<Utf8PathBuf>>()
+        .filter(|relative| !relative.as_str().is_empty())
+        .context(error::TarEntryPath {
+          path: path.as_str(),
+        })?;
 
-      let file = File::open(&path).context(context)?;
+      ensure!(
+        !files.contains_key(&relative),
+        error::PathDuplicated { path: relative }
+      );
 
-      let len = file.metadata().context(context)?.len();
+      let mut reader = HashReader::new(entry);
 
-      let mut hasher = Hasher::new();
+      let mut content = Vec::new();
 
-      hasher.update_reader(file).context(context)?;
+      reader
+        .read_to_end(&mut content)
+        .context(error::Tar { path: tar })?;
 
-      let hash = hasher.finalize();
+      files.insert(relative, (reader.finalize(), content));
+    }
+
+    let (_hash, metadata) = files
+      .remove(Utf8Path::new(Metadata::PATH))
+      .context(error::MetadataMissing { root: tar })?;
+
+    let provenance = Provenance::parse(&tar.join(Metadata::PATH), &metadata)?;
+
+    let metadata = Metadata::parse(&tar.join(Metadata::PATH), &metadata)?;
+
+    let ignore = Self::tar_ignore(tar, files.remove(Utf8Path::new(Self::IGNORE_PATH)))?;
+
+    files.retain(|path, _| {
+      path.file_name() != Some(".DS_Store")
+        && !ignore.matched_path_or_any_parents(path, false).is_ignore()
+    });
+
+    let total = files.len().into_u64();
+
+    let mut hashes = Hashes::new();
+
+    let mut contents = HashMap::new();
+
+    for (done, (path, (mut hash, mut content))) in files.into_iter().enumerate() {
+      if self.strip_metadata && strip::supported(&content) {
+        if let Some(stripped) = strip::metadata(content.clone()) {
+          if stripped != content {
+            hash = blake3::hash(&stripped);
+            content = stripped;
+          }
+        }
+      }
+
+      let len = content.len().into_u64();
+
+      if let Some(progress) = progress {
+        progress(Progress::Hashed {
+          path: path.to_slash(),
+          bytes: len,
+          done: done.into_u64() + 1,
+          total,
+        });
+      }
+
+      hashes.insert(path, (hash, len));
 
-      hashes.insert(relative.clone(), (hash, len));
+      contents.insert(hash, content);
     }
 
-    Ok(hashes)
+    let read = |path: &Utf8Path| {
+      hashes
+        .get(path)
+        .and_then(|(hash, _len)| contents.get(hash))
+        .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+        .context(error::Io {
+          path: tar.join(path),
+        })
+    };
+
+    let template = metadata.template(
+      tar,
+      &hashes,
+      |path, limit| {
+        let content = read(path)?;
+        Ok(
+          content[..content
+            .len()
+            .min(usize::try_from(limit).unwrap_or(usize::MAX))]
+            .to_vec(),
+        )
+      },
+      |path| Ok(Box::new(Cursor::new(read(path)?.clone()))),
+    )?;
+
+    let contents = contents
+      .into_iter()
+      .map(|(hash, content)| (hash, Content::Generated(content)))
+      .collect();
+
+    self.save(
+      template,
+      hashes,
+      provenance,
+      contents,
+      |path| tar.join(path),
+      progress,
+    )
   }
 
-  fn paths(&self) -> Result<HashSet<Utf8PathBuf>> {
-    let mut paths = HashSet::new();
+  fn tar_ignore(tar: &Utf8Path, ignore: Option<(Hash, Vec<u8>)>) -> Result<Gitignore> {
+    let path = tar.join(Self::IGNORE_PATH);
 
-    for result in WalkDir::new(&self.root) {
-      let entry = result.context(error::WalkDir { root: &self.root })?;
+    let mut builder = GitignoreBuilder::new("");
 
-      if entry.file_type().is_dir() || entry.file_name() == ".DS_Store" {
-        continue;
+    if let Some((_hash, content)) = ignore {
+      for line in String::from_utf8_lossy(&content).lines() {
+        builder
+          .add_line(None, line)
+          .context(error::Ignore { path: &path })?;
       }
+    }
 
-      let path = entry
-        .path()
-        .try_into_utf8()?
-        .strip_prefix(&self.root)
-        .unwrap()
-        .to_owned();
+    builder.build().context(error::Ignore { path })
+  }
 
-      if path == Utf8Path::new(Metadata::PATH) {
-        continue;
+  // images that are already small enough are their own thumbnails, and
+  // images that can't be decoded have none
+  fn thumbnails(
+    manifest: Manifest,
+    read: impl Fn(Hash) -> Result<Vec<u8>> + Sync,
+  ) -> Result<(Manifest, HashMap<Hash, Vec<u8>>)> {
+    let generated = manifest
+      .images()
+      .into_par_iter()
+      .map(|image| Ok((image, thumbnail::thumbnail(&read(image)?))))
+      .collect::<Result<Vec<(Hash, Option<Thumbnail>)>>>()?;
+
+    let mut thumbnails = Vec::new();
+
+    let mut contents = HashMap::new();
+
+    for (image, thumbnail) in generated {
+      thumbnails.push(match thumbnail {
+        Some(Thumbnail::Generated(content)) => {
+          let hash = blake3::hash(&content);
+          contents.insert(hash, content);
+          Some(hash)
+        }
+        Some(Thumbnail::Original) => Some(image),
+        None => None,
+      });
+    }
+
+    Ok((manifest.with_thumbnails(thumbnails), contents))
+  }
+
+  fn dry_run(hashes: &HashMap<Utf8PathBuf, (Hash, u64)>, manifest: &Manifest) -> String {
+    let mut paths = hashes
+      .iter()
+      .map(|(path, (_hash, len))| (path.to_slash(), len))
+      .collect::<Vec<(String, &u64)>>();
+
+    paths.sort();
+
+    let mut output = String::new();
+
+    for (path, len) in paths {
+      output.push_str(&format!("{path} {len}\n"));
+    }
+
+    output.push_str(&format!(
+      "{}\n",
+      serde_json::to_string(&manifest.summary()).unwrap()
+    ));
+
+    output
+  }
+
+  // merges the walks of each root, which are sorted by file name and so yield
+  // paths in ascending order, without collecting the whole tree
+  fn tree<'a>(
+    &'a self,
+    roots: &'a [Utf8PathBuf],
+  ) -> Result<impl Iterator<Item = Result<(Utf8PathBuf, &'a Utf8Path)>> + Send + 'a> {
+    let mut walks = roots
+      .iter()
+      .map(|root| Ok((root.as_path(), self.paths(root)?.peekable())))
+      .collect::<Result<Vec<_>>>()?;
+
+    Ok(iter::from_fn(move || {
+      for (_root, walk) in &mut walks {
+        if let Some(Err(err)) = walk.next_if(Result::is_err) {
+          return Some(Err(err));
+        }
+      }
+
+      let path = walks
+        .iter_mut()
+        .filter_map(|(_root, walk)| walk.peek()?.as_ref().ok())
+        .min()?
+        .clone();
+
+      let mut found = None::<&Utf8Path>;
+
+      for (root, walk) in &mut walks {
+        if walk
+          .next_if(|result| result.as_ref().is_ok_and(|next| *next == path))
+          .is_some()
+        {
+          if let Some(previous) = found.filter(|_| !self.overlay) {
+            return Some(
+              error::PathConflict {
+                roots: vec![previous.to_path_buf(), root.to_path_buf()],
+                path,
+              }
+              .fail(),
+            );
+          }
+
+          found = Some(root);
+        }
+      }
+
+      Some(Ok((path, found.unwrap())))
+    }))
+  }
+
+  fn hashes<'a>(
+    &self,
+    tree: impl Iterator<Item = Result<(Utf8PathBuf, &'a Utf8Path)>> + Send,
+    total: u64,
+    progress: Option<&(dyn Fn(Progress) + Sync)>,
+  ) -> Result<(Hashes, HashMap<Hash, Content>, Roots<'a>)> {
+    let done = AtomicU64::new(0);
+
+    let results = tree
+      .par_bridge()
+      .map(|result| {
+        let (relative, root) = result?;
+
+        let path = root.join(&relative);
+
+        let context = error::Io { path: &path };
+
+        let mut file = File::open(&path).context(context)?;
+
+        let len = file.metadata().context(context)?.len();
+
+        let (hash, len, stripped) = match self.stripped(&mut file).context(context)? {
+          Some(stripped) => (blake3::hash(&stripped), stripped.len().into_u64(), true),
+          None => {
+            let mut hasher = Hasher::new();
+            hasher.update_reader(file).context(context)?;
+            (hasher.finalize(), len, false)
+          }
+        };
+
+        if let Some(progress) = progress {
+          progress(Progress::Hashed {
+            path: relative.to_slash(),
+            bytes: len,
+            done: done.fetch_add(1, Ordering::Relaxed) + 1,
+            total,
+          });
+        }
+
+        Ok((relative, root, (hash, len), stripped))
+      })
+      .collect::<Result<Vec<_>>>()?;
+
+    let mut hashes = HashMap::new();
+
+    let mut stripped = HashMap::new();
+
+    let mut roots = HashMap::new();
+
+    for (path, root, (hash, len), content) in results {
+      hashes.insert(path.clone(), (hash, len));
+
+      roots.insert(path, root);
+
+      if content {
+        stripped.insert(hash, Content::Stripped);
+      }
+    }
+
+    Ok((hashes, stripped, roots))
+  }
+
+  // returns the contents of `file` with metadata stripped if it is an image,
+  // `--strip-metadata` was passed, and stripping changed it, otherwise leaves
+  // `file` at its start
+  fn stripped(&self, file: &mut File) -> io::Result<Option<Vec<u8>>> {
+    if !self.strip_metadata {
+      return Ok(None);
+    }
+
+    let mut content = Vec::new();
+
+    (&mut *file).take(12).read_to_end(&mut content)?;
+
+    if strip::supported(&content) {
+      file.read_to_end(&mut content)?;
+
+      if let Some(stripped) = strip::metadata(content.clone()) {
+        if stripped != content {
+          return Ok(Some(stripped));
+        }
+      }
+    }
+
+    file.rewind()?;
+
+    Ok(None)
+  }
+
+  fn ignore(root: &Utf8Path) -> Result<Gitignore> {
+    let path = root.join(Self::IGNORE_PATH);
+
+    let mut builder = GitignoreBuilder::new(root);
+
+    if path.is_file() {
+      if let Some(err) = builder.add(&path) {
+        return Err(err).context(error::Ignore { path });
       }
+    }
+
+    builder.build().context(error::Ignore { path })
+  }
+
+  fn paths<'a>(
+    &self,
+    root: &'a Utf8Path,
+  ) -> Result<impl Iterator<Item = Result<Utf8PathBuf>> + Send + 'a> {
+    let ignore = Self::ignore(root)?;
+
+    let canonical = if self.follow_symlinks {
+      Some(root.canonicalize_utf8().context(error::Io { path: root })?)
+    } else {
+      None
+    };
+
+    Ok(
+      WalkDir::new(root)
+        .follow_links(self.follow_symlinks)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(move |entry| {
+          entry.depth() == 0
+            || !ignore
+              .matched(entry.path(), entry.file_type().is_dir())
+              .is_ignore()
+        })
+        .filter_map(move |result| Self::relative(root, result, canonical.as_deref()).transpose()),
+    )
+  }
+
+  fn relative(
+    root: &Utf8Path,
+    result: walkdir::Result<walkdir::DirEntry>,
+    canonical: Option<&Utf8Path>,
+  ) -> Result<Option<Utf8PathBuf>> {
+    let entry = result.context(error::WalkDir { root })?;
+
+    if entry.path_is_symlink() {
+      let Some(canonical) = canonical else {
+        return Ok(None);
+      };
+
+      let path = entry.path().try_into_utf8()?;
+
+      let target = path.canonicalize_utf8().context(error::Io { path })?;
+
+      ensure!(
+        target.starts_with(canonical),
+        error::SymlinkEscapesRoot { path, target },
+      );
+    }
+
+    if entry.file_type().is_dir() || entry.file_name() == ".DS_Store" {
+      return Ok(None);
+    }
+
+    let path = entry
+      .path()
+      .try_into_utf8()?
+      .strip_prefix(root)
+      .unwrap()
+      .to_owned();
 
-      paths.insert(path);
+    if path == Utf8Path::new(Metadata::PATH) || path == Utf8Path::new(Self::IGNORE_PATH) {
+      return Ok(None);
     }
 
-    Ok(paths)
+    Ok(Some(path))
   }
 }
 
@@ -110,10 +711,11 @@ mod tests {
       let tempdir = tempdir();
 
       let result = Package {
-        root: root.into(),
+        root: vec![root.into()],
         output: Utf8Path::from_path(tempdir.path())
           .unwrap()
           .join("output.package"),
+        ..Default::default()
       }
       .run();
 
@@ -124,12 +726,56 @@ mod tests {
     }
   }
 
+  #[test]
+  fn hashes() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+
+    fs::create_dir(&root).unwrap();
+
+    let mut expected = HashMap::new();
+
+    for i in 0..256 {
+      let path = Utf8PathBuf::from(format!("{i}.jpg"));
+      let content = i.to_string().repeat(i);
+      fs::write(root.join(&path), &content).unwrap();
+      expected.insert(
+        path,
+        (blake3::hash(content.as_bytes()), content.len().into_u64()),
+      );
+    }
+
+    let package = Package {
+      root: vec![root.clone()],
+      ..Default::default()
+    };
+
+    assert_eq!(
+      package
+        .hashes(package.tree(&package.root).unwrap(), 0, None)
+        .unwrap()
+        .0,
+      expected
+    );
+
+    let missing = Utf8PathBuf::from("missing.jpg");
+
+    assert_matches!(
+      package
+        .hashes(iter::once(Ok((missing, root.as_path()))), 1, None)
+        .unwrap_err(),
+      Error::Io { path, .. } if path == root.join("missing.jpg"),
+    );
+  }
+
   #[test]
   fn output_in_root_error() {
     assert_matches!(
       Package {
-        root: "foo".into(),
+        root: vec!["foo".into()],
         output: "foo/bar".into(),
+        ..Default::default()
       }
       .run()
       .unwrap_err(),
@@ -152,8 +798,9 @@ mod tests {
 
     assert_matches!(
       Package {
-        root: "foo".into(),
+        root: vec!["foo".into()],
         output: output_dir.clone(),
+        ..Default::default()
       }
       .run()
       .unwrap_err(),
@@ -176,8 +823,9 @@ mod tests {
 
     assert_matches!(
       Package {
-        root: root_dir.clone(),
+        root: vec![root_dir.clone()],
         output,
+        ..Default::default()
       }
       .run()
       .unwrap_err(),
@@ -202,8 +850,9 @@ mod tests {
 
     assert_matches!(
       Package {
-        root: root_dir.clone(),
+        root: vec![root_dir.clone()],
         output,
+        ..Default::default()
       }
       .run()
       .unwrap_err(),
@@ -244,8 +893,9 @@ mod tests {
     fs::write(root.join("index.js"), "bar").unwrap();
 
     Package {
-      root: root.clone(),
+      root: vec![root.clone()],
       output: output.clone(),
+      ..Default::default()
     }
     .run()
     .unwrap_or_display();
@@ -262,11 +912,11 @@ mod tests {
 
     let manifest = blake3::hash(&manifest_bytes);
 
-    let Manifest::App { handles, paths } = package.manifest else {
+    let Manifest::App { handles, paths, .. } = package.manifest else {
       panic!("unexpected manifest type");
     };
 
-    assert_eq!(handles, Type::Comic);
+    assert_eq!(handles, [Type::Comic].into());
 
     let foo = blake3::hash("foo".as_bytes());
     let bar = blake3::hash("bar".as_bytes());
@@ -281,7 +931,7 @@ mod tests {
   }
 
   #[test]
-  fn comic_package_includes_all_pages() {
+  fn app_mime_overrides() {
     let tempdir = tempdir();
 
     let root = tempdir.path_utf8().join("root");
@@ -289,47 +939,42 @@ mod tests {
 
     fs::create_dir(&root).unwrap();
 
-    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
-    fs::write(root.join("0.jpg"), "foo").unwrap();
-    fs::write(root.join("1.jpg"), "bar").unwrap();
+    fs::write(
+      root.join("metadata.yaml"),
+      "type: app\nhandles: comic\nmime_overrides:\n  wasm: application/wasm\n  index.js: text/x-custom\n",
+    )
+    .unwrap();
+    fs::write(root.join("index.html"), "foo").unwrap();
+    fs::write(root.join("index.js"), "bar").unwrap();
+    fs::write(root.join("app.wasm"), "baz").unwrap();
+    fs::write(root.join("main.js"), "qux").unwrap();
 
     Package {
-      root,
+      root: vec![root.clone()],
       output: output.clone(),
+      ..Default::default()
     }
     .run()
     .unwrap_or_display();
 
     let package = super::super::Package::load(&output).unwrap_or_display();
 
-    assert_eq!(package.files.len(), 3);
-
-    let manifest_bytes = {
-      let mut buffer = Vec::new();
-      ciborium::into_writer(&package.manifest, &mut buffer).unwrap();
-      buffer
-    };
-
-    let manifest = blake3::hash(&manifest_bytes);
-
-    let Manifest::Comic { pages } = package.manifest else {
-      panic!("unexpected manifest type");
-    };
-
-    let foo = blake3::hash("foo".as_bytes());
-    let bar = blake3::hash("bar".as_bytes());
-
-    assert_eq!(pages.len(), 2);
-    assert_eq!(pages[0], foo);
-    assert_eq!(pages[1], bar);
-
-    assert_eq!(package.files[&foo], "foo".as_bytes());
-    assert_eq!(package.files[&bar], "bar".as_bytes());
-    assert_eq!(package.files[&manifest], manifest_bytes);
+    for (path, content_type) in [
+      ("index.html", "text/html"),
+      ("index.js", "text/x-custom"),
+      ("main.js", "text/javascript"),
+      ("app.wasm", "application/wasm"),
+    ] {
+      assert_eq!(
+        package.file(path).unwrap().0.essence_str(),
+        content_type,
+        "{path}",
+      );
+    }
   }
 
   #[test]
-  fn directories_are_ignored() {
+  fn app_redirects() {
     let tempdir = tempdir();
 
     let root = tempdir.path_utf8().join("root");
@@ -337,15 +982,52 @@ mod tests {
 
     fs::create_dir(&root).unwrap();
 
-    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
-    fs::write(root.join("0.jpg"), "").unwrap();
-    fs::create_dir(root.join("bar")).unwrap();
+    fs::write(
+      root.join("metadata.yaml"),
+      "type: app\nhandles: comic\nredirects:\n  /old: /app/new\n  /gone:\n    location: /app/\n    status: 301\n",
+    )
+    .unwrap();
+    fs::write(root.join("index.html"), "foo").unwrap();
+
+    Package {
+      root: vec![root.clone()],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
 
-    Package { root, output }.run().unwrap();
+    let manifest = super::super::Package::load(&output)
+      .unwrap_or_display()
+      .manifest;
+
+    assert_eq!(
+      manifest.redirect("old"),
+      Some(&Redirect::Location("/app/new".into())),
+    );
+    assert_eq!(manifest.redirect("/gone").unwrap().status(), 301);
+    assert_eq!(manifest.redirect("index.html"), None);
+
+    fs::write(
+      root.join("metadata.yaml"),
+      "type: app\nhandles: comic\nredirects:\n  /old:\n    location: /app/new\n    status: 200\n",
+    )
+    .unwrap();
+
+    assert_matches!(
+      Package {
+        root: vec![root],
+        output,
+        ..Default::default()
+      }
+      .run()
+      .unwrap_err(),
+      Error::RedirectStatus { from, status: 200, .. } if from == "/old",
+    );
   }
 
   #[test]
-  fn ds_store_files_are_ignored() {
+  fn app_headers() {
     let tempdir = tempdir();
 
     let root = tempdir.path_utf8().join("root");
@@ -353,41 +1035,80 @@ mod tests {
 
     fs::create_dir(&root).unwrap();
 
-    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
-    fs::write(root.join("0.jpg"), "").unwrap();
-    fs::write(root.join(".DS_Store"), "").unwrap();
+    fs::write(
+      root.join("metadata.yaml"),
+      "type: app\nhandles: comic\nheaders:\n  '*.html':\n    content-security-policy: default-src 'self'\n",
+    )
+    .unwrap();
+    fs::write(root.join("index.html"), "foo").unwrap();
+    fs::write(root.join("index.js"), "bar").unwrap();
 
-    Package { root, output }.run().unwrap();
-  }
+    Package {
+      root: vec![root.clone()],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
 
-  #[test]
-  fn comic_must_have_pages() {
-    let tempdir = tempdir();
+    let manifest = super::super::Package::load(&output)
+      .unwrap_or_display()
+      .manifest;
 
-    let root_dir = tempdir.path_utf8().join("root");
-    let output = tempdir.path_utf8().join("output.package");
+    let Manifest::App { headers, .. } = manifest else {
+      panic!("unexpected manifest type");
+    };
 
-    fs::create_dir(&root_dir).unwrap();
+    assert_eq!(
+      headers,
+      [(
+        "*.html".into(),
+        [(
+          "content-security-policy".into(),
+          "default-src 'self'".into()
+        )]
+        .into()
+      )]
+      .into(),
+    );
 
-    fs::write(root_dir.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(
+      root.join("metadata.yaml"),
+      "type: app\nhandles: comic\nheaders:\n  '[a':\n    x-frame-options: deny\n",
+    )
+    .unwrap();
 
     assert_matches!(
       Package {
-        root: root_dir.clone(),
-        output,
+        root: vec![root.clone()],
+        output: output.clone(),
+        ..Default::default()
       }
       .run()
       .unwrap_err(),
-      Error::NoPages {
-        root,
-        ..
-      }
-      if root == root_dir,
+      Error::InvalidHeaderGlob { glob, .. } if glob == "[a",
     );
-  }
+
+    fs::write(
+      root.join("metadata.yaml"),
+      "type: app\nhandles: comic\nheaders:\n  '*.html':\n    'x frame options': deny\n",
+    )
+    .unwrap();
+
+    assert_matches!(
+      Package {
+        root: vec![root],
+        output,
+        ..Default::default()
+      }
+      .run()
+      .unwrap_err(),
+      Error::InvalidHeaderName { name, .. } if name == "x frame options",
+    );
+  }
 
   #[test]
-  fn comic_page_missing_error() {
+  fn identical_files_are_stored_once() {
     let tempdir = tempdir();
 
     let root = tempdir.path_utf8().join("root");
@@ -396,25 +1117,311 @@ mod tests {
     fs::create_dir(&root).unwrap();
 
     fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
-    fs::write(root.join("1.jpg"), "").unwrap();
+    fs::write(root.join("0.jpg"), jpeg(b"foo")).unwrap();
+    fs::write(root.join("1.jpg"), jpeg(b"foo")).unwrap();
+
+    Package {
+      root: vec![root],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    let package = super::super::Package::load(&output).unwrap_or_display();
+
+    assert_eq!(package.files.len(), 2);
+
+    let foo = blake3::hash(&jpeg(b"foo"));
+
+    assert_eq!(
+      package.manifest,
+      Manifest::Comic {
+        content_types: vec!["image/jpeg".into(), "image/jpeg".into()],
+        dimensions: vec![(1, 1), (1, 1)],
+        pages: vec![foo, foo],
+        provenance: Provenance::default(),
+        thumbnails: Vec::new(),
+      },
+    );
+
+    assert_eq!(package.file("0"), package.file("1"));
+  }
+
+  #[test]
+  fn comic_package_includes_all_pages() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("0.jpg"), jpeg(b"foo")).unwrap();
+    fs::write(root.join("1.jpg"), jpeg(b"bar")).unwrap();
+
+    Package {
+      root: vec![root],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    let package = super::super::Package::load(&output).unwrap_or_display();
+
+    assert_eq!(package.files.len(), 3);
+
+    let manifest_bytes = {
+      let mut buffer = Vec::new();
+      ciborium::into_writer(&package.manifest, &mut buffer).unwrap();
+      buffer
+    };
+
+    let manifest = blake3::hash(&manifest_bytes);
+
+    let Manifest::Comic {
+      content_types,
+      pages,
+      ..
+    } = package.manifest
+    else {
+      panic!("unexpected manifest type");
+    };
+
+    let foo = blake3::hash(&jpeg(b"foo"));
+    let bar = blake3::hash(&jpeg(b"bar"));
+
+    assert_eq!(pages.len(), 2);
+    assert_eq!(pages[0], foo);
+    assert_eq!(pages[1], bar);
+
+    assert_eq!(content_types, ["image/jpeg", "image/jpeg"]);
+
+    assert_eq!(package.files[&foo], jpeg(b"foo"));
+    assert_eq!(package.files[&bar], jpeg(b"bar"));
+    assert_eq!(package.files[&manifest], manifest_bytes);
+  }
+
+  #[test]
+  fn comic_page_content_types() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    let pages = [png(1, 2), webp(3, 4), jpeg(b"qux")];
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("0.png"), &pages[0]).unwrap();
+    fs::write(root.join("1.webp"), &pages[1]).unwrap();
+    fs::write(root.join("2.jpg"), &pages[2]).unwrap();
+
+    Package {
+      root: vec![root],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    let package = super::super::Package::load(&output).unwrap_or_display();
+
+    for ((path, content_type), content) in
+      [("0", "image/png"), ("1", "image/webp"), ("2", "image/jpeg")]
+        .into_iter()
+        .zip(&pages)
+    {
+      assert_eq!(
+        package.manifest.file(path).unwrap().0,
+        content_type.parse::<Mime>().unwrap(),
+      );
+      assert_eq!(&package.file(path).unwrap().1, content);
+    }
+
+    assert_eq!(
+      package
+        .manifest
+        .paths()
+        .into_iter()
+        .map(|(path, _hash)| path)
+        .collect::<Vec<Utf8PathBuf>>(),
+      ["0.png", "1.webp", "2.jpg"],
+    );
+
+    let Manifest::Comic { dimensions, .. } = package.manifest else {
+      panic!("unexpected manifest type");
+    };
+
+    assert_eq!(dimensions, [(1, 2), (3, 4), (1, 1)]);
+  }
+
+  #[test]
+  fn comic_page_dimensions_read_from_header_prefix() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+
+    fs::create_dir(&root).unwrap();
+
+    let mut page = png(7, 8);
+    page.resize(usize::try_from(Metadata::HEADER_PREFIX).unwrap() * 2, 0);
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("0.png"), &page).unwrap();
+
+    let package = Package {
+      root: vec![root.clone()],
+      dry_run: true,
+      ..Default::default()
+    };
+
+    let (hashes, _stripped, _roots) = package
+      .hashes(package.tree(&package.root).unwrap(), 0, None)
+      .unwrap();
+
+    let lengths = Mutex::new(Vec::new());
+
+    let Template::Comic { dimensions, .. } = Metadata::Comic
+      .template(
+        &root,
+        &hashes,
+        |path, limit| {
+          let mut content = Vec::new();
+          File::open(root.join(path))
+            .unwrap()
+            .take(limit)
+            .read_to_end(&mut content)
+            .unwrap();
+          lengths.lock().unwrap().push(content.len().into_u64());
+          Ok(content)
+        },
+        |_path| unreachable!(),
+      )
+      .unwrap()
+    else {
+      panic!("unexpected template type");
+    };
+
+    assert_eq!(dimensions, [(7, 8)]);
+    assert_eq!(*lengths.lock().unwrap(), [Metadata::HEADER_PREFIX]);
+  }
+
+  #[test]
+  fn audio_package_includes_all_tracks() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(
+      root.join("metadata.yaml"),
+      "type: audio\ntitles:\n- Foo\n- Bar",
+    )
+    .unwrap();
+    let tracks = [mp3(100), mp3(50)];
+
+    fs::write(root.join("0.mp3"), &tracks[0]).unwrap();
+    fs::write(root.join("1.mp3"), &tracks[1]).unwrap();
+
+    Package {
+      root: vec![root],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    let package = super::super::Package::load(&output).unwrap_or_display();
+
+    assert_eq!(package.files.len(), 3);
+
+    assert_eq!(
+      package.manifest,
+      Manifest::Audio {
+        durations_ms: vec![Some(2612), Some(1306)],
+        titles: vec!["Foo".into(), "Bar".into()],
+        tracks: tracks.iter().map(|track| blake3::hash(track)).collect(),
+        provenance: Provenance::default(),
+      },
+    );
+
+    assert_eq!(package.manifest.summary().duration_ms, Some(3918));
+
+    assert_eq!(
+      package.file("1"),
+      Some(("audio/mpeg".parse().unwrap(), tracks[1].clone())),
+    );
+    assert_eq!(package.file("2"), None);
+  }
+
+  #[test]
+  fn audio_duration_unknown() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(
+      root.join("metadata.yaml"),
+      "type: audio\ntitles: [foo, bar]",
+    )
+    .unwrap();
+    fs::write(root.join("0.mp3"), "foo").unwrap();
+    fs::write(root.join("1.mp3"), mp3(50)).unwrap();
+
+    Package {
+      root: vec![root],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    let package = super::super::Package::load(&output).unwrap_or_display();
+
+    let Manifest::Audio { durations_ms, .. } = &package.manifest else {
+      panic!("unexpected manifest: {:?}", package.manifest);
+    };
+
+    assert_eq!(*durations_ms, [None, Some(1306)]);
+
+    assert_eq!(package.manifest.summary().duration_ms, None);
+  }
+
+  #[test]
+  fn audio_track_missing_error() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: audio\ntitles: [foo]").unwrap();
+    fs::write(root.join("1.mp3"), "").unwrap();
 
     assert_matches!(
       Package {
-        root,
+        root: vec![root],
         output,
+        ..Default::default()
       }
       .run()
       .unwrap_err(),
-      Error::PageMissing {
-        page,
-        ..
-      }
-      if page == 0,
+      Error::PageMissing { page: 0, .. },
     );
   }
 
   #[test]
-  fn comic_page_duplicated_error() {
+  fn audio_unexpected_file() {
     let tempdir = tempdir();
 
     let root = tempdir.path_utf8().join("root");
@@ -422,27 +1429,29 @@ mod tests {
 
     fs::create_dir(&root).unwrap();
 
-    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
-    fs::write(root.join("0.jpg"), "").unwrap();
-    fs::write(root.join("00.jpg"), "").unwrap();
+    fs::write(root.join("metadata.yaml"), "type: audio\ntitles: [foo]").unwrap();
+    fs::write(root.join("0.mp3"), "").unwrap();
+    fs::write(root.join("1.jpg"), "").unwrap();
 
     assert_matches!(
       Package {
-        root,
+        root: vec![root],
         output,
+        ..Default::default()
       }
       .run()
       .unwrap_err(),
-      Error::PageDuplicated {
-        page,
+      Error::UnexpectedFile {
+        file,
+        ty: Type::Audio,
         ..
       }
-      if page == 0,
+      if file == "1.jpg",
     );
   }
 
   #[test]
-  fn comic_unexpected_file() {
+  fn audio_track_titles_error() {
     let tempdir = tempdir();
 
     let root = tempdir.path_utf8().join("root");
@@ -450,28 +1459,28 @@ mod tests {
 
     fs::create_dir(&root).unwrap();
 
-    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
-    fs::write(root.join("0.jpg"), "").unwrap();
-    fs::write(root.join("foo.jpg"), "").unwrap();
+    fs::write(root.join("metadata.yaml"), "type: audio\ntitles: [foo]").unwrap();
+    fs::write(root.join("0.mp3"), "").unwrap();
+    fs::write(root.join("1.mp3"), "").unwrap();
 
     assert_matches!(
       Package {
-        root,
+        root: vec![root],
         output,
+        ..Default::default()
       }
       .run()
       .unwrap_err(),
-      Error::UnexpectedFile {
-        file,
-        ty,
+      Error::TrackTitles {
+        titles: 1,
+        tracks: 2,
         ..
-      }
-      if file == "foo.jpg" && ty == Type::Comic,
+      },
     );
   }
 
   #[test]
-  fn comic_invalid_page() {
+  fn video_package() {
     let tempdir = tempdir();
 
     let root = tempdir.path_utf8().join("root");
@@ -479,21 +1488,1699 @@ mod tests {
 
     fs::create_dir(&root).unwrap();
 
-    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
-    fs::write(root.join(format!("{}.jpg", u128::from(u64::MAX) + 1)), "").unwrap();
+    fs::write(root.join("metadata.yaml"), "type: video").unwrap();
+    fs::write(root.join("video.mp4"), "foo").unwrap();
+
+    Package {
+      root: vec![root.clone()],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    let package = super::super::Package::load(&output).unwrap_or_display();
+
+    assert_eq!(
+      package.manifest,
+      Manifest::Video {
+        captions: None,
+        video: blake3::hash(b"foo"),
+        provenance: Provenance::default(),
+      },
+    );
+
+    assert_eq!(
+      package.file("video"),
+      Some(("video/mp4".parse().unwrap(), b"foo".to_vec())),
+    );
+    assert_eq!(package.file("captions"), None);
+
+    fs::write(root.join("captions.vtt"), "bar").unwrap();
+
+    Package {
+      root: vec![root],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    let package = super::super::Package::load(&output).unwrap_or_display();
+
+    assert_eq!(
+      package.manifest,
+      Manifest::Video {
+        captions: Some(blake3::hash(b"bar")),
+        video: blake3::hash(b"foo"),
+        provenance: Provenance::default(),
+      },
+    );
+
+    assert_eq!(
+      package.file("captions"),
+      Some(("text/vtt".parse().unwrap(), b"bar".to_vec())),
+    );
+  }
+
+  #[test]
+  fn video_missing_error() {
+    let tempdir = tempdir();
+
+    let root_dir = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root_dir).unwrap();
+
+    fs::write(root_dir.join("metadata.yaml"), "type: video").unwrap();
+    fs::write(root_dir.join("captions.vtt"), "").unwrap();
 
     assert_matches!(
       Package {
-        root,
+        root: vec![root_dir.clone()],
         output,
+        ..Default::default()
       }
       .run()
       .unwrap_err(),
-      Error::InvalidPage {
-        path,
-        ..
-      }
-      if path == "18446744073709551616.jpg",
+      Error::VideoMissing { root, .. } if root == root_dir,
     );
   }
+
+  #[test]
+  fn video_unexpected_file() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: video").unwrap();
+    fs::write(root.join("video.mp4"), "").unwrap();
+    fs::write(root.join("extra.mp4"), "").unwrap();
+
+    assert_matches!(
+      Package {
+        root: vec![root],
+        output,
+        ..Default::default()
+      }
+      .run()
+      .unwrap_err(),
+      Error::UnexpectedFile {
+        file,
+        ty: Type::Video,
+        ..
+      }
+      if file == "extra.mp4",
+    );
+  }
+
+  #[test]
+  fn book_package() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+    let extracted = tempdir.path_utf8().join("extracted");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: book\n").unwrap();
+    fs::write(
+      root.join("toc.yaml"),
+      "- title: Foo\n  chapter: 0\n- title: Bar\n  chapter: 1\n",
+    )
+    .unwrap();
+    fs::write(root.join("chapter-0.html"), "foo").unwrap();
+    fs::write(root.join("chapter-1.html"), "bar").unwrap();
+
+    Package {
+      root: vec![root.clone()],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    let package = super::super::Package::load(&output).unwrap_or_display();
+
+    assert_eq!(package.files.len(), 3);
+
+    assert_eq!(
+      package.manifest,
+      Manifest::Book {
+        chapters: vec![blake3::hash(b"foo"), blake3::hash(b"bar")],
+        toc: vec![("Foo".into(), 0), ("Bar".into(), 1)],
+        provenance: Provenance::default(),
+      },
+    );
+
+    assert_eq!(
+      package.file("chapter/1"),
+      Some((mime::TEXT_HTML, b"bar".to_vec())),
+    );
+    assert_eq!(package.file("chapter/2"), None);
+    assert_eq!(
+      package.file("toc"),
+      Some((mime::APPLICATION_JSON, br#"[["Foo",0],["Bar",1]]"#.to_vec())),
+    );
+
+    package.extract(&extracted).unwrap();
+
+    for path in [
+      "metadata.yaml",
+      "toc.yaml",
+      "chapter-0.html",
+      "chapter-1.html",
+    ] {
+      assert_eq!(
+        fs::read_to_string(extracted.join(path)).unwrap(),
+        fs::read_to_string(root.join(path)).unwrap(),
+      );
+    }
+  }
+
+  #[test]
+  fn book_toc_missing_error() {
+    let tempdir = tempdir();
+
+    let root_dir = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root_dir).unwrap();
+
+    fs::write(root_dir.join("metadata.yaml"), "type: book").unwrap();
+    fs::write(root_dir.join("chapter-0.html"), "").unwrap();
+
+    assert_matches!(
+      Package {
+        root: vec![root_dir.clone()],
+        output,
+        ..Default::default()
+      }
+      .run()
+      .unwrap_err(),
+      Error::TocMissing { root, .. } if root == root_dir,
+    );
+  }
+
+  #[test]
+  fn book_toc_index_out_of_bounds_error() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: book").unwrap();
+    fs::write(root.join("toc.yaml"), "- title: Foo\n  chapter: 1\n").unwrap();
+    fs::write(root.join("chapter-0.html"), "").unwrap();
+
+    assert_matches!(
+      Package {
+        root: vec![root],
+        output,
+        ..Default::default()
+      }
+      .run()
+      .unwrap_err(),
+      Error::TocIndexOutOfBounds {
+        chapter: 1,
+        chapters: 1,
+        title,
+        ..
+      }
+      if title == "Foo",
+    );
+  }
+
+  #[test]
+  fn gallery_package() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+    let extracted = tempdir.path_utf8().join("extracted");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: gallery\n").unwrap();
+    fs::write(root.join("captions.yaml"), "beach.png: The beach\n").unwrap();
+    fs::write(root.join("beach.png"), "foo").unwrap();
+    fs::write(root.join("forest.webp"), "bar").unwrap();
+
+    Package {
+      root: vec![root.clone()],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    let package = super::super::Package::load(&output).unwrap_or_display();
+
+    assert_eq!(package.files.len(), 3);
+
+    assert_eq!(
+      package.manifest,
+      Manifest::Gallery {
+        captions: [("beach.png".into(), "The beach".into())].into(),
+        images: [
+          ("beach.png".into(), blake3::hash(b"foo")),
+          ("forest.webp".into(), blake3::hash(b"bar")),
+        ]
+        .into(),
+        provenance: Provenance::default(),
+        thumbnails: Vec::new(),
+      },
+    );
+
+    assert_eq!(
+      package.file("beach.png"),
+      Some((mime::IMAGE_PNG, b"foo".to_vec())),
+    );
+    assert_eq!(
+      package.file("forest.webp"),
+      Some(("image/webp".parse().unwrap(), b"bar".to_vec())),
+    );
+    assert_eq!(package.file("desert.jpg"), None);
+    assert_eq!(
+      package.file("captions"),
+      Some((
+        mime::APPLICATION_JSON,
+        br#"{"beach.png":"The beach"}"#.to_vec()
+      )),
+    );
+
+    package.extract(&extracted).unwrap();
+
+    for path in ["metadata.yaml", "captions.yaml", "beach.png", "forest.webp"] {
+      assert_eq!(
+        fs::read_to_string(extracted.join(path)).unwrap(),
+        fs::read_to_string(root.join(path)).unwrap(),
+      );
+    }
+  }
+
+  #[test]
+  fn gallery_unexpected_file() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: gallery").unwrap();
+    fs::write(root.join("foo.jpg"), "").unwrap();
+    fs::write(root.join("bar.txt"), "").unwrap();
+
+    assert_matches!(
+      Package {
+        root: vec![root],
+        output,
+        ..Default::default()
+      }
+      .run()
+      .unwrap_err(),
+      Error::UnexpectedFile {
+        file,
+        ty: Type::Gallery,
+        ..
+      }
+      if file == "bar.txt",
+    );
+  }
+
+  #[test]
+  fn comic_and_gallery_accept_the_same_image_extensions() {
+    let tempdir = tempdir();
+
+    let comic = tempdir.path_utf8().join("comic");
+    let gallery = tempdir.path_utf8().join("gallery");
+
+    fs::create_dir(&comic).unwrap();
+    fs::create_dir(&gallery).unwrap();
+
+    fs::write(comic.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(gallery.join("metadata.yaml"), "type: gallery").unwrap();
+
+    for (i, (extension, content)) in [
+      ("JPEG", jpeg(b"")),
+      ("jpg", jpeg(b"")),
+      ("PNG", png(1, 1)),
+      ("webp", webp(1, 1)),
+      ("avif", avif(1, 1)),
+    ]
+    .iter()
+    .enumerate()
+    {
+      fs::write(comic.join(format!("{i}.{extension}")), content).unwrap();
+      fs::write(gallery.join(format!("{i}.{extension}")), content).unwrap();
+    }
+
+    // the `image` crate can't decode AVIF without a native decoder, so the
+    // dimensions of AVIF comic pages can't be read
+    assert_matches!(
+      Package {
+        root: vec![comic.clone()],
+        output: tempdir.path_utf8().join("output.package"),
+        ..Default::default()
+      }
+      .run()
+      .unwrap_err(),
+      Error::ImageDecode { path, .. } if path == comic.join("4.avif"),
+    );
+
+    Package {
+      root: vec![gallery],
+      output: tempdir.path_utf8().join("output.package"),
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+  }
+
+  #[test]
+  fn gallery_caption_image_missing_error() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: gallery").unwrap();
+    fs::write(root.join("captions.yaml"), "bar.jpg: Bar").unwrap();
+    fs::write(root.join("foo.jpg"), "").unwrap();
+
+    assert_matches!(
+      Package {
+        root: vec![root],
+        output,
+        ..Default::default()
+      }
+      .run()
+      .unwrap_err(),
+      Error::CaptionImageMissing { image, .. } if image == "bar.jpg",
+    );
+  }
+
+  #[test]
+  fn directories_are_ignored() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("0.jpg"), jpeg(b"")).unwrap();
+    fs::create_dir(root.join("bar")).unwrap();
+
+    Package {
+      root: vec![root],
+      output,
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+  }
+
+  #[test]
+  fn ds_store_files_are_ignored() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("0.jpg"), jpeg(b"")).unwrap();
+    fs::write(root.join(".DS_Store"), "").unwrap();
+
+    Package {
+      root: vec![root],
+      output,
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+  }
+
+  #[test]
+  fn mediaignore() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+    fs::create_dir(root.join("work")).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join(".mediaignore"), "*.xcf\nThumbs.db\nwork/\n").unwrap();
+    fs::write(root.join("0.jpg"), jpeg(b"foo")).unwrap();
+    fs::write(root.join("0.xcf"), "").unwrap();
+    fs::write(root.join("Thumbs.db"), "").unwrap();
+    fs::write(root.join("work").join("1.jpg"), jpeg(b"")).unwrap();
+
+    Package {
+      root: vec![root],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    let package = super::super::Package::load(&output).unwrap_or_display();
+
+    assert_eq!(package.files.len(), 2);
+    assert!(package.files.contains_key(&blake3::hash(&jpeg(b"foo"))));
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn symlinks() {
+    use std::os::unix::fs::symlink;
+
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+    let outside = tempdir.path_utf8().join("outside.jpg");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("0.jpg"), jpeg(b"foo")).unwrap();
+    fs::write(&outside, "secret").unwrap();
+
+    symlink(root.join("0.jpg"), root.join("1.jpg")).unwrap();
+    symlink(&outside, root.join("2.jpg")).unwrap();
+
+    Package {
+      root: vec![root.clone()],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    assert_eq!(
+      super::super::Package::load(&output)
+        .unwrap_or_display()
+        .entries(),
+      ["0"],
+    );
+
+    assert_matches!(
+      Package {
+        root: vec![root.clone()],
+        output: output.clone(),
+        follow_symlinks: true,
+        ..Default::default()
+      }
+      .run()
+      .unwrap_err(),
+      Error::SymlinkEscapesRoot { path, target, .. }
+      if path == root.join("2.jpg") && target == outside.canonicalize_utf8().unwrap(),
+    );
+
+    fs::remove_file(root.join("2.jpg")).unwrap();
+
+    Package {
+      root: vec![root],
+      output: output.clone(),
+      follow_symlinks: true,
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    let package = super::super::Package::load(&output).unwrap_or_display();
+
+    assert_eq!(package.entries(), ["0", "1"]);
+    assert_eq!(package.file("1").unwrap().1, jpeg(b"foo"));
+  }
+
+  #[test]
+  fn comic_page_dimensions() {
+    let tempdir = tempdir();
+
+    let output = tempdir.path_utf8().join("output.package");
+
+    Package {
+      root: vec!["content/comic".into()],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    let Manifest::Comic { dimensions, .. } = super::super::Package::load(&output)
+      .unwrap_or_display()
+      .manifest
+    else {
+      panic!("unexpected manifest type");
+    };
+
+    assert_eq!(dimensions, [(2, 3); 3]);
+  }
+
+  #[test]
+  fn comic_page_image_decode_error() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("0.jpg"), jpeg(b"foo")).unwrap();
+    fs::write(root.join("1.jpg"), "bar").unwrap();
+
+    assert_matches!(
+      Package {
+        root: vec![root.clone()],
+        output: tempdir.path_utf8().join("output.package"),
+        ..Default::default()
+      }
+      .run()
+      .unwrap_err(),
+      Error::ImageDecode { path, .. }
+      if path == root.join("1.jpg"),
+    );
+  }
+
+  #[test]
+  fn metadata_deserialize_error() {
+    for metadata in ["type: bogus", "type: comic\n  creator: [", "- type: comic"] {
+      let tempdir = tempdir();
+
+      let root = tempdir.path_utf8().join("root");
+      let output = tempdir.path_utf8().join("output.package");
+
+      fs::create_dir(&root).unwrap();
+
+      fs::write(root.join("metadata.yaml"), metadata).unwrap();
+      fs::write(root.join("0.jpg"), "").unwrap();
+
+      let err = Package {
+        root: vec![root.clone()],
+        output,
+        ..Default::default()
+      }
+      .run()
+      .unwrap_err();
+
+      assert_eq!(
+        err.to_string(),
+        format!(
+          "failed to deserialize YAML package metadata at `{}`",
+          root.join("metadata.yaml"),
+        ),
+        "{metadata}",
+      );
+
+      assert_matches!(
+        err,
+        Error::DeserializeMetadata { path, .. } if path == root.join("metadata.yaml"),
+      );
+    }
+  }
+
+  #[test]
+  fn comic_must_have_pages() {
+    let tempdir = tempdir();
+
+    let root_dir = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root_dir).unwrap();
+
+    fs::write(root_dir.join("metadata.yaml"), "type: comic").unwrap();
+
+    assert_matches!(
+      Package {
+        root: vec![root_dir.clone()],
+        output,
+        ..Default::default()
+      }
+      .run()
+      .unwrap_err(),
+      Error::NoPages {
+        root,
+        ..
+      }
+      if root == root_dir,
+    );
+  }
+
+  #[test]
+  fn comic_page_missing_error() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("1.jpg"), "").unwrap();
+
+    assert_matches!(
+      Package {
+        root: vec![root],
+        output,
+        ..Default::default()
+      }
+      .run()
+      .unwrap_err(),
+      Error::PageMissing {
+        page,
+        ..
+      }
+      if page == 0,
+    );
+  }
+
+  #[test]
+  fn progress() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("0.jpg"), jpeg(b"foo")).unwrap();
+    fs::write(root.join("1.jpg"), jpeg(b"quux")).unwrap();
+
+    let events = Mutex::new(Vec::new());
+
+    Package {
+      root: vec![root],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run_with_progress(&|progress| events.lock().unwrap().push(progress))
+    .unwrap_or_display();
+
+    let mut events = events.into_inner().unwrap();
+
+    let saved = events.pop().unwrap();
+
+    events.sort_by_key(|progress| match progress {
+      Progress::Hashed { done, .. } => *done,
+      Progress::Saved { .. } => panic!("unexpected saved event"),
+    });
+
+    let mut paths = events
+      .iter()
+      .map(|progress| match progress {
+        Progress::Hashed {
+          path, bytes, total, ..
+        } => (path.clone(), *bytes, *total),
+        Progress::Saved { .. } => unreachable!(),
+      })
+      .collect::<Vec<(String, u64, u64)>>();
+
+    paths.sort();
+
+    let (foo, quux) = (
+      jpeg(b"foo").len().into_u64(),
+      jpeg(b"quux").len().into_u64(),
+    );
+
+    assert_eq!(paths, [("0.jpg".into(), foo, 2), ("1.jpg".into(), quux, 2)]);
+
+    assert_eq!(
+      events
+        .iter()
+        .map(|progress| match progress {
+          Progress::Hashed { done, .. } => *done,
+          Progress::Saved { .. } => unreachable!(),
+        })
+        .collect::<Vec<u64>>(),
+      [1, 2],
+    );
+
+    assert_eq!(
+      saved,
+      Progress::Saved {
+        output: output.to_string(),
+        files: 2,
+        bytes: foo + quux,
+      },
+    );
+  }
+
+  #[test]
+  fn dry_run() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("0.jpg"), jpeg(b"foo")).unwrap();
+    fs::write(root.join("1.jpg"), jpeg(b"quux")).unwrap();
+
+    let package = Package {
+      root: vec![root.clone()],
+      output: output.clone(),
+      dry_run: true,
+      ..Default::default()
+    };
+
+    let (hashes, _stripped, _roots) = package
+      .hashes(package.tree(&package.root).unwrap(), 0, None)
+      .unwrap();
+
+    let manifest = Metadata::Comic
+      .template(
+        &root,
+        &hashes,
+        |path, _limit| Ok(fs::read(root.join(path)).unwrap()),
+        |_path| unreachable!(),
+      )
+      .unwrap()
+      .manifest(&hashes);
+
+    assert_eq!(
+      Package::dry_run(&hashes, &manifest),
+      format!(
+        "0.jpg {}\n1.jpg {}\n{{\"type\":\"comic\",\"pages\":2}}\n",
+        jpeg(b"foo").len(),
+        jpeg(b"quux").len(),
+      ),
+    );
+
+    package.run().unwrap_or_display();
+
+    assert!(!output.exists());
+
+    fs::write(root.join("3.jpg"), jpeg(b"")).unwrap();
+
+    assert_matches!(
+      Package {
+        root: vec![root],
+        output: output.clone(),
+        dry_run: true,
+        ..Default::default()
+      }
+      .run()
+      .unwrap_err(),
+      Error::PageMissing { page: 2, .. },
+    );
+
+    assert!(!output.exists());
+  }
+
+  #[test]
+  fn provenance() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+    let destination = tempdir.path_utf8().join("destination");
+    let repackaged = tempdir.path_utf8().join("repackaged.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(
+      root.join("metadata.yaml"),
+      "type: comic\ncreated_at: 2024-06-01T12:30:00+02:00\ncreator: Alice\n",
+    )
+    .unwrap();
+    fs::write(root.join("0.jpg"), jpeg(b"foo")).unwrap();
+
+    Package {
+      root: vec![root],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    let package = super::super::Package::load(&output).unwrap_or_display();
+
+    let provenance = Provenance {
+      created_at: Some("2024-06-01T12:30:00+02:00".into()),
+      creator: Some("Alice".into()),
+    };
+
+    assert_eq!(package.manifest.provenance(), &provenance);
+
+    let json = serde_json::from_slice::<serde_json::Value>(&package.manifest_json()).unwrap();
+    assert_eq!(json["created_at"], "2024-06-01T12:30:00+02:00");
+    assert_eq!(json["creator"], "Alice");
+
+    assert_eq!(
+      super::super::Package::open(&output)
+        .unwrap()
+        .info()
+        .provenance,
+      provenance,
+    );
+
+    package.extract(&destination).unwrap();
+
+    Package {
+      root: vec![destination],
+      output: repackaged.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    assert_eq!(
+      super::super::Package::load(&repackaged).unwrap_or_display(),
+      package,
+    );
+  }
+
+  #[test]
+  fn comic_page_content_type_sniffed() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("0.jpg"), png(1, 1)).unwrap();
+    fs::write(root.join("1.jpg"), jpeg(b"")).unwrap();
+    fs::write(root.join("2.jpg"), gif(1, 1)).unwrap();
+
+    Package {
+      root: vec![root],
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    let package = super::super::Package::load(&output).unwrap_or_display();
+
+    for (path, content_type) in [
+      ("0", mime::IMAGE_PNG),
+      ("1", mime::IMAGE_JPEG),
+      ("2", mime::IMAGE_GIF),
+    ] {
+      assert_eq!(package.file(path).unwrap().0, content_type);
+    }
+  }
+
+  #[test]
+  fn comic_zero_padded_pages() {
+    for (start, first) in [(0, "000.jpg"), (1, "001.jpg")] {
+      let tempdir = tempdir();
+
+      let root = tempdir.path_utf8().join("root");
+      let output = tempdir.path_utf8().join("output.package");
+
+      fs::create_dir(&root).unwrap();
+
+      fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+
+      for page in start..start + 150 {
+        fs::write(
+          root.join(format!("{page:03}.jpg")),
+          jpeg(page.to_string().as_bytes()),
+        )
+        .unwrap();
+      }
+
+      assert!(root.join(first).is_file());
+
+      Package {
+        root: vec![root],
+        output: output.clone(),
+        ..Default::default()
+      }
+      .run()
+      .unwrap_or_display();
+
+      let package = super::super::Package::load(&output).unwrap_or_display();
+
+      assert_eq!(package.entries().len(), 150);
+      assert_eq!(
+        package.file("0").unwrap().1,
+        jpeg(start.to_string().as_bytes()),
+      );
+      assert_eq!(
+        package.file("149").unwrap().1,
+        jpeg((start + 149).to_string().as_bytes()),
+      );
+    }
+  }
+
+  #[test]
+  fn comic_padded_and_unpadded_page_duplicated_error() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("001.jpg"), "").unwrap();
+    fs::write(root.join("1.jpg"), "").unwrap();
+    fs::write(root.join("002.jpg"), "").unwrap();
+
+    assert_matches!(
+      Package {
+        root: vec![root],
+        output,
+        ..Default::default()
+      }
+      .run()
+      .unwrap_err(),
+      Error::PageDuplicated { page: 1, .. },
+    );
+  }
+
+  #[test]
+  fn comic_page_duplicated_error() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("0.jpg"), "").unwrap();
+    fs::write(root.join("00.jpg"), "").unwrap();
+
+    assert_matches!(
+      Package {
+        root: vec![root],
+        output,
+        ..Default::default()
+      }
+      .run()
+      .unwrap_err(),
+      Error::PageDuplicated {
+        page,
+        ..
+      }
+      if page == 0,
+    );
+  }
+
+  #[test]
+  fn comic_unexpected_file() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("0.jpg"), "").unwrap();
+    fs::write(root.join("foo.jpg"), "").unwrap();
+
+    assert_matches!(
+      Package {
+        root: vec![root],
+        output,
+        ..Default::default()
+      }
+      .run()
+      .unwrap_err(),
+      Error::UnexpectedFile {
+        file,
+        ty,
+        ..
+      }
+      if file == "foo.jpg" && ty == Type::Comic,
+    );
+  }
+
+  #[test]
+  fn comic_invalid_page() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join(format!("{}.jpg", u128::from(u64::MAX) + 1)), "").unwrap();
+
+    assert_matches!(
+      Package {
+        root: vec![root],
+        output,
+        ..Default::default()
+      }
+      .run()
+      .unwrap_err(),
+      Error::InvalidPage {
+        path,
+        ..
+      }
+      if path == "18446744073709551616.jpg",
+    );
+  }
+
+  #[test]
+  fn tar_package_matches_directory_package() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let archive = tempdir.path_utf8().join("root.tar");
+    let expected = tempdir.path_utf8().join("expected.package");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&root).unwrap();
+
+    let files = [
+      ("metadata.yaml", b"type: comic\ncreator: foo".to_vec()),
+      (".mediaignore", b"*.xcf".to_vec()),
+      ("0.jpg", jpeg(b"foo")),
+      ("1.jpg", jpeg(b"bar")),
+      ("1.xcf", Vec::new()),
+      (".DS_Store", Vec::new()),
+    ];
+
+    for (path, content) in &files {
+      fs::write(root.join(path), content).unwrap();
+    }
+
+    fs::write(
+      &archive,
+      tar(
+        &files
+          .iter()
+          .map(|(path, content)| (*path, content.as_slice()))
+          .collect::<Vec<(&str, &[u8])>>(),
+      ),
+    )
+    .unwrap();
+
+    Package {
+      root: vec![root],
+      output: expected.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    Package {
+      tar: Some(archive),
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    assert_eq!(fs::read(output).unwrap(), fs::read(expected).unwrap());
+  }
+
+  #[test]
+  fn tar_gallery_matches_directory_package() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let archive = tempdir.path_utf8().join("root.tar");
+    let expected = tempdir.path_utf8().join("expected.package");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir_all(root.join("work")).unwrap();
+
+    let files = [
+      ("metadata.yaml", b"type: gallery".to_vec()),
+      (".mediaignore", b"work/".to_vec()),
+      ("captions.yaml", b"large.png: Large".to_vec()),
+      ("large.png", png(512, 2)),
+      ("small.png", png(16, 16)),
+      ("work/draft.xcf", Vec::new()),
+    ];
+
+    for (path, content) in &files {
+      fs::write(root.join(path), content).unwrap();
+    }
+
+    fs::write(
+      &archive,
+      tar(
+        &files
+          .iter()
+          .map(|(path, content)| (*path, content.as_slice()))
+          .collect::<Vec<(&str, &[u8])>>(),
+      ),
+    )
+    .unwrap();
+
+    Package {
+      root: vec![root],
+      output: expected.clone(),
+      thumbnails: true,
+      compress: true,
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    Package {
+      tar: Some(archive),
+      output: output.clone(),
+      thumbnails: true,
+      compress: true,
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    assert_eq!(fs::read(output).unwrap(), fs::read(expected).unwrap());
+  }
+
+  #[test]
+  fn tar_long_paths() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let archive = tempdir.path_utf8().join("root.tar");
+    let expected = tempdir.path_utf8().join("expected.package");
+    let output = tempdir.path_utf8().join("output.package");
+
+    let long = format!("{}/0.jpg", "a".repeat(120));
+
+    fs::create_dir_all(root.join("a".repeat(120))).unwrap();
+    fs::write(root.join("metadata.yaml"), "type: gallery").unwrap();
+    fs::write(root.join(&long), jpeg(b"foo")).unwrap();
+
+    let mut builder = tar::Builder::new(File::create(&archive).unwrap());
+    builder.mode(tar::HeaderMode::Deterministic);
+    builder
+      .append_path_with_name(root.join("metadata.yaml"), "./metadata.yaml")
+      .unwrap();
+    builder
+      .append_path_with_name(root.join(&long), &long)
+      .unwrap();
+    builder.finish().unwrap();
+
+    Package {
+      root: vec![root],
+      output: expected.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    Package {
+      tar: Some(archive),
+      output: output.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    assert_eq!(fs::read(output).unwrap(), fs::read(expected).unwrap());
+  }
+
+  #[test]
+  fn tar_metadata_missing_error() {
+    let tempdir = tempdir();
+
+    let archive = tempdir.path_utf8().join("root.tar");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::write(&archive, tar(&[("0.jpg", &jpeg(b"foo"))])).unwrap();
+
+    assert_matches!(
+      Package {
+        tar: Some(archive.clone()),
+        output,
+        ..Default::default()
+      }
+      .run()
+      .unwrap_err(),
+      Error::MetadataMissing {
+        root,
+        ..
+      }
+      if root == archive,
+    );
+  }
+
+  #[test]
+  fn tar_entry_path_error() {
+    let tempdir = tempdir();
+
+    let archive = tempdir.path_utf8().join("root.tar");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::write(
+      &archive,
+      tar(&[
+        ("metadata.yaml", b"type: comic"),
+        ("../0.jpg", &jpeg(b"foo")),
+      ]),
+    )
+    .unwrap();
+
+    assert_matches!(
+      Package {
+        tar: Some(archive),
+        output,
+        ..Default::default()
+      }
+      .run()
+      .unwrap_err(),
+      Error::TarEntryPath {
+        path,
+        ..
+      }
+      if path == "../0.jpg",
+    );
+  }
+
+  #[track_caller]
+  fn assert_roots_match(roots: &[&[(&str, &[u8])]], merged: &[(&str, &[u8])], overlay: bool) {
+    let tempdir = tempdir();
+
+    let expected = tempdir.path_utf8().join("expected.package");
+    let output = tempdir.path_utf8().join("output.package");
+
+    let mut dirs = Vec::new();
+
+    for (i, files) in roots.iter().chain([&merged]).enumerate() {
+      let root = tempdir.path_utf8().join(i.to_string());
+
+      fs::create_dir(&root).unwrap();
+
+      for (path, content) in *files {
+        fs::write(root.join(path), content).unwrap();
+      }
+
+      dirs.push(root);
+    }
+
+    Package {
+      root: vec![dirs.pop().unwrap()],
+      output: expected.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    Package {
+      root: dirs,
+      output: output.clone(),
+      overlay,
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    assert_eq!(fs::read(output).unwrap(), fs::read(expected).unwrap());
+  }
+
+  #[test]
+  fn roots_are_merged() {
+    assert_roots_match(
+      &[
+        &[("metadata.yaml", b"type: comic"), ("0.jpg", &jpeg(b"foo"))],
+        &[("1.jpg", &jpeg(b"bar"))],
+      ],
+      &[
+        ("metadata.yaml", b"type: comic"),
+        ("0.jpg", &jpeg(b"foo")),
+        ("1.jpg", &jpeg(b"bar")),
+      ],
+      false,
+    );
+  }
+
+  #[test]
+  fn tree_merges_roots_in_path_order() {
+    let tempdir = tempdir();
+
+    let a = tempdir.path_utf8().join("a");
+    let b = tempdir.path_utf8().join("b");
+
+    for path in ["a/x", "b", "d/z"] {
+      fs::create_dir_all(a.join(path).parent().unwrap()).unwrap();
+      fs::write(a.join(path), "").unwrap();
+    }
+
+    for path in ["a.txt", "a/y", "c", "d/z"] {
+      fs::create_dir_all(b.join(path).parent().unwrap()).unwrap();
+      fs::write(b.join(path), "").unwrap();
+    }
+
+    let package = Package {
+      root: vec![a.clone(), b.clone()],
+      overlay: true,
+      ..Default::default()
+    };
+
+    assert_eq!(
+      package
+        .tree(&package.root)
+        .unwrap()
+        .collect::<Result<Vec<_>>>()
+        .unwrap(),
+      [
+        ("a/x".into(), a.as_path()),
+        ("a/y".into(), b.as_path()),
+        ("a.txt".into(), b.as_path()),
+        ("b".into(), a.as_path()),
+        ("c".into(), b.as_path()),
+        ("d/z".into(), b.as_path()),
+      ],
+    );
+  }
+
+  #[test]
+  fn overlay_last_root_wins() {
+    assert_roots_match(
+      &[
+        &[
+          ("metadata.yaml", b"type: comic\ncreator: foo"),
+          ("0.jpg", &jpeg(b"foo")),
+          ("1.jpg", &jpeg(b"bar")),
+        ],
+        &[
+          ("metadata.yaml", b"type: comic\ncreator: bar"),
+          ("1.jpg", &jpeg(b"baz")),
+        ],
+      ],
+      &[
+        ("metadata.yaml", b"type: comic\ncreator: bar"),
+        ("0.jpg", &jpeg(b"foo")),
+        ("1.jpg", &jpeg(b"baz")),
+      ],
+      true,
+    );
+  }
+
+  #[test]
+  fn root_path_conflict_error() {
+    let tempdir = tempdir();
+
+    let a = tempdir.path_utf8().join("a");
+    let b = tempdir.path_utf8().join("b");
+    let output = tempdir.path_utf8().join("output.package");
+
+    fs::create_dir(&a).unwrap();
+    fs::create_dir(&b).unwrap();
+
+    fs::write(a.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(a.join("0.jpg"), jpeg(b"foo")).unwrap();
+    fs::write(b.join("0.jpg"), jpeg(b"bar")).unwrap();
+
+    assert_matches!(
+      Package {
+        root: vec![a.clone(), b.clone()],
+        output: output.clone(),
+        ..Default::default()
+      }
+      .run()
+      .unwrap_err(),
+      Error::PathConflict {
+        path,
+        roots,
+        ..
+      }
+      if path == "0.jpg" && roots == [a.clone(), b.clone()],
+    );
+
+    fs::write(b.join("metadata.yaml"), "type: comic").unwrap();
+
+    assert_matches!(
+      Package {
+        root: vec![a.clone(), b.clone()],
+        output,
+        ..Default::default()
+      }
+      .run()
+      .unwrap_err(),
+      Error::PathConflict {
+        path,
+        roots,
+        ..
+      }
+      if path == "metadata.yaml" && roots == [a, b],
+    );
+  }
+
+  #[test]
+  fn output_is_deterministic() {
+    for root in ["apps/comic", "content/comic"] {
+      for compress in [false, true] {
+        let tempdir = tempdir();
+
+        let outputs = ["a.package", "b.package"].map(|output| tempdir.path_utf8().join(output));
+
+        for output in &outputs {
+          Package {
+            root: vec![root.into()],
+            output: output.clone(),
+            compress,
+            ..Default::default()
+          }
+          .run()
+          .unwrap_or_display();
+        }
+
+        assert_eq!(
+          fs::read(&outputs[0]).unwrap(),
+          fs::read(&outputs[1]).unwrap(),
+        );
+
+        assert_eq!(
+          super::super::Package::fingerprint(&outputs[0]).unwrap(),
+          super::super::Package::fingerprint(&outputs[1]).unwrap(),
+        );
+      }
+    }
+  }
+
+  #[test]
+  fn strip_metadata() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let archive = tempdir.path_utf8().join("root.tar");
+
+    fs::create_dir(&root).unwrap();
+
+    let image = b"\xFF\xC0\x00\x0B\x08\x00\x01\x00\x01\x01\x01\x11\x00\xFF\xDA\x00\x08\x01\x01\x00\x00\x3F\x00foo\xFF\xD9";
+
+    let exif = [b"\xFF\xD8\xFF\xE1\x00\x08Exif\0\0".as_slice(), image].concat();
+
+    let stripped = [b"\xFF\xD8".as_slice(), image].concat();
+
+    let files = [
+      ("metadata.yaml", b"type: app\nhandles: comic".as_slice()),
+      ("index.html", b"<html><img src=photo.jpg></html>"),
+      ("photo.jpg", &exif),
+    ];
+
+    for (path, content) in files {
+      fs::write(root.join(path), content).unwrap();
+    }
+
+    fs::write(&archive, tar(&files)).unwrap();
+
+    for (root, tar) in [(vec![root], None), (Vec::new(), Some(archive))] {
+      let output = tempdir.path_utf8().join("output.package");
+
+      Package {
+        root,
+        tar,
+        output: output.clone(),
+        strip_metadata: true,
+        ..Default::default()
+      }
+      .run()
+      .unwrap_or_display();
+
+      let package = super::super::Package::load(&output).unwrap_or_display();
+
+      assert_eq!(
+        package.get(blake3::hash(&stripped)),
+        Some(stripped.as_slice())
+      );
+
+      assert!(!package.contains(blake3::hash(&exif)));
+
+      assert_eq!(package.get(blake3::hash(files[1].1)), Some(files[1].1),);
+    }
+  }
+
+  #[test]
+  fn strip_metadata_same_length() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let archive = tempdir.path_utf8().join("root.tar");
+
+    fs::create_dir(&root).unwrap();
+
+    let plain = webp(1, 1);
+
+    // a VP8X chunk whose EXIF flag is set without an EXIF chunk, so stripping
+    // clears the flag without changing the length
+    let flagged = {
+      let vp8x = [b"VP8X".as_slice(), &10u32.to_le_bytes(), &[0x08], &[0; 9]].concat();
+      let chunks = [b"WEBP".as_slice(), &vp8x, &plain[12..]].concat();
+      let size = u32::try_from(chunks.len()).unwrap().to_le_bytes();
+      [b"RIFF".as_slice(), &size, &chunks].concat()
+    };
+
+    let cleared = [&flagged[..20], &[0x00], &flagged[21..]].concat();
+
+    assert_eq!(flagged.len(), cleared.len());
+
+    let files = [
+      ("metadata.yaml", b"type: gallery".as_slice()),
+      ("0.webp", &plain),
+      ("1.webp", &flagged),
+    ];
+
+    for (path, content) in files {
+      fs::write(root.join(path), content).unwrap();
+    }
+
+    fs::write(&archive, tar(&files)).unwrap();
+
+    for (root, tar) in [(vec![root], None), (Vec::new(), Some(archive))] {
+      let output = tempdir.path_utf8().join("output.package");
+
+      Package {
+        root,
+        tar,
+        output: output.clone(),
+        strip_metadata: true,
+        ..Default::default()
+      }
+      .run()
+      .unwrap_or_display();
+
+      let package = super::super::Package::load(&output).unwrap_or_display();
+
+      assert_eq!(package.get(blake3::hash(&plain)), Some(plain.as_slice()));
+
+      assert_eq!(
+        package.get(blake3::hash(&cleared)),
+        Some(cleared.as_slice())
+      );
+
+      assert!(!package.contains(blake3::hash(&flagged)));
+    }
+  }
+
+  #[test]
+  fn thumbnails() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+    let archive = tempdir.path_utf8().join("root.tar");
+
+    fs::create_dir(&root).unwrap();
+
+    let large = png(512, 2);
+    let small = png(16, 16);
+
+    let files = [
+      ("metadata.yaml", b"type: comic".as_slice()),
+      ("0.png", &large),
+      ("1.png", &small),
+    ];
+
+    for (path, content) in files {
+      fs::write(root.join(path), content).unwrap();
+    }
+
+    fs::write(&archive, tar(&files)).unwrap();
+
+    for (root, tar) in [(vec![root], None), (Vec::new(), Some(archive))] {
+      let output = tempdir.path_utf8().join("output.package");
+
+      Package {
+        root,
+        tar,
+        output: output.clone(),
+        thumbnails: true,
+        ..Default::default()
+      }
+      .run()
+      .unwrap_or_display();
+
+      let package = super::super::Package::load(&output).unwrap_or_display();
+
+      let Manifest::Comic {
+        pages, thumbnails, ..
+      } = &package.manifest
+      else {
+        panic!("unexpected manifest: {:?}", package.manifest);
+      };
+
+      assert_eq!(*pages, [blake3::hash(&large), blake3::hash(&small)]);
+      assert_eq!(thumbnails.len(), 2);
+      assert_ne!(thumbnails[0], Some(pages[0]));
+      assert_eq!(thumbnails[1], Some(pages[1]));
+
+      assert_eq!(package.entries(), ["0", "1", "thumb/0", "thumb/1"]);
+
+      let (content_type, thumbnail) = package.file("thumb/0").unwrap();
+      assert_eq!(content_type, mime::IMAGE_PNG);
+      assert_eq!(sniff::dimensions(&thumbnail), Some((256, 1)));
+
+      assert_eq!(package.file("thumb/1").unwrap().1, small);
+    }
+
+    let gallery = tempdir.path_utf8().join("gallery");
+    let output = tempdir.path_utf8().join("gallery.package");
+
+    fs::create_dir(&gallery).unwrap();
+
+    fs::write(gallery.join("metadata.yaml"), "type: gallery").unwrap();
+    fs::write(gallery.join("a.png"), &large).unwrap();
+    fs::write(gallery.join("b.avif"), avif(512, 512)).unwrap();
+
+    Package {
+      root: vec![gallery],
+      output: output.clone(),
+      thumbnails: true,
+      ..Default::default()
+    }
+    .run()
+    .unwrap_or_display();
+
+    let package = super::super::Package::load(&output).unwrap_or_display();
+
+    let Manifest::Gallery { thumbnails, .. } = &package.manifest else {
+      panic!("unexpected manifest: {:?}", package.manifest);
+    };
+
+    assert!(thumbnails[0].is_some());
+    assert_eq!(thumbnails[1], None);
+
+    assert_eq!(
+      package.entries(),
+      ["a.png", "b.avif", "captions", "thumb/0"]
+    );
+
+    assert_eq!(package.file("thumb/1"), None);
+  }
 }
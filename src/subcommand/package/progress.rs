@@ -0,0 +1,52 @@
+use super::*;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "event")]
+pub enum Progress {
+  Hashed {
+    path: String,
+    bytes: u64,
+    done: u64,
+    total: u64,
+  },
+  Saved {
+    output: String,
+    files: u64,
+    bytes: u64,
+  },
+}
+
+impl Progress {
+  pub fn json(&self) -> String {
+    serde_json::to_string(self).unwrap()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn json() {
+    assert_eq!(
+      Progress::Hashed {
+        path: "0.jpg".into(),
+        bytes: 3,
+        done: 1,
+        total: 2,
+      }
+      .json(),
+      r#"{"event":"hashed","path":"0.jpg","bytes":3,"done":1,"total":2}"#,
+    );
+
+    assert_eq!(
+      Progress::Saved {
+        output: "foo.package".into(),
+        files: 2,
+        bytes: 6,
+      }
+      .json(),
+      r#"{"event":"saved","output":"foo.package","files":2,"bytes":6}"#,
+    );
+  }
+}
@@ -0,0 +1,89 @@
+use super::*;
+
+#[derive(Parser)]
+pub struct Repack {
+  #[arg(help = "Repack <INPUT> package…")]
+  pub input: Utf8PathBuf,
+  #[arg(help = "…to <OUTPUT> with the current package format.")]
+  pub output: Utf8PathBuf,
+  #[arg(long, help = "Compress package files.")]
+  pub compress: bool,
+}
+
+impl Repack {
+  pub fn run(self) -> Result {
+    let package = Package::load(&self.input).context(error::PackageLoad { path: &self.input })?;
+
+    package
+      .repack(&self.output, self.compress)
+      .context(error::PackageSave { path: &self.output })?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn repack() {
+    let tempdir = tempdir();
+
+    let input = tempdir.path_utf8().join("input.package");
+    let output = tempdir.path_utf8().join("output.package");
+
+    subcommand::package::Package {
+      root: vec!["content/comic".into()],
+      output: input.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    Repack {
+      input: input.clone(),
+      output: output.clone(),
+      compress: false,
+    }
+    .run()
+    .unwrap();
+
+    assert_eq!(fs::read(&output).unwrap(), fs::read(&input).unwrap());
+
+    Repack {
+      input: input.clone(),
+      output: output.clone(),
+      compress: true,
+    }
+    .run()
+    .unwrap();
+
+    assert!(fs::metadata(&output).unwrap().len() < fs::metadata(&input).unwrap().len());
+
+    let input = Package::load(&input).unwrap();
+    let output = Package::load(&output).unwrap();
+
+    assert_eq!(output.manifest_hash(), input.manifest_hash());
+    assert_eq!(output, input);
+  }
+
+  #[test]
+  fn package_load_error() {
+    let tempdir = tempdir();
+
+    let input = tempdir.path_utf8().join("input.package");
+
+    assert_matches!(
+      Repack {
+        input: input.clone(),
+        output: tempdir.path_utf8().join("output.package"),
+        compress: false,
+      }
+      .run()
+      .unwrap_err(),
+      Error::PackageLoad { path, .. }
+      if path == input,
+    );
+  }
+}
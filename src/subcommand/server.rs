@@ -1,311 +1,3619 @@
-use super::*;
+use {
+  self::{
+    custom_headers::CustomHeaders,
+    encoding::{Compressed, Encoding},
+    manifest_format::ManifestFormat,
+  },
+  super::*,
+  axum::body::HttpBody as _,
+  axum::{
+    handler::Handler,
+    http::{HeaderMap, HeaderValue},
+    routing::MethodRouter,
+  },
+  axum_server::{tls_rustls::RustlsConfig, Handle},
+  std::{
+    future::{self, Future},
+    mem,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    ops::RangeInclusive,
+    str::FromStr,
+    sync::RwLock,
+    time::{Duration, SystemTime},
+  },
+  tokio::sync::Semaphore,
+  tower_http::trace::TraceLayer,
+};
+
+mod cors;
+mod custom_headers;
+mod encoding;
+mod inject;
+mod limit;
+mod manifest_format;
+mod range;
 
 #[derive(Parser)]
 pub struct Server {
-  #[arg(long, help = "Listen on <ADDRESS> for incoming requests.")]
-  address: SocketAddr,
+  #[arg(
+    long,
+    help = "Listen on <ADDRESS> for incoming requests.",
+    required_unless_present = "systemd"
+  )]
+  address: Option<SocketAddr>,
   #[arg(
     long,
     help = "Serve contents with app <PACKAGE>.",
     value_name = "PACKAGE"
   )]
   app: Utf8PathBuf,
-  #[arg(long, help = "Serve contents of <PACKAGE>.", value_name = "PACKAGE")]
-  content: Utf8PathBuf,
+  #[arg(
+    long,
+    help = "Mount all routes under <PREFIX>, e.g. `/reader`.",
+    value_name = "PREFIX"
+  )]
+  base_path: Option<String>,
+  #[arg(
+    long,
+    help = "Serve contents of <PACKAGE>, optionally named with `<NAME>=<PACKAGE>`. May be given more than once, in which case content is served from `/content/<NAME>/`. May be an `http://` or `https://` URL when built with the `reqwest` feature.",
+    required = true,
+    value_name = "PACKAGE"
+  )]
+  content: Vec<NamedPackage>,
+  #[arg(
+    long,
+    help = "Cache up to <BYTES> of recently served files in memory. Requires `--verify-on-read`.",
+    requires = "verify_on_read",
+    value_name = "BYTES"
+  )]
+  cache_bytes: Option<u64>,
+  #[arg(
+    long,
+    help = "Allow cross-origin requests from <ORIGIN>. May be given more than once.",
+    value_name = "ORIGIN"
+  )]
+  cors_allow_origin: Vec<String>,
+  #[arg(
+    long,
+    default_value = "info",
+    help = "Log requests with filter <LEVEL>, e.g. `info`, `debug`, or `media=debug`.",
+    value_name = "LEVEL"
+  )]
+  log_level: String,
+  #[arg(
+    long,
+    help = "Inject the content manifest into app `index.html` served at `/` as `window.__MANIFEST__`."
+  )]
+  inject_manifest: bool,
+  #[arg(
+    long,
+    help = "Serve at most <N> requests concurrently, responding to additional requests with `503 Service Unavailable`. By default concurrency is unlimited.",
+    value_name = "N"
+  )]
+  max_connections: Option<usize>,
+  #[arg(
+    long,
+    conflicts_with = "verify_on_read",
+    help = "Memory-map packages instead of reading them into memory, verifying each file the first time it is served."
+  )]
+  mmap: bool,
+  #[arg(
+    long,
+    help = "Don't send `Cache-Control: immutable` for app and content files."
+  )]
+  no_cache: bool,
+  #[arg(
+    long,
+    help = "Open the app in the default browser once the server is listening."
+  )]
+  open: bool,
+  #[arg(
+    long,
+    help = "Serve app `index.html` for app paths that are not found, for single-page apps."
+  )]
+  spa: bool,
+  #[arg(
+    long,
+    conflicts_with = "address",
+    help = "Listen on the socket passed by systemd socket activation instead of binding `--address`."
+  )]
+  systemd: bool,
+  #[arg(
+    long,
+    help = "Serve over HTTPS with PEM certificate chain <PEM>. Requires `--tls-key`.",
+    value_name = "PEM"
+  )]
+  tls_cert: Option<Utf8PathBuf>,
+  #[arg(
+    long,
+    help = "Serve over HTTPS with PEM private key <PEM>. Requires `--tls-cert`.",
+    value_name = "PEM"
+  )]
+  tls_key: Option<Utf8PathBuf>,
+  #[arg(
+    long,
+    help = "Open packages lazily, verifying each file the first time it is served."
+  )]
+  verify_on_read: bool,
+  #[arg(
+    long,
+    help = "Reload packages when they change on disk. Implies `--no-cache`, since files at the same path may change."
+  )]
+  watch: bool,
 }
 
-#[derive(Debug)]
-struct State {
-  app: Package,
-  content: Package,
-}
+type Shared = Arc<RwLock<Arc<State>>>;
 
-#[derive(Debug)]
-struct Resource {
-  content_type: Mime,
-  content: Vec<u8>,
+#[derive(Clone, Debug, PartialEq)]
+struct NamedPackage {
+  name: String,
+  path: Utf8PathBuf,
 }
 
-impl Resource {
-  fn new(content_type: Mime, content: Vec<u8>) -> Self {
+impl From<Utf8PathBuf> for NamedPackage {
+  fn from(path: Utf8PathBuf) -> Self {
     Self {
-      content_type,
-      content,
+      name: path.file_stem().unwrap_or(path.as_str()).into(),
+      path,
     }
   }
 }
 
-impl IntoResponse for Resource {
-  fn into_response(self) -> axum::http::Response<axum::body::Body> {
-    (
-      [(header::CONTENT_TYPE, self.content_type.to_string())],
-      self.content,
+impl FromStr for NamedPackage {
+  type Err = Infallible;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Ok(
+      match s
+        .split_once('=')
+        .filter(|(name, _path)| !name.contains("://"))
+      {
+        Some((name, path)) => Self {
+          name: name.into(),
+          path: path.into(),
+        },
+        None => Utf8PathBuf::from(s).into(),
+      },
     )
-      .into_response()
   }
 }
 
-#[derive(Debug, PartialEq)]
-pub enum ServerError {
-  NotFound { path: String },
+#[derive(Debug)]
+enum Source {
+  Loaded(Arc<Package>),
+  Lazy(PackageReader),
+  Mapped(MappedPackage),
 }
 
-impl IntoResponse for ServerError {
-  fn into_response(self) -> Response {
+impl Source {
+  fn open(path: &Utf8Path, lazy: bool, mmap: bool, cache: Option<u64>) -> Result<Self> {
+    let context = error::PackageLoad { path };
+
+    #[cfg(feature = "reqwest")]
+    if Self::is_url(path) {
+      return Ok(
+        Package::load_from_url(path.as_str())
+          .context(context)?
+          .into(),
+      );
+    }
+
+    if mmap {
+      Ok(Self::Mapped(Package::map(path).context(context)?))
+    } else if lazy {
+      let reader = Package::open(path).context(context)?;
+      Ok(Self::Lazy(match cache {
+        Some(budget) => reader.with_cache(budget),
+        None => reader,
+      }))
+    } else {
+      Ok(Package::load(path).context(context)?.into())
+    }
+  }
+
+  fn is_url(path: &Utf8Path) -> bool {
+    path.as_str().starts_with("http://") || path.as_str().starts_with("https://")
+  }
+
+  fn file(&self, path: &str) -> Result<Option<(Mime, Hash, Bytes)>, crate::package::Error> {
+    self.manifest().content(path, |hash| {
+      Ok(match self {
+        Self::Loaded(package) => package.get(hash).is_some().then(|| {
+          Bytes::from_owner(LoadedFile {
+            hash,
+            package: package.clone(),
+          })
+        }),
+        Self::Lazy(reader) => reader.read_file(hash)?,
+        Self::Mapped(mapped) => mapped.file(hash)?,
+      })
+    })
+  }
+
+  fn manifest(&self) -> &Manifest {
     match self {
-      Self::NotFound { path } => {
-        (StatusCode::NOT_FOUND, format!("{path} not found")).into_response()
-      }
+      Self::Loaded(package) => &package.manifest,
+      Self::Lazy(reader) => reader.manifest(),
+      Self::Mapped(mapped) => mapped.manifest(),
     }
   }
 }
 
-type ServerResult = std::result::Result<Resource, ServerError>;
+impl From<Package> for Source {
+  fn from(package: Package) -> Self {
+    Self::Loaded(Arc::new(package))
+  }
+}
 
-impl Server {
-  pub fn run(self) -> Result {
-    let app = Package::load(&self.app).context(error::PackageLoad { path: &self.app })?;
-    let content = Package::load(&self.content).context(error::PackageLoad {
-      path: &self.content,
-    })?;
+// a file of a loaded package, which keeps the package alive while the file is
+// being served, so that it can be served without being copied
+struct LoadedFile {
+  hash: Hash,
+  package: Arc<Package>,
+}
 
-    match app.manifest {
-      Manifest::App { handles, .. } => {
-        ensure!(
-          content.manifest.ty() == handles,
-          error::ContentType {
-            content: content.manifest.ty(),
-            handles,
-          }
-        );
+impl AsRef<[u8]> for LoadedFile {
+  fn as_ref(&self) -> &[u8] {
+    self.package.get(self.hash).unwrap()
+  }
+}
+
+#[derive(Debug)]
+struct State {
+  app: Source,
+  base_path: String,
+  compressed: HashMap<Hash, Compressed>,
+  contents: Vec<(String, Source)>,
+  connections: Option<Arc<Semaphore>>,
+  headers: CustomHeaders,
+  inject_manifest: bool,
+  last_modified: Option<SystemTime>,
+  no_cache: bool,
+  spa: bool,
+}
+
+impl State {
+  fn new(app: Source, content: Source) -> Result<Self> {
+    Self::with_contents(app, vec![("content".into(), content)])
+  }
+
+  fn with_contents(app: Source, contents: Vec<(String, Source)>) -> Result<Self> {
+    let Manifest::App { handles, .. } = app.manifest() else {
+      return error::AppType {
+        ty: app.manifest().ty(),
       }
-      _ => {
-        return error::AppType {
-          ty: app.manifest.ty(),
+      .fail();
+    };
+
+    let mut names = HashSet::new();
+
+    for (name, content) in &contents {
+      ensure!(
+        handles.contains(&content.manifest().ty()),
+        error::ContentType {
+          content: content.manifest().ty(),
+          handles: handles.clone(),
+        }
+      );
+
+      ensure!(
+        names.insert(name.as_str()),
+        error::ContentNameDuplicated { name }
+      );
+    }
+
+    let mut compressed = HashMap::new();
+
+    for source in [&app]
+      .into_iter()
+      .chain(contents.iter().map(|(_, source)| source))
+    {
+      let Source::Loaded(package) = source else {
+        continue;
+      };
+
+      for (path, hash) in package.manifest.paths() {
+        let content_type = mime_guess::from_path(&path).first_or_octet_stream();
+
+        if Encoding::compressible(&content_type) && !compressed.contains_key(&hash) {
+          compressed.insert(hash, Compressed::new(&package.files[&hash]));
         }
-        .fail()
       }
     }
 
-    Runtime::new().context(error::Runtime)?.block_on(async {
-      axum_server::Server::bind(self.address)
-        .serve(
-          Router::new()
-            .route("/", get(Self::root))
-            .route("/api/manifest", get(Self::manifest))
-            .route("/app/*path", get(Self::app))
-            .route("/content/*path", get(Self::content))
-            .layer(Extension(Arc::new(State { app, content })))
-            .into_make_service(),
-        )
-        .await
-        .context(error::Serve {
-          address: self.address,
+    Ok(Self {
+      headers: CustomHeaders::new(app.manifest())?,
+      app,
+      base_path: String::new(),
+      compressed,
+      connections: None,
+      contents,
+      inject_manifest: false,
+      last_modified: None,
+      no_cache: false,
+      spa: false,
+    })
+  }
+
+  fn open(
+    app: &Utf8Path,
+    contents: &[NamedPackage],
+    lazy: bool,
+    mmap: bool,
+    cache: Option<u64>,
+  ) -> Result<Self> {
+    let last_modified = [app]
+      .into_iter()
+      .chain(contents.iter().map(|content| content.path.as_path()))
+      .filter(|path| !Source::is_url(path))
+      .map(|path| {
+        fs::metadata(path)
+          .and_then(|metadata| metadata.modified())
+          .ok()
+      })
+      .collect::<Option<Vec<SystemTime>>>()
+      .and_then(|modified| modified.into_iter().max());
+
+    let mut state = Self::with_contents(
+      Source::open(app, lazy, mmap, cache)?,
+      contents
+        .iter()
+        .map(|content| {
+          Ok((
+            content.name.clone(),
+            Source::open(&content.path, lazy, mmap, cache)?,
+          ))
         })
-    })?;
+        .collect::<Result<Vec<(String, Source)>>>()?,
+    )?;
 
-    Ok(())
-  }
+    state.last_modified = last_modified;
 
-  async fn manifest(Extension(state): Extension<Arc<State>>) -> Resource {
-    Resource::new(
-      mime::APPLICATION_JSON,
-      serde_json::to_vec(&state.content.manifest).unwrap(),
-    )
+    Ok(state)
   }
 
-  async fn root(Extension(state): Extension<Arc<State>>) -> ServerResult {
-    Self::file(&state.app, "", "index.html")
+  fn content(&self) -> &Source {
+    &self.contents[0].1
   }
 
-  async fn app(Extension(state): Extension<Arc<State>>, Path(path): Path<String>) -> ServerResult {
-    Self::file(&state.app, "/app/", &path)
+  fn named(&self, name: &str) -> Option<&Source> {
+    self
+      .contents
+      .iter()
+      .find(|(n, _)| n == name)
+      .map(|(_, source)| source)
   }
+}
 
-  async fn content(
-    Extension(state): Extension<Arc<State>>,
-    Path(path): Path<String>,
-  ) -> ServerResult {
-    Self::file(&state.content, "/content/", &path)
-  }
+#[derive(Debug, Default, Deserialize)]
+struct ContentQuery {
+  download: Option<String>,
+}
 
-  fn file(package: &Package, prefix: &str, path: &str) -> ServerResult {
-    match package.file(path) {
-      Some((content_type, content)) => Ok(Resource::new(content_type, content)),
-      None => Err(ServerError::NotFound {
-        path: format!("{prefix}{path}"),
-      }),
-    }
+impl ContentQuery {
+  fn download(&self) -> bool {
+    self
+      .download
+      .as_deref()
+      .is_some_and(|download| download != "0" && download != "false")
   }
 }
 
-#[cfg(test)]
-mod tests {
-  use super::*;
-
-  static PACKAGES: Mutex<Option<TempDir>> = Mutex::new(None);
+#[derive(Debug)]
+struct Resource {
+  accept_ranges: bool,
+  content_type: Mime,
+  content: Bytes,
+  encoding: Option<Encoding>,
+  filename: Option<String>,
+  hash: Option<Hash>,
+  headers: Vec<(String, String)>,
+  if_modified_since: Option<String>,
+  if_none_match: Option<String>,
+  immutable: bool,
+  last_modified: Option<SystemTime>,
+  negotiated: bool,
+  range: Option<String>,
+  vary: bool,
+}
 
-  fn packages() -> Utf8PathBuf {
-    let mut packages = PACKAGES.lock().unwrap();
+impl Resource {
+  fn new(content_type: Mime, content: impl Into<Bytes>) -> Self {
+    Self {
+      accept_ranges: false,
+      content_type,
+      content: content.into(),
+      encoding: None,
+      filename: None,
+      hash: None,
+      headers: Vec::new(),
+      if_modified_since: None,
+      if_none_match: None,
+      immutable: false,
+      last_modified: None,
+      negotiated: false,
+      range: None,
+      vary: false,
+    }
+  }
 
-    if packages.is_none() {
-      let tempdir = tempdir();
+  fn file(content_type: Mime, hash: Hash, content: Bytes, headers: &HeaderMap) -> Self {
+    Self {
+      accept_ranges: true,
+      content_type,
+      content,
+      encoding: None,
+      filename: None,
+      hash: Some(hash),
+      headers: Vec::new(),
+      if_modified_since: Self::header(headers, header::IF_MODIFIED_SINCE),
+      if_none_match: Self::header(headers, header::IF_NONE_MATCH),
+      immutable: false,
+      last_modified: None,
+      negotiated: false,
+      range: Self::header(headers, header::RANGE),
+      vary: false,
+    }
+  }
 
-      subcommand::package::Package {
-        root: "apps/comic".into(),
-        output: tempdir.path_utf8().join("app.package"),
-      }
-      .run()
-      .unwrap();
+  fn json(value: &impl Serialize, headers: &HeaderMap) -> Self {
+    Self::new(mime::APPLICATION_JSON, serde_json::to_vec(value).unwrap()).encode(headers, None)
+  }
 
-      subcommand::package::Package {
-        root: "content/comic".into(),
-        output: tempdir.path_utf8().join("content.package"),
-      }
-      .run()
-      .unwrap();
+  fn manifest(manifest: &Manifest, headers: &HeaderMap) -> Self {
+    let format = Self::header(headers, header::ACCEPT)
+      .map(|accept| ManifestFormat::negotiate(&accept))
+      .unwrap_or(ManifestFormat::Json);
 
-      *packages = Some(tempdir);
+    Self {
+      negotiated: true,
+      ..Self::new(format.content_type(), format.serialize(manifest))
     }
-
-    packages.as_ref().unwrap().path_utf8().into()
+    .encode(headers, None)
   }
 
-  fn content_package() -> Utf8PathBuf {
-    packages().join("content.package")
+  fn header(headers: &HeaderMap, name: header::HeaderName) -> Option<String> {
+    headers
+      .get(name)
+      .and_then(|value| value.to_str().ok())
+      .map(str::to_owned)
   }
 
-  fn app_package() -> Utf8PathBuf {
-    packages().join("app.package")
-  }
+  fn encode(mut self, headers: &HeaderMap, compressed: Option<&Compressed>) -> Self {
+    if !Encoding::compressible(&self.content_type) {
+      return self;
+    }
 
-  #[test]
-  fn app_load_error() {
-    let tempdir = tempdir();
+    self.vary = true;
 
-    let app = tempdir.path_utf8().join("app.package");
-    let content = tempdir.path_utf8().join("content.package");
+    let Some(encoding) = Self::header(headers, header::ACCEPT_ENCODING)
+      .and_then(|accept_encoding| Encoding::negotiate(&accept_encoding))
+    else {
+      return self;
+    };
 
-    assert_matches!(
-      Server {
-        address: "0.0.0.0:80".parse().unwrap(),
-        app: app.clone(),
-        content,
-      }
-      .run()
-      .unwrap_err(),
-      Error::PackageLoad { path, .. }
-      if path == app,
-    );
+    self.content = match compressed {
+      Some(compressed) => compressed.get(encoding),
+      None => encoding.compress(&self.content).into(),
+    };
+
+    self.encoding = Some(encoding);
+
+    self
   }
 
-  #[test]
-  fn content_load_error() {
-    let tempdir = tempdir();
+  fn content_disposition(filename: &str) -> String {
+    let fallback = filename
+      .chars()
+      .map(|c| {
+        if c == ' ' || (c.is_ascii_graphic() && c != '"' && c != '\\') {
+          c
+        } else {
+          '_'
+        }
+      })
+      .collect::<String>();
 
-    let content = tempdir.path_utf8().join("content.package");
+    let mut disposition = format!("attachment; filename=\"{fallback}\"");
 
-    assert_matches!(
-      Server {
-        address: "0.0.0.0:80".parse().unwrap(),
-        app: app_package(),
-        content: content.clone(),
-      }
-      .run()
-      .unwrap_err(),
-      Error::PackageLoad { path, .. }
-      if path == content,
-    );
-  }
+    if fallback != filename {
+      disposition.push_str("; filename*=UTF-8''");
 
-  #[test]
-  fn app_package_is_not_app() {
-    assert_matches!(
-      Server {
-        address: "0.0.0.0:80".parse().unwrap(),
-        app: content_package(),
-        content: content_package(),
+      for byte in filename.bytes() {
+        if byte.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&byte) {
+          disposition.push(byte.into());
+        } else {
+          disposition.push_str(&format!("%{byte:02X}"));
+        }
       }
-      .run()
-      .unwrap_err(),
-      Error::AppType { ty, .. }
-      if ty == Type::Comic,
-    );
-  }
+    }
 
-  #[test]
-  fn app_doesnt_handle_content_type() {
-    assert_matches!(
-      Server {
-        address: "0.0.0.0:80".parse().unwrap(),
-        app: app_package(),
-        content: app_package(),
-      }
-      .run()
-      .unwrap_err(),
-      Error::ContentType {
-        content: Type::App,
-        handles: Type::Comic,
-        ..
-      }
-    );
+    disposition
   }
 
-  #[tokio::test]
-  async fn routes() {
-    let state = Extension(Arc::new(State {
-      app: Package::load(&app_package()).unwrap(),
-      content: Package::load(&content_package()).unwrap(),
-    }));
+  fn etag(&self) -> Option<String> {
+    let hash = self.hash?.to_hex();
 
-    let root = Server::root(state.clone()).await.unwrap();
-    assert_eq!(root.content_type, mime::TEXT_HTML);
-    assert!(root.content.starts_with(b"<html>"));
+    Some(match self.encoding {
+      Some(encoding) => format!("\"{hash}-{}\"", encoding.name()),
+      None => format!("\"{hash}\""),
+    })
+  }
 
-    let manifest = Server::manifest(state.clone()).await;
-    assert_eq!(manifest.content_type, mime::APPLICATION_JSON);
-    assert!(
-      manifest.content.starts_with(b"{\"type\":\"comic\""),
-      "{}",
-      String::from_utf8(manifest.content).unwrap()
-    );
+  fn insert_headers(&self, response: &mut Response) {
+    let headers = response.headers_mut();
 
-    let app = Server::app(state.clone(), Path("index.js".into()))
-      .await
-      .unwrap();
-    assert_eq!(app.content_type, mime::TEXT_JAVASCRIPT);
-    assert!(
-      app.content.starts_with(b"const response ="),
-      "{}",
-      String::from_utf8(app.content).unwrap()
-    );
+    if self.accept_ranges {
+      headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    }
 
-    let content = Server::content(state.clone(), Path("0".into()))
-      .await
-      .unwrap();
-    assert_eq!(content.content_type, mime::IMAGE_JPEG);
-    assert!(
-      content.content.starts_with(b"\xff\xd8\xff\xe0\x00\x10JFIF"),
-      "{}",
-      String::from_utf8_lossy(&content.content),
-    );
+    if let Some(encoding) = self.encoding {
+      headers.insert(
+        header::CONTENT_ENCODING,
+        HeaderValue::from_static(encoding.name()),
+      );
+    }
+
+    if let Some(etag) = self.etag() {
+      headers.insert(header::ETAG, etag.parse().unwrap());
+    }
+
+    if let Some(filename) = &self.filename {
+      headers.insert(
+        header::CONTENT_DISPOSITION,
+        Self::content_disposition(filename).parse().unwrap(),
+      );
+    }
+
+    if self.immutable {
+      headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static("public, max-age=31536000, immutable"),
+      );
+    }
+
+    for (name, value) in &self.headers {
+      if let (Ok(name), Ok(value)) = (
+        header::HeaderName::from_bytes(name.as_bytes()),
+        HeaderValue::from_str(value),
+      ) {
+        headers.insert(name, value);
+      }
+    }
+
+    if let Some(last_modified) = self.last_modified {
+      headers.insert(
+        header::LAST_MODIFIED,
+        httpdate::fmt_http_date(last_modified).parse().unwrap(),
+      );
+    }
+
+    if self.negotiated {
+      headers.append(header::VARY, HeaderValue::from_static("accept"));
+    }
+
+    if self.vary {
+      headers.append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    }
+  }
+
+  fn not_modified(&self) -> bool {
+    if let Some(if_none_match) = &self.if_none_match {
+      let Some(etag) = self.etag() else {
+        return false;
+      };
+
+      return if_none_match
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag);
+    }
+
+    let (Some(last_modified), Some(if_modified_since)) =
+      (self.last_modified, &self.if_modified_since)
+    else {
+      return false;
+    };
+
+    let Ok(if_modified_since) = httpdate::parse_http_date(if_modified_since) else {
+      return false;
+    };
+
+    // HTTP dates have one second resolution
+    httpdate::parse_http_date(&httpdate::fmt_http_date(last_modified))
+      .is_ok_and(|last_modified| last_modified <= if_modified_since)
+  }
+}
+
+impl IntoResponse for Resource {
+  fn into_response(mut self) -> axum::http::Response<axum::body::Body> {
+    if self.not_modified() {
+      let mut response = StatusCode::NOT_MODIFIED.into_response();
+      self.insert_headers(&mut response);
+      return response;
+    }
+
+    let len = self.content.len().into_u64();
+
+    let content_type = self.content_type.to_string();
+
+    let ranges = self
+      .range
+      .as_deref()
+      .and_then(|range| range::parse(range, len));
+
+    let mut response = match ranges.as_deref() {
+      None => (
+        [(header::CONTENT_TYPE, content_type)],
+        mem::take(&mut self.content),
+      )
+        .into_response(),
+      Some([]) => (
+        StatusCode::RANGE_NOT_SATISFIABLE,
+        [(header::CONTENT_RANGE, format!("bytes */{len}"))],
+      )
+        .into_response(),
+      Some([range]) => (
+        StatusCode::PARTIAL_CONTENT,
+        [
+          (header::CONTENT_TYPE, content_type),
+          (
+            header::CONTENT_RANGE,
+            format!("bytes {}-{}/{len}", range.start(), range.end()),
+          ),
+        ],
+        self
+          .content
+          .slice(*range.start() as usize..=*range.end() as usize),
+      )
+        .into_response(),
+      Some(ranges) => {
+        let boundary = blake3::hash(&self.content).to_hex();
+
+        let mut body = Vec::new();
+
+        for range in ranges {
+          body.extend_from_slice(
+            format!(
+              "--{boundary}\r\nContent-Type: {content_type}\r\nContent-Range: bytes {}-{}/{len}\r\n\r\n",
+              range.start(),
+              range.end(),
+            )
+            .as_bytes(),
+          );
+          body.extend_from_slice(&self.content[*range.start() as usize..=*range.end() as usize]);
+          body.extend_from_slice(b"\r\n");
+        }
+
+        body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+
+        (
+          StatusCode::PARTIAL_CONTENT,
+          [(
+            header::CONTENT_TYPE,
+            format!("multipart/byteranges; boundary={boundary}"),
+          )],
+          body,
+        )
+          .into_response()
+      }
+    };
+
+    if let Some(len) = response.body().size_hint().exact() {
+      response
+        .headers_mut()
+        .insert(header::CONTENT_LENGTH, len.into());
+    }
+
+    self.insert_headers(&mut response);
+
+    response
+  }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ServerError {
+  BadRequest {
+    path: String,
+  },
+  Internal {
+    path: String,
+  },
+  NotFound {
+    path: String,
+  },
+  NotFoundPage {
+    content: Bytes,
+  },
+  Redirect {
+    location: HeaderValue,
+    status: StatusCode,
+  },
+}
+
+impl IntoResponse for ServerError {
+  fn into_response(self) -> Response {
+    match self {
+      Self::BadRequest { path } => (
+        StatusCode::BAD_REQUEST,
+        format!("{} is not a valid path", path.escape_debug()),
+      )
+        .into_response(),
+      Self::Internal { path } => (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("failed to read {path}"),
+      )
+        .into_response(),
+      Self::NotFound { path } => {
+        (StatusCode::NOT_FOUND, format!("{path} not found")).into_response()
+      }
+      Self::NotFoundPage { content } => (
+        StatusCode::NOT_FOUND,
+        [(header::CONTENT_TYPE, mime::TEXT_HTML_UTF_8.as_ref())],
+        content,
+      )
+        .into_response(),
+      Self::Redirect { location, status } => {
+        (status, [(header::LOCATION, location)]).into_response()
+      }
+    }
+  }
+}
+
+type ServerResult = std::result::Result<Resource, ServerError>;
+
+impl Server {
+  const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+  // installs the global subscriber, so only called from the binary entry
+  // point, never from tests
+  pub fn init_tracing(&self) -> Result {
+    let filter =
+      tracing_subscriber::EnvFilter::try_new(&self.log_level).context(error::LogLevel {
+        level: &self.log_level,
+      })?;
+
+    tracing_subscriber::fmt()
+      .compact()
+      .with_env_filter(filter)
+      .with_writer(io::stderr)
+      .try_init()
+      .ok();
+
+    Ok(())
+  }
+
+  pub fn run(self) -> Result {
+    let tls = match (&self.tls_cert, &self.tls_key) {
+      (Some(cert), Some(key)) => Some((cert.clone(), key.clone())),
+      (None, None) => None,
+      _ => return error::TlsArgs.fail(),
+    };
+
+    let state = self.open_state()?;
+
+    let open = self.open.then(|| format!("{}/", state.base_path));
+
+    let shared = Arc::new(RwLock::new(Arc::new(state)));
+
+    // acquire the listener before spawning the watcher thread, since
+    // `Self::systemd` modifies the environment
+    let (listener, address) = match self.address {
+      Some(address) if !self.systemd => (Self::bind(address)?, address),
+      _ => Self::systemd()?,
+    };
+
+    let _watcher = if self.watch {
+      Some(Self::watch(
+        &self.app,
+        &self.content,
+        self.verify_on_read,
+        self.mmap,
+        self.cache_bytes,
+        shared.clone(),
+      )?)
+    } else {
+      None
+    };
+
+    Self::listen(
+      listener,
+      address,
+      Self::shared_router(shared, self.cors_allow_origin),
+      tls,
+      open,
+      Self::shutdown_signal(),
+    )
+  }
+
+  fn open_state(&self) -> Result<State> {
+    let mut state = State::open(
+      &self.app,
+      &self.content,
+      self.verify_on_read,
+      self.mmap,
+      self.cache_bytes,
+    )?;
+
+    state.base_path = self
+      .base_path
+      .as_deref()
+      .map(|base_path| {
+        let base_path = base_path.trim_matches('/');
+        if base_path.is_empty() {
+          String::new()
+        } else {
+          format!("/{base_path}")
+        }
+      })
+      .unwrap_or_default();
+    state.connections = self
+      .max_connections
+      .map(|max_connections| Arc::new(Semaphore::new(max_connections)));
+    state.inject_manifest = self.inject_manifest;
+    // files are served from paths rather than hashes, so when they may be
+    // reloaded, they can't be cached forever
+    state.no_cache = self.no_cache || self.watch;
+    state.spa = self.spa;
+
+    Ok(state)
+  }
+
+  fn watch(
+    app: &Utf8Path,
+    contents: &[NamedPackage],
+    lazy: bool,
+    mmap: bool,
+    cache: Option<u64>,
+    shared: Shared,
+  ) -> Result<notify::RecommendedWatcher> {
+    let paths = [app]
+      .into_iter()
+      .chain(contents.iter().map(|content| content.path.as_path()))
+      .filter(|path| !Source::is_url(path))
+      .collect::<Vec<&Utf8Path>>();
+
+    let packages = paths
+      .iter()
+      .filter_map(|path| path.file_name())
+      .map(str::to_owned)
+      .collect::<Vec<String>>();
+
+    let mut watcher = notify::recommended_watcher({
+      let app = app.to_owned();
+      let contents = contents.to_vec();
+      move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else {
+          return;
+        };
+
+        if !event.kind.is_create() && !event.kind.is_modify() {
+          return;
+        }
+
+        if !event.paths.iter().any(|path| {
+          path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| packages.iter().any(|package| package == name))
+        }) {
+          return;
+        }
+
+        match Self::reload(&shared, &app, &contents, lazy, mmap, cache) {
+          Ok(()) => tracing::info!("reloaded packages"),
+          Err(err) => tracing::error!(error = err.chain(), "failed to reload packages"),
+        }
+      }
+    })
+    .context(error::Watch { path: app })?;
+
+    for path in paths {
+      let directory = match path.parent() {
+        Some(parent) if !parent.as_str().is_empty() => parent,
+        _ => Utf8Path::new("."),
+      };
+
+      notify::Watcher::watch(
+        &mut watcher,
+        directory.as_std_path(),
+        notify::RecursiveMode::NonRecursive,
+      )
+      .context(error::Watch { path })?;
+    }
+
+    Ok(watcher)
+  }
+
+  fn reload(
+    shared: &Shared,
+    app: &Utf8Path,
+    contents: &[NamedPackage],
+    lazy: bool,
+    mmap: bool,
+    cache: Option<u64>,
+  ) -> Result {
+    let mut state = State::open(app, contents, lazy, mmap, cache)?;
+
+    let previous = shared.read().unwrap().clone();
+
+    state.base_path = previous.base_path.clone();
+    state.connections = previous.connections.clone();
+    state.inject_manifest = previous.inject_manifest;
+    state.no_cache = previous.no_cache;
+    state.spa = previous.spa;
+
+    *shared.write().unwrap() = Arc::new(state);
+
+    Ok(())
+  }
+
+  pub fn serve(address: SocketAddr, app: Package, content: Package) -> Result {
+    Self::listen(
+      Self::bind(address)?,
+      address,
+      Self::router(State::new(app.into(), content.into())?, Vec::new()),
+      None,
+      None,
+      Self::shutdown_signal(),
+    )
+  }
+
+  fn listen(
+    listener: std::net::TcpListener,
+    address: SocketAddr,
+    router: Router,
+    tls: Option<(Utf8PathBuf, Utf8PathBuf)>,
+    open: Option<String>,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+  ) -> Result {
+    let runtime = Runtime::new().context(error::Runtime)?;
+
+    let tls = tls
+      .map(|(cert, key)| {
+        runtime
+          .block_on(RustlsConfig::from_pem_file(&cert, &key))
+          .context(error::TlsConfig { cert, key })
+      })
+      .transpose()?;
+
+    runtime.block_on(async {
+      let handle = Handle::new();
+
+      tokio::spawn({
+        let handle = handle.clone();
+        async move {
+          shutdown.await;
+          tracing::info!("shutting down");
+          handle.graceful_shutdown(Some(Self::SHUTDOWN_TIMEOUT));
+        }
+      });
+
+      if let Some(path) = open {
+        let handle = handle.clone();
+        let https = tls.is_some();
+        tokio::spawn(async move {
+          if let Some(address) = handle.listening().await {
+            let url = Self::url(address, https, &path);
+            if let Err(err) = open::that_detached(&url) {
+              tracing::warn!(%url, error = %err, "failed to open browser");
+            }
+          }
+        });
+      }
+
+      match tls {
+        Some(config) => {
+          axum_server::from_tcp_rustls(listener, config)
+            .handle(handle)
+            .serve(router.into_make_service())
+            .await
+        }
+        None => {
+          axum_server::from_tcp(listener)
+            .handle(handle)
+            .serve(router.into_make_service())
+            .await
+        }
+      }
+      .context(error::Serve { address })
+    })?;
+
+    Ok(())
+  }
+
+  fn bind(address: SocketAddr) -> Result<std::net::TcpListener> {
+    std::net::TcpListener::bind(address).map_err(|source| Self::bind_error(address, source))
+  }
+
+  fn systemd() -> Result<(std::net::TcpListener, SocketAddr)> {
+    let listener = ListenFd::from_env()
+      .take_tcp_listener(0)
+      .context(error::SystemdSocket)?
+      .context(error::SystemdSocketMissing)?;
+
+    let address = listener.local_addr().context(error::SystemdSocket)?;
+
+    Ok((listener, address))
+  }
+
+  fn bind_error(address: SocketAddr, source: io::Error) -> Error {
+    match source.kind() {
+      io::ErrorKind::AddrInUse => error::AddressInUse { address }.into_error(source),
+      io::ErrorKind::PermissionDenied => {
+        error::AddressPermissionDenied { address }.into_error(source)
+      }
+      _ => error::Bind { address }.into_error(source),
+    }
+  }
+
+  fn url(mut address: SocketAddr, https: bool, path: &str) -> String {
+    if address.ip().is_unspecified() {
+      address.set_ip(match address.ip() {
+        IpAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+        IpAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+      });
+    }
+
+    format!("{}://{address}{path}", if https { "https" } else { "http" })
+  }
+
+  async fn shutdown_signal() {
+    let interrupt = async {
+      tokio::signal::ctrl_c().await.ok();
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+      match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+        Ok(mut signal) => {
+          signal.recv().await;
+        }
+        Err(_) => future::pending().await,
+      }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = future::pending::<()>();
+
+    tokio::select! {
+      () = interrupt => {}
+      () = terminate => {}
+    }
+  }
+
+  fn router(state: State, cors_allow_origin: Vec<String>) -> Router {
+    Self::shared_router(Arc::new(RwLock::new(Arc::new(state))), cors_allow_origin)
+  }
+
+  fn shared_router(shared: Shared, cors_allow_origin: Vec<String>) -> Router {
+    let (base_path, connections) = {
+      let state = shared.read().unwrap();
+      (state.base_path.clone(), state.connections.clone())
+    };
+
+    let mut router = Router::new()
+      .route("/", Self::get(Self::root))
+      .route("/api/content", Self::get(Self::entries))
+      .route("/api/content/:name", Self::get(Self::named_entries))
+      .route("/api/manifest", Self::get(Self::manifest))
+      .route("/api/manifest/:name", Self::get(Self::named_manifest))
+      .route("/api/summary", Self::get(Self::summary))
+      .route("/api/summary/:name", Self::get(Self::named_summary))
+      .route("/app/*path", Self::get(Self::app))
+      .route("/healthz", Self::get(Self::healthz))
+      .route("/content/*path", Self::get(Self::content))
+      .layer(axum::middleware::from_fn_with_state(shared, Self::state));
+
+    if !base_path.is_empty() {
+      router = Router::new().nest(&base_path, router);
+    }
+
+    if !cors_allow_origin.is_empty() {
+      router = router.layer(axum::middleware::from_fn_with_state(
+        Arc::new(cors_allow_origin),
+        cors::cors,
+      ));
+    }
+
+    if let Some(connections) = connections {
+      router = router.layer(axum::middleware::from_fn_with_state(
+        connections,
+        limit::limit,
+      ));
+    }
+
+    router.layer(
+      TraceLayer::new_for_http()
+        .make_span_with(|request: &axum::extract::Request| {
+          tracing::info_span!(
+            "request",
+            method = %request.method(),
+            path = %request.uri().path(),
+          )
+        })
+        .on_request(())
+        .on_response(
+          |response: &Response, latency: Duration, _span: &tracing::Span| {
+            tracing::info!(
+              status = response.status().as_u16(),
+              size = response.body().size_hint().exact(),
+              ?latency,
+            );
+          },
+        ),
+    )
+  }
+
+  fn get<H: Handler<T, ()>, T: 'static>(handler: H) -> MethodRouter {
+    get(handler).fallback(Self::method_not_allowed)
+  }
+
+  async fn method_not_allowed() -> Response {
+    (
+      StatusCode::METHOD_NOT_ALLOWED,
+      [(header::ALLOW, HeaderValue::from_static("GET, HEAD"))],
+    )
+      .into_response()
+  }
+
+  async fn state(
+    axum::extract::State(shared): axum::extract::State<Shared>,
+    mut request: axum::extract::Request,
+    next: axum::middleware::Next,
+  ) -> Response {
+    let state = shared.read().unwrap().clone();
+    request.extensions_mut().insert(state);
+    next.run(request).await
+  }
+
+  async fn entries(Extension(state): Extension<Arc<State>>, headers: HeaderMap) -> Resource {
+    Resource::json(&state.content().manifest().entries(), &headers)
+  }
+
+  async fn named_entries(
+    Extension(state): Extension<Arc<State>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+  ) -> ServerResult {
+    let source = Self::named(&state, "content", &name)?;
+
+    Ok(Resource::json(&source.manifest().entries(), &headers))
+  }
+
+  async fn manifest(Extension(state): Extension<Arc<State>>, headers: HeaderMap) -> Resource {
+    Resource::manifest(state.content().manifest(), &headers)
+  }
+
+  async fn named_manifest(
+    Extension(state): Extension<Arc<State>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+  ) -> ServerResult {
+    let source = Self::named(&state, "manifest", &name)?;
+
+    Ok(Resource::manifest(source.manifest(), &headers))
+  }
+
+  async fn summary(Extension(state): Extension<Arc<State>>, headers: HeaderMap) -> Resource {
+    Resource::json(&state.content().manifest().summary(), &headers)
+  }
+
+  async fn named_summary(
+    Extension(state): Extension<Arc<State>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+  ) -> ServerResult {
+    let source = Self::named(&state, "summary", &name)?;
+
+    Ok(Resource::json(&source.manifest().summary(), &headers))
+  }
+
+  fn named<'a>(state: &'a State, route: &str, name: &str) -> Result<&'a Source, ServerError> {
+    state.named(name).ok_or_else(|| ServerError::NotFound {
+      path: format!("{}/api/{route}/{name}", state.base_path),
+    })
+  }
+
+  async fn healthz() -> Resource {
+    Resource::new(mime::TEXT_PLAIN, b"ok".to_vec())
+  }
+
+  async fn root(Extension(state): Extension<Arc<State>>, headers: HeaderMap) -> ServerResult {
+    Self::redirect(&state, "/")?;
+
+    if state.inject_manifest {
+      if let Ok(Some((content_type, _hash, content))) = state.app.file("index.html") {
+        let content = inject::manifest(&content, state.content().manifest());
+        let mut resource = Resource::file(
+          content_type,
+          blake3::hash(&content),
+          content.into(),
+          &headers,
+        )
+        .encode(&headers, None);
+        resource.headers = state.headers.get("index.html");
+        resource.last_modified = state.last_modified;
+        return Ok(resource);
+      }
+    }
+
+    Self::file(&state, &state.app, "", "index.html", &headers)
+  }
+
+  async fn app(
+    Extension(state): Extension<Arc<State>>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+  ) -> ServerResult {
+    let path = Self::normalize_path(&state, "/app/", &path)?;
+
+    Self::redirect(&state, path)?;
+
+    let index = state.app.manifest().index(path);
+
+    let path = index.as_deref().unwrap_or(path);
+
+    match Self::file(&state, &state.app, "/app/", path, &headers) {
+      Err(ServerError::NotFound { .. } | ServerError::NotFoundPage { .. }) if state.spa => {
+        Self::file(&state, &state.app, "", "index.html", &headers)
+      }
+      result => result,
+    }
+  }
+
+  async fn content(
+    Extension(state): Extension<Arc<State>>,
+    Path(path): Path<String>,
+    Query(query): Query<ContentQuery>,
+    headers: HeaderMap,
+  ) -> ServerResult {
+    let path = Self::normalize_path(&state, "/content/", &path)?;
+
+    let (source, prefix, path) = if state.contents.len() == 1 {
+      (state.content(), "/content/".into(), path)
+    } else {
+      let (name, path) = path.split_once('/').unwrap_or((path, ""));
+
+      let Some(source) = state.named(name) else {
+        return Err(ServerError::NotFound {
+          path: format!("{}/content/{name}/{path}", state.base_path),
+        });
+      };
+
+      (source, format!("/content/{name}/"), path)
+    };
+
+    let mut resource = Self::file(&state, source, &prefix, path, &headers)?;
+
+    if query.download() {
+      resource.filename = source.manifest().filename(path);
+    }
+
+    Ok(resource)
+  }
+
+  fn redirect(state: &State, path: &str) -> Result<(), ServerError> {
+    let Some(redirect) = state.app.manifest().redirect(path) else {
+      return Ok(());
+    };
+
+    let (Ok(location), Ok(status)) = (
+      HeaderValue::from_str(redirect.location()),
+      StatusCode::from_u16(redirect.status()),
+    ) else {
+      return Ok(());
+    };
+
+    if !status.is_redirection() {
+      return Ok(());
+    }
+
+    Err(ServerError::Redirect { location, status })
+  }
+
+  fn normalize_path<'a>(
+    state: &State,
+    prefix: &str,
+    path: &'a str,
+  ) -> Result<&'a str, ServerError> {
+    if path.contains('\0') || path.split('/').any(|segment| segment == "..") {
+      return Err(ServerError::BadRequest {
+        path: format!("{}{prefix}{path}", state.base_path),
+      });
+    }
+
+    Ok(path)
+  }
+
+  fn file(
+    state: &State,
+    source: &Source,
+    prefix: &str,
+    path: &str,
+    headers: &HeaderMap,
+  ) -> ServerResult {
+    match source.file(path) {
+      Ok(Some((content_type, hash, content))) => {
+        let mut resource = Resource::file(content_type, hash, content, headers)
+          .encode(headers, state.compressed.get(&hash));
+        if std::ptr::eq(source, &state.app) {
+          resource.headers = state.headers.get(path);
+        }
+        resource.immutable = !state.no_cache && !prefix.is_empty();
+        resource.last_modified = state.last_modified;
+        Ok(resource)
+      }
+      Ok(None) => match state.app.file("404.html") {
+        Ok(Some((_, _, content))) => Err(ServerError::NotFoundPage { content }),
+        _ => Err(ServerError::NotFound {
+          path: format!("{}{prefix}{path}", state.base_path),
+        }),
+      },
+      Err(source) => {
+        let path = format!("{}{prefix}{path}", state.base_path);
+        tracing::error!(
+          error = error::PackageRead { path: &path }
+            .into_error(source)
+            .chain(),
+          "failed to read file",
+        );
+        Err(ServerError::Internal { path })
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use {super::*, tower::ServiceExt};
+
+  static PACKAGES: Mutex<Option<TempDir>> = Mutex::new(None);
+
+  fn packages() -> Utf8PathBuf {
+    let mut packages = PACKAGES.lock().unwrap();
+
+    if packages.is_none() {
+      let tempdir = tempdir();
+
+      subcommand::package::Package {
+        root: vec!["apps/comic".into()],
+        output: tempdir.path_utf8().join("app.package"),
+        ..Default::default()
+      }
+      .run()
+      .unwrap();
+
+      subcommand::package::Package {
+        root: vec!["content/comic".into()],
+        output: tempdir.path_utf8().join("content.package"),
+        ..Default::default()
+      }
+      .run()
+      .unwrap();
+
+      *packages = Some(tempdir);
+    }
+
+    packages.as_ref().unwrap().path_utf8().into()
+  }
+
+  fn content_package() -> Utf8PathBuf {
+    packages().join("content.package")
+  }
+
+  fn app_package() -> Utf8PathBuf {
+    packages().join("app.package")
+  }
+
+  #[test]
+  fn app_load_error() {
+    let tempdir = tempdir();
+
+    let app = tempdir.path_utf8().join("app.package");
+    let content = tempdir.path_utf8().join("content.package");
+
+    assert_matches!(
+      Server {
+        address: Some("0.0.0.0:80".parse().unwrap()),
+        app: app.clone(),
+        base_path: None,
+        content: vec![content.into()],
+        cache_bytes: None,
+        cors_allow_origin: Vec::new(),
+        log_level: "info".into(),
+        max_connections: None,
+        mmap: false,
+        inject_manifest: false,
+        no_cache: false,
+        open: false,
+        spa: false,
+        systemd: false,
+        tls_cert: None,
+        tls_key: None,
+        verify_on_read: false,
+        watch: false,
+      }
+      .run()
+      .unwrap_err(),
+      Error::PackageLoad { path, .. }
+      if path == app,
+    );
+  }
+
+  #[test]
+  fn watch_implies_no_cache() {
+    for (no_cache, watch) in [(false, false), (true, false), (false, true)] {
+      let state = Server {
+        address: Some("0.0.0.0:80".parse().unwrap()),
+        app: app_package(),
+        base_path: None,
+        content: vec![content_package().into()],
+        cache_bytes: None,
+        cors_allow_origin: Vec::new(),
+        log_level: "info".into(),
+        max_connections: None,
+        mmap: false,
+        inject_manifest: false,
+        no_cache,
+        open: false,
+        spa: false,
+        systemd: false,
+        tls_cert: None,
+        tls_key: None,
+        verify_on_read: false,
+        watch,
+      }
+      .open_state()
+      .unwrap();
+
+      assert_eq!(state.no_cache, no_cache || watch);
+    }
+  }
+
+  #[test]
+  fn content_load_error() {
+    let tempdir = tempdir();
+
+    let content = tempdir.path_utf8().join("content.package");
+
+    assert_matches!(
+      Server {
+        address: Some("0.0.0.0:80".parse().unwrap()),
+        app: app_package(),
+        base_path: None,
+        content: vec![content.clone().into()],
+        cache_bytes: None,
+        cors_allow_origin: Vec::new(),
+        log_level: "info".into(),
+        max_connections: None,
+        mmap: false,
+        inject_manifest: false,
+        no_cache: false,
+        open: false,
+        spa: false,
+        systemd: false,
+        tls_cert: None,
+        tls_key: None,
+        verify_on_read: false,
+        watch: false,
+      }
+      .run()
+      .unwrap_err(),
+      Error::PackageLoad { path, .. }
+      if path == content,
+    );
+  }
+
+  #[test]
+  fn app_package_is_not_app() {
+    assert_matches!(
+      Server {
+        address: Some("0.0.0.0:80".parse().unwrap()),
+        app: content_package(),
+        base_path: None,
+        content: vec![content_package().into()],
+        cache_bytes: None,
+        cors_allow_origin: Vec::new(),
+        log_level: "info".into(),
+        max_connections: None,
+        mmap: false,
+        inject_manifest: false,
+        no_cache: false,
+        open: false,
+        spa: false,
+        systemd: false,
+        tls_cert: None,
+        tls_key: None,
+        verify_on_read: false,
+        watch: false,
+      }
+      .run()
+      .unwrap_err(),
+      Error::AppType { ty, .. }
+      if ty == Type::Comic,
+    );
+  }
+
+  #[test]
+  fn app_doesnt_handle_content_type() {
+    assert_matches!(
+      Server {
+        address: Some("0.0.0.0:80".parse().unwrap()),
+        app: app_package(),
+        base_path: None,
+        content: vec![app_package().into()],
+        cache_bytes: None,
+        cors_allow_origin: Vec::new(),
+        log_level: "info".into(),
+        max_connections: None,
+        mmap: false,
+        inject_manifest: false,
+        no_cache: false,
+        open: false,
+        spa: false,
+        systemd: false,
+        tls_cert: None,
+        tls_key: None,
+        verify_on_read: false,
+        watch: false,
+      }
+      .run()
+      .unwrap_err(),
+      Error::ContentType {
+        content: Type::App,
+        handles,
+        ..
+      }
+      if handles == [Type::Comic].into()
+    );
+  }
+
+  #[test]
+  fn tls_args() {
+    for (tls_cert, tls_key) in [(Some("cert.pem"), None), (None, Some("key.pem"))] {
+      assert_matches!(
+        Server {
+          address: Some("0.0.0.0:80".parse().unwrap()),
+          app: app_package(),
+          base_path: None,
+          content: vec![content_package().into()],
+          cache_bytes: None,
+          cors_allow_origin: Vec::new(),
+          log_level: "info".into(),
+          max_connections: None,
+          mmap: false,
+          inject_manifest: false,
+          no_cache: false,
+          open: false,
+          spa: false,
+          systemd: false,
+          tls_cert: tls_cert.map(Into::into),
+          tls_key: tls_key.map(Into::into),
+          verify_on_read: false,
+          watch: false,
+        }
+        .run()
+        .unwrap_err(),
+        Error::TlsArgs { .. },
+      );
+    }
+  }
+
+  #[test]
+  fn log_level_error() {
+    assert_matches!(
+      Server {
+        address: Some("0.0.0.0:80".parse().unwrap()),
+        app: app_package(),
+        base_path: None,
+        content: vec![content_package().into()],
+        cache_bytes: None,
+        cors_allow_origin: Vec::new(),
+        log_level: "foo=bar".into(),
+        max_connections: None,
+        mmap: false,
+        inject_manifest: false,
+        no_cache: false,
+        open: false,
+        spa: false,
+        systemd: false,
+        tls_cert: None,
+        tls_key: None,
+        verify_on_read: false,
+        watch: false,
+      }
+      .init_tracing()
+      .unwrap_err(),
+      Error::LogLevel { level, .. } if level == "foo=bar",
+    );
+  }
+
+  #[test]
+  fn tls_config_error() {
+    let tempdir = tempdir();
+
+    let cert = tempdir.path_utf8().join("cert.pem");
+    let key = tempdir.path_utf8().join("key.pem");
+
+    fs::write(&cert, "foo").unwrap();
+
+    assert_matches!(
+      Server {
+        address: Some("0.0.0.0:80".parse().unwrap()),
+        app: app_package(),
+        base_path: None,
+        content: vec![content_package().into()],
+        cache_bytes: None,
+        cors_allow_origin: Vec::new(),
+        log_level: "info".into(),
+        max_connections: None,
+        mmap: false,
+        inject_manifest: false,
+        no_cache: false,
+        open: false,
+        spa: false,
+        systemd: false,
+        tls_cert: Some(cert.clone()),
+        tls_key: Some(key.clone()),
+        verify_on_read: false,
+        watch: false,
+      }
+      .run()
+      .unwrap_err(),
+      Error::TlsConfig { cert: c, key: k, .. } if c == cert && k == key,
+    );
+  }
+
+  #[test]
+  fn address_in_use() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+
+    let address = listener.local_addr().unwrap();
+
+    assert_matches!(
+      Server::bind(address).unwrap_err(),
+      Error::AddressInUse { address: a, .. } if a == address,
+    );
+  }
+
+  #[test]
+  fn address_permission_denied() {
+    let address = "127.0.0.1:80".parse().unwrap();
+
+    assert_matches!(
+      Server::bind_error(address, io::ErrorKind::PermissionDenied.into()),
+      Error::AddressPermissionDenied { address: a, .. } if a == address,
+    );
+
+    assert_matches!(
+      Server::bind_error(address, io::ErrorKind::AddrNotAvailable.into()),
+      Error::Bind { address: a, .. } if a == address,
+    );
+
+    assert_eq!(
+      Server::bind_error(address, io::ErrorKind::PermissionDenied.into()).to_string(),
+      "permission denied binding to 127.0.0.1:80, ports below 1024 usually require elevated \
+       privileges, try a higher port with `--address`",
+    );
+  }
+
+  #[test]
+  fn systemd_socket_missing() {
+    assert_matches!(
+      Server::systemd().unwrap_err(),
+      Error::SystemdSocketMissing { .. }
+    );
+  }
+
+  #[test]
+  fn graceful_shutdown() {
+    let _subscriber =
+      tracing::subscriber::set_default(tracing_subscriber::fmt().with_test_writer().finish());
+
+    let address = "127.0.0.1:0".parse().unwrap();
+
+    Server::listen(
+      Server::bind(address).unwrap(),
+      address,
+      Router::new(),
+      None,
+      None,
+      future::ready(()),
+    )
+    .unwrap();
+  }
+
+  #[tokio::test]
+  async fn video() {
+    let tempdir = tempdir();
+
+    let app = tempdir.path_utf8().join("app");
+    let content = tempdir.path_utf8().join("content");
+
+    fs::create_dir(&app).unwrap();
+    fs::write(app.join("metadata.yaml"), "type: app\nhandles: video").unwrap();
+    fs::write(app.join("index.html"), "<html>").unwrap();
+
+    fs::create_dir(&content).unwrap();
+    fs::write(content.join("metadata.yaml"), "type: video").unwrap();
+    fs::write(content.join("video.mp4"), "foo").unwrap();
+
+    for (root, output) in [(&app, "app.package"), (&content, "content.package")] {
+      subcommand::package::Package {
+        root: vec![root.clone()],
+        output: tempdir.path_utf8().join(output),
+        ..Default::default()
+      }
+      .run()
+      .unwrap();
+    }
+
+    let state = Extension(Arc::new(
+      State::new(
+        Source::open(&tempdir.path_utf8().join("app.package"), false, false, None).unwrap(),
+        Source::open(
+          &tempdir.path_utf8().join("content.package"),
+          false,
+          false,
+          None,
+        )
+        .unwrap(),
+      )
+      .unwrap(),
+    ));
+
+    let video = Server::content(
+      state,
+      Path("video".into()),
+      Query::default(),
+      HeaderMap::new(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(video.content_type, "video/mp4");
+    assert_eq!(video.content, b"foo".as_slice());
+  }
+
+  #[tokio::test]
+  async fn nested_app_paths() {
+    let tempdir = tempdir();
+
+    let app = tempdir.path_utf8().join("app");
+
+    fs::create_dir_all(app.join("assets/js")).unwrap();
+    fs::create_dir_all(app.join("assets/css")).unwrap();
+    fs::write(app.join("metadata.yaml"), "type: app\nhandles: comic").unwrap();
+    fs::write(app.join("index.html"), "<html>").unwrap();
+    fs::write(app.join("assets/js/app.js"), "foo").unwrap();
+    fs::write(app.join("assets/css/main.css"), "bar").unwrap();
+
+    subcommand::package::Package {
+      root: vec![app],
+      output: tempdir.path_utf8().join("app.package"),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    let router = Server::router(
+      State::new(
+        Source::open(&tempdir.path_utf8().join("app.package"), false, false, None).unwrap(),
+        Source::open(&content_package(), false, false, None).unwrap(),
+      )
+      .unwrap(),
+      Vec::new(),
+    );
+
+    for (path, content_type, content) in [
+      ("/app/assets/js/app.js", "text/javascript", "foo"),
+      ("/app//assets/js/app.js", "text/javascript", "foo"),
+      ("/app/assets/css/main.css", "text/css", "bar"),
+    ] {
+      let response = router
+        .clone()
+        .oneshot(
+          axum::http::Request::get(path)
+            .body(axum::body::Body::empty())
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+
+      assert_eq!(response.status(), StatusCode::OK, "{path}");
+      assert_eq!(response.headers()[header::CONTENT_TYPE], content_type);
+      assert_eq!(
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+          .await
+          .unwrap(),
+        content,
+      );
+    }
+  }
+
+  #[tokio::test]
+  async fn not_found_page() {
+    let tempdir = tempdir();
+
+    let app = tempdir.path_utf8().join("app");
+
+    fs::create_dir(&app).unwrap();
+    fs::write(app.join("metadata.yaml"), "type: app\nhandles: comic").unwrap();
+    fs::write(app.join("index.html"), "<html>").unwrap();
+    fs::write(app.join("404.html"), "<html>not found</html>").unwrap();
+
+    subcommand::package::Package {
+      root: vec![app],
+      output: tempdir.path_utf8().join("app.package"),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    let router = Server::router(
+      State::new(
+        Source::open(&tempdir.path_utf8().join("app.package"), false, false, None).unwrap(),
+        Source::open(&content_package(), false, false, None).unwrap(),
+      )
+      .unwrap(),
+      Vec::new(),
+    );
+
+    for path in ["/content/foo", "/app/foo.js"] {
+      let response = router
+        .clone()
+        .oneshot(
+          axum::http::Request::get(path)
+            .body(axum::body::Body::empty())
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+
+      assert_eq!(response.status(), StatusCode::NOT_FOUND, "{path}");
+      assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "text/html; charset=utf-8",
+      );
+      assert_eq!(
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+          .await
+          .unwrap(),
+        "<html>not found</html>",
+      );
+    }
+  }
+
+  #[tokio::test]
+  async fn path_traversal() {
+    let mut state = State::new(
+      Source::from(Package::load(&app_package()).unwrap()),
+      Source::from(Package::load(&content_package()).unwrap()),
+    )
+    .unwrap();
+
+    state.spa = true;
+
+    let router = Server::router(state, Vec::new());
+
+    for (path, body) in [
+      ("/app/../secret", "/app/../secret is not a valid path"),
+      (
+        "/app/foo/../index.js",
+        "/app/foo/../index.js is not a valid path",
+      ),
+      ("/app/%2e%2e/secret", "/app/../secret is not a valid path"),
+      ("/app/index.js%00", "/app/index.js\\0 is not a valid path"),
+      ("/content/../0", "/content/../0 is not a valid path"),
+      ("/content/0/..", "/content/0/.. is not a valid path"),
+    ] {
+      let response = router
+        .clone()
+        .oneshot(
+          axum::http::Request::get(path)
+            .body(axum::body::Body::empty())
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+
+      assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{path}");
+      assert_eq!(
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+          .await
+          .unwrap(),
+        body,
+      );
+    }
+
+    let response = router
+      .oneshot(
+        axum::http::Request::get("/app/..foo")
+          .body(axum::body::Body::empty())
+          .unwrap(),
+      )
+      .await
+      .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+  }
+
+  #[tokio::test]
+  async fn missing_payload() {
+    let state = Extension(Arc::new(
+      State::new(
+        Source::from(Package::load(&app_package()).unwrap()),
+        Source::from(Package {
+          files: HashMap::new(),
+          fingerprint: None,
+          manifest: Manifest::Comic {
+            content_types: Vec::new(),
+            dimensions: Vec::new(),
+            pages: vec![blake3::hash(b"foo")],
+            provenance: Provenance::default(),
+            thumbnails: Vec::new(),
+          },
+          manifest_hash: None,
+        }),
+      )
+      .unwrap(),
+    ));
+
+    assert_eq!(
+      Server::content(state, Path("0".into()), Query::default(), HeaderMap::new())
+        .await
+        .unwrap_err(),
+      ServerError::NotFound {
+        path: "/content/0".into(),
+      },
+    );
+  }
+
+  #[tokio::test]
+  async fn comic_page_content_type() {
+    let png = b"\x89PNG\r\n\x1a\n".to_vec();
+
+    let state = Extension(Arc::new(
+      State::new(
+        Source::from(Package::load(&app_package()).unwrap()),
+        Source::from(Package {
+          files: [
+            (blake3::hash(b"foo"), b"foo".to_vec()),
+            (blake3::hash(&png), png.clone()),
+          ]
+          .into(),
+          fingerprint: None,
+          manifest: Manifest::Comic {
+            content_types: vec!["image/avif".into(), "image/jpeg".into()],
+            dimensions: Vec::new(),
+            pages: vec![blake3::hash(b"foo"), blake3::hash(&png)],
+            provenance: Provenance::default(),
+            thumbnails: Vec::new(),
+          },
+          manifest_hash: None,
+        }),
+      )
+      .unwrap(),
+    ));
+
+    for (path, content_type) in [("0", "image/avif"), ("1", "image/png")] {
+      assert_eq!(
+        Server::content(
+          state.clone(),
+          Path(path.into()),
+          Query::default(),
+          HeaderMap::new()
+        )
+        .await
+        .unwrap()
+        .content_type,
+        content_type,
+      );
+    }
+  }
+
+  #[tokio::test]
+  async fn thumbnail() {
+    let page = b"\xFF\xD8\xFF".to_vec();
+    let thumbnail = b"\x89PNG\r\n\x1a\n".to_vec();
+
+    let state = Extension(Arc::new(
+      State::new(
+        Source::from(Package::load(&app_package()).unwrap()),
+        Source::from(Package {
+          files: [
+            (blake3::hash(&page), page.clone()),
+            (blake3::hash(&thumbnail), thumbnail.clone()),
+          ]
+          .into(),
+          fingerprint: None,
+          manifest: Manifest::Comic {
+            content_types: vec!["image/jpeg".into(), "image/jpeg".into()],
+            dimensions: Vec::new(),
+            pages: vec![blake3::hash(&page), blake3::hash(&page)],
+            provenance: Provenance::default(),
+            thumbnails: vec![Some(blake3::hash(&thumbnail)), Some(blake3::hash(&page))],
+          },
+          manifest_hash: None,
+        }),
+      )
+      .unwrap(),
+    ));
+
+    for (path, content_type, content) in [
+      ("thumb/0", "image/png", &thumbnail),
+      ("thumb/1", "image/jpeg", &page),
+    ] {
+      let resource = Server::content(
+        state.clone(),
+        Path(path.into()),
+        Query::default(),
+        HeaderMap::new(),
+      )
+      .await
+      .unwrap();
+
+      assert_eq!(resource.content_type, content_type);
+      assert_eq!(resource.content, *content);
+    }
+  }
+
+  #[test]
+  fn reload() {
+    let tempdir = tempdir();
+
+    let app = tempdir.path_utf8().join("app.package");
+    let content = tempdir.path_utf8().join("content.package");
+
+    fs::copy(app_package(), &app).unwrap();
+    fs::copy(content_package(), &content).unwrap();
+
+    let mut state = State::new(
+      Source::open(&app, false, false, None).unwrap(),
+      Source::open(&content, false, false, None).unwrap(),
+    )
+    .unwrap();
+
+    state.spa = true;
+
+    let shared = Arc::new(RwLock::new(Arc::new(state)));
+
+    let root = tempdir.path_utf8().join("root");
+
+    fs::create_dir(&root).unwrap();
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("0.jpg"), jpeg(b"foo")).unwrap();
+
+    subcommand::package::Package {
+      root: vec![root],
+      output: content.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    Server::reload(&shared, &app, &[content.clone().into()], false, false, None).unwrap();
+
+    let state = shared.read().unwrap().clone();
+    assert!(state.spa);
+    assert_eq!(state.content().manifest().summary().pages, Some(1),);
+    assert_eq!(state.content().file("0").unwrap().unwrap().2, jpeg(b"foo"));
+
+    fs::write(&content, "this-is-not-a-package").unwrap();
+
+    assert_matches!(
+      Server::reload(&shared, &app, &[content.clone().into()], false, false, None).unwrap_err(),
+      Error::PackageLoad { path, .. } if path == content,
+    );
+
+    assert!(Arc::ptr_eq(&state, &shared.read().unwrap()));
+  }
+
+  #[test]
+  fn named_package() {
+    assert_eq!(
+      "foo.package".parse::<NamedPackage>().unwrap(),
+      NamedPackage {
+        name: "foo".into(),
+        path: "foo.package".into(),
+      },
+    );
+
+    assert_eq!(
+      "bar=dir/foo.package".parse::<NamedPackage>().unwrap(),
+      NamedPackage {
+        name: "bar".into(),
+        path: "dir/foo.package".into(),
+      },
+    );
+
+    assert_eq!(
+      "https://example.com/foo.package?token=bar"
+        .parse::<NamedPackage>()
+        .unwrap(),
+      NamedPackage {
+        name: "foo".into(),
+        path: "https://example.com/foo.package?token=bar".into(),
+      },
+    );
+  }
+
+  #[tokio::test]
+  async fn custom_headers() {
+    let app = Package::build(
+      [
+        ("index.html".into(), b"<html>".to_vec()),
+        ("index.js".into(), b"js".to_vec()),
+      ]
+      .into(),
+      Template::App {
+        handles: [Type::Comic].into(),
+        headers: [(
+          "*.html".into(),
+          [("x-frame-options".into(), "DENY".into())].into(),
+        )]
+        .into(),
+        mime_overrides: BTreeMap::new(),
+        redirects: BTreeMap::new(),
+      },
+    )
+    .unwrap();
+
+    let router = Server::router(
+      State::new(
+        Source::from(Package::parse(&app).unwrap()),
+        Source::from(Package::load(&content_package()).unwrap()),
+      )
+      .unwrap(),
+      Vec::new(),
+    );
+
+    for (path, expected) in [
+      ("/", Some("DENY")),
+      ("/app/index.html", Some("DENY")),
+      ("/app/index.js", None),
+      ("/content/0", None),
+    ] {
+      let response = router
+        .clone()
+        .oneshot(
+          axum::http::Request::builder()
+            .uri(path)
+            .body(axum::body::Body::empty())
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+
+      assert_eq!(response.status(), StatusCode::OK, "{path}");
+      assert_eq!(
+        response
+          .headers()
+          .get(header::X_FRAME_OPTIONS)
+          .map(|value| value.to_str().unwrap()),
+        expected,
+        "{path}",
+      );
+    }
+  }
+
+  #[tokio::test]
+  async fn redirects() {
+    let app = Package::build(
+      [("index.html".into(), b"<html>".to_vec())].into(),
+      Template::App {
+        handles: [Type::Comic].into(),
+        headers: BTreeMap::new(),
+        mime_overrides: BTreeMap::new(),
+        redirects: [
+          (
+            "/".into(),
+            Redirect::Status {
+              location: "/app/home".into(),
+              status: 301,
+            },
+          ),
+          ("/old".into(), Redirect::Location("/app/new".into())),
+        ]
+        .into(),
+      },
+    )
+    .unwrap();
+
+    let router = Server::router(
+      State::new(
+        Source::from(Package::parse(&app).unwrap()),
+        Source::from(Package::load(&content_package()).unwrap()),
+      )
+      .unwrap(),
+      Vec::new(),
+    );
+
+    for (path, status, location) in [
+      ("/", StatusCode::MOVED_PERMANENTLY, Some("/app/home")),
+      ("/app/old", StatusCode::FOUND, Some("/app/new")),
+      ("/app/index.html", StatusCode::OK, None),
+      ("/content/0", StatusCode::OK, None),
+    ] {
+      let response = router
+        .clone()
+        .oneshot(
+          axum::http::Request::builder()
+            .uri(path)
+            .body(axum::body::Body::empty())
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+
+      assert_eq!(response.status(), status, "{path}");
+      assert_eq!(
+        response
+          .headers()
+          .get(header::LOCATION)
+          .map(|location| location.to_str().unwrap()),
+        location,
+        "{path}",
+      );
+    }
+  }
+
+  #[tokio::test]
+  async fn directory_index() {
+    let app = Package::build(
+      [
+        ("index.html".into(), b"root".to_vec()),
+        ("docs/index.html".into(), b"docs".to_vec()),
+        ("docs/intro.html".into(), b"intro".to_vec()),
+      ]
+      .into(),
+      Template::App {
+        handles: [Type::Comic].into(),
+        headers: BTreeMap::new(),
+        mime_overrides: BTreeMap::new(),
+        redirects: BTreeMap::new(),
+      },
+    )
+    .unwrap();
+
+    let router = Server::router(
+      State::new(
+        Source::from(Package::parse(&app).unwrap()),
+        Source::from(Package::load(&content_package()).unwrap()),
+      )
+      .unwrap(),
+      Vec::new(),
+    );
+
+    for (path, status, body) in [
+      ("/app/docs/", StatusCode::OK, "docs"),
+      ("/app/docs", StatusCode::OK, "docs"),
+      ("/app/docs/intro.html", StatusCode::OK, "intro"),
+      ("/app/docs/intro.html/", StatusCode::NOT_FOUND, "not found"),
+      ("/app/missing/", StatusCode::NOT_FOUND, "not found"),
+    ] {
+      let response = router
+        .clone()
+        .oneshot(
+          axum::http::Request::builder()
+            .uri(path)
+            .body(axum::body::Body::empty())
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+
+      assert_eq!(response.status(), status, "{path}");
+
+      let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+
+      assert!(
+        String::from_utf8_lossy(&bytes).contains(body),
+        "{path}: {}",
+        String::from_utf8_lossy(&bytes),
+      );
+    }
+  }
+
+  #[test]
+  fn app_handles_multiple_types() {
+    let app = Package::build(
+      [("index.html".into(), b"<html>".to_vec())].into(),
+      Template::App {
+        handles: [Type::Comic, Type::Gallery].into(),
+        headers: BTreeMap::new(),
+        mime_overrides: BTreeMap::new(),
+        redirects: BTreeMap::new(),
+      },
+    )
+    .unwrap();
+
+    let gallery = Package::parse(
+      &Package::build(
+        [("a.jpg".into(), b"foo".to_vec())].into(),
+        Template::Gallery {
+          captions: BTreeMap::new(),
+          images: vec!["a.jpg".into()],
+        },
+      )
+      .unwrap(),
+    )
+    .unwrap();
+
+    State::with_contents(
+      Source::from(Package::parse(&app).unwrap()),
+      vec![
+        (
+          "comic".into(),
+          Source::from(Package::load(&content_package()).unwrap()),
+        ),
+        ("gallery".into(), Source::from(gallery)),
+      ],
+    )
+    .unwrap();
+
+    assert_matches!(
+      State::new(Source::from(Package::parse(&app).unwrap()), Source::from(Package::parse(&app).unwrap())).unwrap_err(),
+      Error::ContentType { content: Type::App, handles, .. }
+      if handles == [Type::Comic, Type::Gallery].into(),
+    );
+  }
+
+  #[tokio::test]
+  async fn multiple_contents() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8().join("root");
+
+    fs::create_dir(&root).unwrap();
+    fs::write(root.join("metadata.yaml"), "type: comic").unwrap();
+    fs::write(root.join("0.jpg"), jpeg(b"foo")).unwrap();
+
+    let other = tempdir.path_utf8().join("other.package");
+
+    subcommand::package::Package {
+      root: vec![root],
+      output: other.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    let state = State::open(
+      &app_package(),
+      &[
+        content_package().into(),
+        format!("b={other}").parse().unwrap(),
+      ],
+      false,
+      false,
+      None,
+    )
+    .unwrap();
+
+    let router = Server::router(state, Vec::new());
+
+    for (path, status, body) in [
+      (
+        "/content/content/0",
+        StatusCode::OK,
+        Some(fs::read("content/comic/0.jpg").unwrap()),
+      ),
+      ("/content/b/0", StatusCode::OK, Some(jpeg(b"foo"))),
+      ("/content/c/0", StatusCode::NOT_FOUND, None),
+      ("/content/b/1", StatusCode::NOT_FOUND, None),
+      ("/api/manifest/b", StatusCode::OK, None),
+      ("/api/manifest/c", StatusCode::NOT_FOUND, None),
+      ("/api/content/b", StatusCode::OK, Some(br#"["0"]"#.to_vec())),
+      ("/api/content/c", StatusCode::NOT_FOUND, None),
+      (
+        "/api/summary/b",
+        StatusCode::OK,
+        Some(br#"{"type":"comic","pages":1}"#.to_vec()),
+      ),
+      ("/api/summary/c", StatusCode::NOT_FOUND, None),
+    ] {
+      let response = router
+        .clone()
+        .oneshot(
+          axum::http::Request::get(path)
+            .body(axum::body::Body::empty())
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+
+      assert_eq!(response.status(), status, "{path}");
+
+      if let Some(body) = body {
+        assert_eq!(
+          axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap(),
+          body,
+        );
+      }
+    }
+
+    assert_matches!(
+      State::open(
+        &app_package(),
+        &[content_package().into(), content_package().into()],
+        false,
+        false,
+        None,
+      )
+      .unwrap_err(),
+      Error::ContentNameDuplicated { name, .. } if name == "content",
+    );
+
+    assert_matches!(
+      State::open(
+        &app_package(),
+        &[content_package().into(), app_package().into()],
+        false,
+        false,
+        None,
+      )
+      .unwrap_err(),
+      Error::ContentType {
+        content: Type::App,
+        handles,
+        ..
+      }
+      if handles == [Type::Comic].into(),
+    );
+  }
+
+  #[tokio::test]
+  async fn inject_manifest() {
+    let mut state = State::new(
+      Source::from(Package::load(&app_package()).unwrap()),
+      Source::from(Package::load(&content_package()).unwrap()),
+    )
+    .unwrap();
+
+    state.inject_manifest = true;
+
+    let state = Extension(Arc::new(state));
+
+    let index = fs::read("apps/comic/index.html").unwrap();
+
+    let expected = inject::manifest(&index, state.content().manifest());
+
+    let root = Server::root(state.clone(), HeaderMap::new()).await.unwrap();
+    assert_eq!(root.content_type, mime::TEXT_HTML);
+    assert_eq!(root.content, expected);
+    assert_eq!(root.hash, Some(blake3::hash(&expected)));
+    assert!(String::from_utf8(root.content.to_vec())
+      .unwrap()
+      .contains("<script>window.__MANIFEST__={"));
+
+    let app = Server::app(state, Path("index.html".into()), HeaderMap::new())
+      .await
+      .unwrap();
+    assert_eq!(app.content, index);
+  }
+
+  #[tokio::test]
+  async fn spa() {
+    let mut state = State::new(
+      Source::from(Package::load(&app_package()).unwrap()),
+      Source::from(Package::load(&content_package()).unwrap()),
+    )
+    .unwrap();
+
+    state.spa = true;
+
+    let state = Extension(Arc::new(state));
+
+    let app = Server::app(state.clone(), Path("reader/5".into()), HeaderMap::new())
+      .await
+      .unwrap();
+    assert_eq!(app.content_type, mime::TEXT_HTML);
+    assert_eq!(app.content, fs::read("apps/comic/index.html").unwrap());
+
+    let app = Server::app(state.clone(), Path("index.js".into()), HeaderMap::new())
+      .await
+      .unwrap();
+    assert_eq!(app.content_type, mime::TEXT_JAVASCRIPT);
+
+    assert_eq!(
+      Server::content(
+        state,
+        Path("foo".into()),
+        Query::default(),
+        HeaderMap::new()
+      )
+      .await
+      .unwrap_err(),
+      ServerError::NotFound {
+        path: "/content/foo".into(),
+      },
+    );
+  }
+
+  #[tokio::test]
+  async fn routes() {
+    let state = Extension(Arc::new(
+      State::new(
+        Source::from(Package::load(&app_package()).unwrap()),
+        Source::from(Package::load(&content_package()).unwrap()),
+      )
+      .unwrap(),
+    ));
+
+    let root = Server::root(state.clone(), HeaderMap::new()).await.unwrap();
+    assert_eq!(root.content_type, mime::TEXT_HTML);
+    assert!(root.content.starts_with(b"<html>"));
+
+    let manifest = Server::manifest(state.clone(), HeaderMap::new()).await;
+    assert_eq!(manifest.content_type, mime::APPLICATION_JSON);
+    assert!(
+      manifest
+        .content
+        .starts_with(b"{\"schema\":1,\"type\":\"comic\""),
+      "{}",
+      String::from_utf8(manifest.content.to_vec()).unwrap()
+    );
+
+    let summary = Server::summary(state.clone(), HeaderMap::new()).await;
+    assert_eq!(summary.content_type, mime::APPLICATION_JSON);
+    assert_eq!(summary.content, br#"{"type":"comic","pages":3}"#.as_slice());
+
+    let entries = Server::entries(state.clone(), HeaderMap::new()).await;
+    assert_eq!(entries.content_type, mime::APPLICATION_JSON);
+    assert_eq!(entries.content, br#"["0","1","2"]"#.as_slice());
+
+    let app = Server::app(state.clone(), Path("index.js".into()), HeaderMap::new())
+      .await
+      .unwrap();
+    assert_eq!(app.content_type, mime::TEXT_JAVASCRIPT);
+    assert!(
+      app.content.starts_with(b"const response ="),
+      "{}",
+      String::from_utf8(app.content.to_vec()).unwrap()
+    );
+
+    let content = Server::content(
+      state.clone(),
+      Path("0".into()),
+      Query::default(),
+      HeaderMap::new(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(content.content_type, mime::IMAGE_JPEG);
+    assert!(
+      content.content.starts_with(b"\xff\xd8\xff\xe0\x00\x10JFIF"),
+      "{}",
+      String::from_utf8_lossy(&content.content),
+    );
+
+    assert_eq!(
+      Server::content(
+        state.clone(),
+        Path("foo".into()),
+        Query::default(),
+        HeaderMap::new()
+      )
+      .await
+      .unwrap_err(),
+      ServerError::NotFound {
+        path: "/content/foo".into(),
+      },
+    );
+
+    assert_eq!(
+      Server::app(state.clone(), Path("foo".into()), HeaderMap::new())
+        .await
+        .unwrap_err(),
+      ServerError::NotFound {
+        path: "/app/foo".into(),
+      },
+    );
+  }
+
+  #[tokio::test]
+  async fn verify_on_read() {
+    let tempdir = tempdir();
+
+    let content = tempdir.path_utf8().join("content.package");
+
+    let page = fs::read("content/comic/0.jpg").unwrap();
+
+    let mut bytes = fs::read(content_package()).unwrap();
+    let offset = bytes
+      .windows(page.len())
+      .position(|window| window == page)
+      .unwrap();
+    bytes[offset + page.len() - 1] ^= 1;
+    fs::write(&content, bytes).unwrap();
+
+    for (lazy, mmap) in [(true, false), (false, true)] {
+      let state = Extension(Arc::new(
+        State::new(
+          Source::open(&app_package(), lazy, mmap, None).unwrap(),
+          Source::open(&content, lazy, mmap, None).unwrap(),
+        )
+        .unwrap(),
+      ));
+
+      Server::content(
+        state.clone(),
+        Path("1".into()),
+        Query::default(),
+        HeaderMap::new(),
+      )
+      .await
+      .unwrap();
+
+      assert_eq!(
+        Server::content(
+          state.clone(),
+          Path("0".into()),
+          Query::default(),
+          HeaderMap::new()
+        )
+        .await
+        .unwrap_err(),
+        ServerError::Internal {
+          path: "/content/0".into(),
+        },
+      );
+    }
+  }
+
+  #[tokio::test]
+  async fn files_are_served_without_copying() {
+    for mmap in [false, true] {
+      let state = Arc::new(
+        State::new(
+          Source::open(&app_package(), false, mmap, None).unwrap(),
+          Source::open(&content_package(), false, mmap, None).unwrap(),
+        )
+        .unwrap(),
+      );
+
+      let resource = Server::content(
+        Extension(state.clone()),
+        Path("0".into()),
+        Query::default(),
+        HeaderMap::new(),
+      )
+      .await
+      .unwrap();
+
+      let (_content_type, hash) = state.content().manifest().file("0").unwrap();
+
+      let stored = match state.content() {
+        Source::Loaded(package) => package.get(hash).unwrap().as_ptr(),
+        Source::Mapped(mapped) => mapped.file(hash).unwrap().unwrap().as_ptr(),
+        Source::Lazy(_) => unreachable!(),
+      };
+
+      assert_eq!(resource.content.as_ptr(), stored);
+    }
+  }
+
+  #[tokio::test]
+  async fn cache_bytes() {
+    let page = fs::read("content/comic/0.jpg").unwrap();
+
+    let mut corrupted = page.clone();
+    *corrupted.last_mut().unwrap() ^= 1;
+
+    for (cache, expected) in [(Some(page.len().into_u64()), &page), (None, &corrupted)] {
+      let tempdir = tempdir();
+
+      let content = tempdir.path_utf8().join("content.package");
+
+      fs::copy(content_package(), &content).unwrap();
+
+      let router = Server::router(
+        State::open(
+          &app_package(),
+          &[content.clone().into()],
+          true,
+          false,
+          cache,
+        )
+        .unwrap(),
+        Vec::new(),
+      );
+
+      let body = || async {
+        let response = router
+          .clone()
+          .oneshot(
+            axum::http::Request::get("/content/0")
+              .body(axum::body::Body::empty())
+              .unwrap(),
+          )
+          .await
+          .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+          .await
+          .unwrap()
+      };
+
+      assert_eq!(body().await, page);
+
+      // overwrite the page on disk, so that it is only served unchanged if the
+      // first read was cached
+      let mut bytes = fs::read(&content).unwrap();
+      let offset = bytes
+        .windows(page.len())
+        .position(|window| window == page)
+        .unwrap();
+      bytes[offset..offset + page.len()].copy_from_slice(&corrupted);
+      fs::write(&content, bytes).unwrap();
+
+      assert_eq!(body().await, *expected);
+    }
+  }
+
+  #[tokio::test]
+  async fn embedded() {
+    let app = Package::parse(&fs::read(app_package()).unwrap()).unwrap();
+    let content = Package::parse(&fs::read(content_package()).unwrap()).unwrap();
+
+    let state = Extension(Arc::new(
+      State::new(Source::from(app), Source::from(content)).unwrap(),
+    ));
+
+    let root = Server::root(state.clone(), HeaderMap::new()).await.unwrap();
+    assert_eq!(root.content_type, mime::TEXT_HTML);
+    assert!(root.content.starts_with(b"<html>"));
+
+    let content = Server::content(state, Path("0".into()), Query::default(), HeaderMap::new())
+      .await
+      .unwrap();
+    assert_eq!(content.content_type, mime::IMAGE_JPEG);
+  }
+
+  #[tokio::test]
+  async fn range() {
+    let state = Extension(Arc::new(
+      State::new(
+        Source::from(Package::load(&app_package()).unwrap()),
+        Source::from(Package::load(&content_package()).unwrap()),
+      )
+      .unwrap(),
+    ));
+
+    let page = fs::read("content/comic/0.jpg").unwrap();
+    let len = page.len();
+
+    async fn request(
+      state: &Extension<Arc<State>>,
+      range: &str,
+    ) -> (StatusCode, HeaderMap, Vec<u8>) {
+      let mut headers = HeaderMap::new();
+      headers.insert(header::RANGE, range.parse().unwrap());
+
+      let response = Server::content(state.clone(), Path("0".into()), Query::default(), headers)
+        .await
+        .unwrap()
+        .into_response();
+
+      let status = response.status();
+      let headers = response.headers().clone();
+      let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap()
+        .to_vec();
+
+      (status, headers, body)
+    }
+
+    let response = Server::content(
+      state.clone(),
+      Path("0".into()),
+      Query::default(),
+      HeaderMap::new(),
+    )
+    .await
+    .unwrap()
+    .into_response();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::ACCEPT_RANGES], "bytes");
+
+    let (status, headers, body) = request(&state, "bytes=0-9").await;
+    assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+    assert_eq!(headers[header::CONTENT_TYPE], "image/jpeg");
+    assert_eq!(headers[header::CONTENT_RANGE], format!("bytes 0-9/{len}"));
+    assert_eq!(body, &page[..10]);
+
+    let (status, headers, body) = request(&state, "bytes=-4").await;
+    assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+      headers[header::CONTENT_RANGE],
+      format!("bytes {}-{}/{len}", len - 4, len - 1),
+    );
+    assert_eq!(body, &page[len - 4..]);
+
+    let (status, headers, body) = request(&state, "bytes=0-1,4-5").await;
+    assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+    let boundary = blake3::hash(&page).to_hex();
+    assert_eq!(
+      headers[header::CONTENT_TYPE],
+      format!("multipart/byteranges; boundary={boundary}").as_str(),
+    );
+    let mut expected = Vec::new();
+    for (start, end) in [(0, 1), (4, 5)] {
+      expected.extend_from_slice(
+        format!(
+          "--{boundary}\r\nContent-Type: image/jpeg\r\nContent-Range: bytes {start}-{end}/{len}\r\n\r\n"
+        )
+        .as_bytes(),
+      );
+      expected.extend_from_slice(&page[start..=end]);
+      expected.extend_from_slice(b"\r\n");
+    }
+    expected.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+    assert_eq!(body, expected);
+
+    let (status, headers, body) = request(&state, &format!("bytes={len}-")).await;
+    assert_eq!(status, StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(headers[header::CONTENT_RANGE], format!("bytes */{len}"));
+    assert!(body.is_empty());
+
+    let (status, _headers, body) = request(&state, "bytes=5-4").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, page);
+
+    let response = Server::summary(state, HeaderMap::new())
+      .await
+      .into_response();
+    assert!(!response.headers().contains_key(header::ACCEPT_RANGES));
+  }
+
+  #[tokio::test]
+  async fn download() {
+    let router = Server::router(
+      State::new(
+        Source::from(Package::load(&app_package()).unwrap()),
+        Source::from(Package::load(&content_package()).unwrap()),
+      )
+      .unwrap(),
+      Vec::new(),
+    );
+
+    for (path, disposition) in [
+      ("/content/0", None),
+      ("/content/0?download=0", None),
+      (
+        "/content/0?download=1",
+        Some("attachment; filename=\"0.jpg\""),
+      ),
+    ] {
+      let response = router
+        .clone()
+        .oneshot(
+          axum::http::Request::get(path)
+            .body(axum::body::Body::empty())
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+
+      assert_eq!(response.status(), StatusCode::OK, "{path}");
+      assert_eq!(
+        response
+          .headers()
+          .get(header::CONTENT_DISPOSITION)
+          .map(|value| value.to_str().unwrap()),
+        disposition,
+        "{path}",
+      );
+
+      let len = response.headers()[header::CONTENT_LENGTH]
+        .to_str()
+        .unwrap()
+        .parse::<usize>()
+        .unwrap();
+
+      let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+
+      assert_eq!(len, body.len(), "{path}");
+    }
 
     assert_eq!(
-      Server::content(state.clone(), Path("foo".into()))
+      Resource::content_disposition("café \"1\".jpg"),
+      "attachment; filename=\"caf_ _1_.jpg\"; filename*=UTF-8''caf%C3%A9%20%221%22.jpg",
+    );
+  }
+
+  #[tokio::test]
+  async fn manifest_accept() {
+    let router = Server::router(
+      State::new(
+        Source::from(Package::load(&app_package()).unwrap()),
+        Source::from(Package::load(&content_package()).unwrap()),
+      )
+      .unwrap(),
+      Vec::new(),
+    );
+
+    let manifest = Package::load(&content_package()).unwrap().manifest;
+
+    for (accept, content_type) in [
+      (None, "application/json"),
+      (Some("application/json"), "application/json"),
+      (Some("application/yaml"), "application/yaml"),
+      (Some("application/cbor"), "application/cbor"),
+      (Some("text/html"), "application/json"),
+    ] {
+      let mut request = axum::http::Request::get("/api/manifest");
+
+      if let Some(accept) = accept {
+        request = request.header(header::ACCEPT, accept);
+      }
+
+      let response = router
+        .clone()
+        .oneshot(request.body(axum::body::Body::empty()).unwrap())
         .await
-        .unwrap_err(),
-      ServerError::NotFound {
-        path: "/content/foo".into(),
-      },
+        .unwrap();
+
+      assert_eq!(response.status(), StatusCode::OK);
+      assert_eq!(response.headers()[header::CONTENT_TYPE], content_type);
+      assert_eq!(
+        response
+          .headers()
+          .get_all(header::VARY)
+          .iter()
+          .collect::<Vec<&HeaderValue>>(),
+        if content_type == "application/json" {
+          ["accept", "accept-encoding"].as_slice()
+        } else {
+          ["accept"].as_slice()
+        },
+      );
+
+      let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+
+      let actual: Manifest = match content_type {
+        "application/cbor" => ciborium::from_reader(body.as_ref()).unwrap(),
+        "application/json" => serde_json::from_slice(&body).unwrap(),
+        "application/yaml" => serde_yaml::from_slice(&body).unwrap(),
+        _ => unreachable!(),
+      };
+
+      assert_eq!(actual, manifest, "{accept:?}");
+    }
+  }
+
+  #[tokio::test]
+  async fn method_not_allowed() {
+    let router = Server::router(
+      State::new(
+        Source::from(Package::load(&app_package()).unwrap()),
+        Source::from(Package::load(&content_package()).unwrap()),
+      )
+      .unwrap(),
+      Vec::new(),
+    );
+
+    for method in [Method::POST, Method::PUT, Method::DELETE] {
+      let response = router
+        .clone()
+        .oneshot(
+          axum::http::Request::builder()
+            .method(method.clone())
+            .uri("/content/0")
+            .body(axum::body::Body::empty())
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+
+      assert_eq!(
+        response.status(),
+        StatusCode::METHOD_NOT_ALLOWED,
+        "{method}"
+      );
+      assert_eq!(response.headers()[header::ALLOW], "GET, HEAD", "{method}");
+    }
+
+    let response = router
+      .oneshot(
+        axum::http::Request::head("/content/0")
+          .body(axum::body::Body::empty())
+          .unwrap(),
+      )
+      .await
+      .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+  }
+
+  #[test]
+  fn url() {
+    #[track_caller]
+    fn case(address: &str, https: bool, path: &str, expected: &str) {
+      assert_eq!(Server::url(address.parse().unwrap(), https, path), expected);
+    }
+
+    case("0.0.0.0:80", false, "/", "http://127.0.0.1:80/");
+    case("[::]:8080", false, "/", "http://[::1]:8080/");
+    case(
+      "192.168.1.2:443",
+      true,
+      "/reader/",
+      "https://192.168.1.2:443/reader/",
+    );
+  }
+
+  #[tokio::test]
+  async fn cache_control() {
+    for no_cache in [false, true] {
+      let mut state = State::new(
+        Source::from(Package::load(&app_package()).unwrap()),
+        Source::from(Package::load(&content_package()).unwrap()),
+      )
+      .unwrap();
+
+      state.no_cache = no_cache;
+
+      let router = Server::router(state, Vec::new());
+
+      for (path, immutable) in [
+        ("/", false),
+        ("/app/index.js", !no_cache),
+        ("/content/0", !no_cache),
+        ("/api/manifest", false),
+      ] {
+        let response = router
+          .clone()
+          .oneshot(
+            axum::http::Request::get(path)
+              .body(axum::body::Body::empty())
+              .unwrap(),
+          )
+          .await
+          .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK, "{path}");
+        assert_eq!(
+          response
+            .headers()
+            .get(header::CACHE_CONTROL)
+            .map(|value| value.to_str().unwrap()),
+          immutable.then_some("public, max-age=31536000, immutable"),
+          "{path}",
+        );
+      }
+    }
+  }
+
+  #[tokio::test]
+  async fn etag() {
+    let state = Extension(Arc::new(
+      State::new(
+        Source::from(Package::load(&app_package()).unwrap()),
+        Source::from(Package::load(&content_package()).unwrap()),
+      )
+      .unwrap(),
+    ));
+
+    let etag = format!(
+      "\"{}\"",
+      blake3::hash(&fs::read("content/comic/0.jpg").unwrap()).to_hex()
+    );
+
+    async fn request(state: &Extension<Arc<State>>, if_none_match: Option<&str>) -> Response {
+      let mut headers = HeaderMap::new();
+
+      if let Some(if_none_match) = if_none_match {
+        headers.insert(header::IF_NONE_MATCH, if_none_match.parse().unwrap());
+      }
+
+      Server::content(state.clone(), Path("0".into()), Query::default(), headers)
+        .await
+        .unwrap()
+        .into_response()
+    }
+
+    let response = request(&state, None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::ETAG], etag.as_str());
+
+    for if_none_match in [
+      etag.clone(),
+      format!("W/{etag}"),
+      format!("\"foo\", {etag}"),
+      "*".into(),
+    ] {
+      let response = request(&state, Some(&if_none_match)).await;
+      assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+      assert_eq!(response.headers()[header::ETAG], etag.as_str());
+      assert!(axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap()
+        .is_empty());
+    }
+
+    let response = request(&state, Some("\"foo\"")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::ETAG], etag.as_str());
+
+    let response = Server::summary(state, HeaderMap::new())
+      .await
+      .into_response();
+    assert!(!response.headers().contains_key(header::ETAG));
+  }
+
+  #[tokio::test]
+  async fn last_modified() {
+    let state = Extension(Arc::new(
+      State::open(
+        &app_package(),
+        &[content_package().into()],
+        false,
+        false,
+        None,
+      )
+      .unwrap(),
+    ));
+
+    let last_modified = httpdate::fmt_http_date(
+      fs::metadata(app_package())
+        .unwrap()
+        .modified()
+        .unwrap()
+        .max(fs::metadata(content_package()).unwrap().modified().unwrap()),
+    );
+
+    async fn request(
+      state: &Extension<Arc<State>>,
+      headers: &[(header::HeaderName, &str)],
+    ) -> Response {
+      let mut map = HeaderMap::new();
+
+      for (name, value) in headers {
+        map.insert(name, value.parse().unwrap());
+      }
+
+      Server::content(state.clone(), Path("0".into()), Query::default(), map)
+        .await
+        .unwrap()
+        .into_response()
+    }
+
+    let response = request(&state, &[]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+      response.headers()[header::LAST_MODIFIED],
+      last_modified.as_str()
+    );
+
+    let response = request(&state, &[(header::IF_MODIFIED_SINCE, &last_modified)]).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(
+      response.headers()[header::LAST_MODIFIED],
+      last_modified.as_str()
     );
 
+    let response = request(
+      &state,
+      &[(header::IF_MODIFIED_SINCE, "Thu, 01 Jan 1970 00:00:00 GMT")],
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = request(&state, &[(header::IF_MODIFIED_SINCE, "yesterday")]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = request(
+      &state,
+      &[
+        (header::IF_MODIFIED_SINCE, &last_modified),
+        (header::IF_NONE_MATCH, "\"foo\""),
+      ],
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = Server::root(state, HeaderMap::new())
+      .await
+      .unwrap()
+      .into_response();
     assert_eq!(
-      Server::app(state.clone(), Path("foo".into()))
+      response.headers()[header::LAST_MODIFIED],
+      last_modified.as_str()
+    );
+  }
+
+  #[tokio::test]
+  async fn compression() {
+    let state = Extension(Arc::new(
+      State::new(
+        Source::from(Package::load(&app_package()).unwrap()),
+        Source::from(Package::load(&content_package()).unwrap()),
+      )
+      .unwrap(),
+    ));
+
+    let js = fs::read("apps/comic/index.js").unwrap();
+
+    async fn request(state: &Extension<Arc<State>>, path: &str, accept_encoding: &str) -> Response {
+      let mut headers = HeaderMap::new();
+      headers.insert(header::ACCEPT_ENCODING, accept_encoding.parse().unwrap());
+
+      Server::app(state.clone(), Path(path.into()), headers)
         .await
-        .unwrap_err(),
-      ServerError::NotFound {
-        path: "/app/foo".into(),
-      },
+        .unwrap()
+        .into_response()
+    }
+
+    async fn body(response: Response) -> Vec<u8> {
+      axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap()
+        .to_vec()
+    }
+
+    let response = request(&state, "index.js", "gzip").await;
+    assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+    assert_eq!(response.headers()[header::VARY], "accept-encoding");
+    assert_eq!(
+      response.headers()[header::ETAG],
+      format!("\"{}-gzip\"", blake3::hash(&js).to_hex()).as_str(),
+    );
+    let mut decompressed = Vec::new();
+    flate2::read::GzDecoder::new(body(response).await.as_slice())
+      .read_to_end(&mut decompressed)
+      .unwrap();
+    assert_eq!(decompressed, js);
+
+    let response = request(&state, "index.js", "gzip, br").await;
+    assert_eq!(response.headers()[header::CONTENT_ENCODING], "br");
+    let mut decompressed = Vec::new();
+    brotli::Decompressor::new(body(response).await.as_slice(), 4096)
+      .read_to_end(&mut decompressed)
+      .unwrap();
+    assert_eq!(decompressed, js);
+
+    let response = request(&state, "index.js", "identity").await;
+    assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+    assert_eq!(response.headers()[header::VARY], "accept-encoding");
+    assert_eq!(body(response).await, js);
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::ACCEPT_ENCODING, "gzip".parse().unwrap());
+    let response = Server::content(state.clone(), Path("0".into()), Query::default(), headers)
+      .await
+      .unwrap()
+      .into_response();
+    assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+    assert!(!response.headers().contains_key(header::VARY));
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::ACCEPT_ENCODING, "gzip".parse().unwrap());
+    let response = Server::manifest(state, headers).await.into_response();
+    assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+  }
+
+  #[tokio::test]
+  async fn compression_etags() {
+    let state = Extension(Arc::new(
+      State::new(
+        Source::from(Package::load(&app_package()).unwrap()),
+        Source::from(Package::load(&content_package()).unwrap()),
+      )
+      .unwrap(),
+    ));
+
+    async fn request(
+      state: &Extension<Arc<State>>,
+      accept_encoding: &str,
+      if_none_match: Option<&str>,
+    ) -> Response {
+      let mut headers = HeaderMap::new();
+      headers.insert(header::ACCEPT_ENCODING, accept_encoding.parse().unwrap());
+
+      if let Some(if_none_match) = if_none_match {
+        headers.insert(header::IF_NONE_MATCH, if_none_match.parse().unwrap());
+      }
+
+      Server::app(state.clone(), Path("index.js".into()), headers)
+        .await
+        .unwrap()
+        .into_response()
+    }
+
+    let mut etags = Vec::new();
+
+    for accept_encoding in ["identity", "gzip", "br"] {
+      let response = request(&state, accept_encoding, None).await;
+      assert_eq!(response.status(), StatusCode::OK);
+      assert_eq!(response.headers()[header::VARY], "accept-encoding");
+      etags.push(
+        response.headers()[header::ETAG]
+          .to_str()
+          .unwrap()
+          .to_owned(),
+      );
+    }
+
+    assert_eq!(etags.iter().collect::<HashSet<&String>>().len(), 3);
+
+    for (i, accept_encoding) in ["identity", "gzip", "br"].into_iter().enumerate() {
+      for (j, etag) in etags.iter().enumerate() {
+        let response = request(&state, accept_encoding, Some(etag)).await;
+
+        assert_eq!(
+          response.status(),
+          if i == j {
+            StatusCode::NOT_MODIFIED
+          } else {
+            StatusCode::OK
+          },
+        );
+        assert_eq!(response.headers()[header::VARY], "accept-encoding");
+        assert_eq!(response.headers()[header::ETAG], etags[i].as_str());
+      }
+    }
+  }
+
+  #[tokio::test]
+  async fn head() {
+    let router = Server::router(
+      State::new(
+        Source::from(Package::load(&app_package()).unwrap()),
+        Source::from(Package::load(&content_package()).unwrap()),
+      )
+      .unwrap(),
+      Vec::new(),
+    );
+
+    for (path, content_type, len) in [
+      (
+        "/",
+        "text/html",
+        fs::metadata("apps/comic/index.html").unwrap().len(),
+      ),
+      (
+        "/app/index.js",
+        "text/javascript",
+        fs::metadata("apps/comic/index.js").unwrap().len(),
+      ),
+      (
+        "/content/0",
+        "image/jpeg",
+        fs::metadata("content/comic/0.jpg").unwrap().len(),
+      ),
+      (
+        "/api/manifest",
+        "application/json",
+        Package::load(&content_package())
+          .unwrap()
+          .manifest_json()
+          .len()
+          .into_u64(),
+      ),
+    ] {
+      let response = router
+        .clone()
+        .oneshot(
+          axum::http::Request::head(path)
+            .body(axum::body::Body::empty())
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+
+      assert_eq!(response.status(), StatusCode::OK, "{path}");
+      assert_eq!(response.headers()[header::CONTENT_TYPE], content_type);
+      assert_eq!(
+        response.headers()[header::CONTENT_LENGTH],
+        len.to_string().as_str(),
+        "{path}",
+      );
+      assert!(axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap()
+        .is_empty());
+    }
+  }
+
+  #[tokio::test]
+  async fn base_path() {
+    let mut state = State::new(
+      Source::from(Package::load(&app_package()).unwrap()),
+      Source::from(Package::load(&content_package()).unwrap()),
+    )
+    .unwrap();
+
+    state.base_path = "/reader".into();
+
+    let router = Server::router(state, Vec::new());
+
+    for (path, status, body) in [
+      ("/reader", StatusCode::OK, None),
+      ("/reader/app/index.js", StatusCode::OK, None),
+      ("/reader/content/0", StatusCode::OK, None),
+      ("/reader/api/summary", StatusCode::OK, None),
+      ("/app/index.js", StatusCode::NOT_FOUND, None),
+      (
+        "/reader/content/foo",
+        StatusCode::NOT_FOUND,
+        Some("/reader/content/foo not found"),
+      ),
+    ] {
+      let response = router
+        .clone()
+        .oneshot(
+          axum::http::Request::get(path)
+            .body(axum::body::Body::empty())
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+
+      assert_eq!(response.status(), status, "{path}");
+
+      if let Some(body) = body {
+        assert_eq!(
+          axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap(),
+          body,
+        );
+      }
+    }
+  }
+
+  #[tokio::test]
+  async fn healthz() {
+    let response = Server::router(
+      State::new(
+        Source::from(Package::load(&app_package()).unwrap()),
+        Source::from(Package::load(&content_package()).unwrap()),
+      )
+      .unwrap(),
+      Vec::new(),
+    )
+    .oneshot(
+      axum::http::Request::get("/healthz")
+        .body(axum::body::Body::empty())
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "text/plain");
+    assert_eq!(
+      axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap(),
+      "ok",
+    );
+  }
+
+  #[tokio::test]
+  async fn cors() {
+    let state = || {
+      State::new(
+        Source::from(Package::load(&app_package()).unwrap()),
+        Source::from(Package::load(&content_package()).unwrap()),
+      )
+      .unwrap()
+    };
+
+    let request = |method: Method, origin: &str| {
+      axum::http::Request::builder()
+        .method(method)
+        .uri("/api/manifest")
+        .header(header::ORIGIN, origin)
+        .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+        .body(axum::body::Body::empty())
+        .unwrap()
+    };
+
+    let router = Server::router(state(), vec!["https://example.com".into()]);
+
+    let response = router
+      .clone()
+      .oneshot(request(Method::GET, "https://example.com"))
+      .await
+      .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+      response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+      "https://example.com",
+    );
+    assert_eq!(
+      response.headers()[header::ACCESS_CONTROL_ALLOW_METHODS],
+      "GET, HEAD, OPTIONS",
+    );
+
+    let response = router
+      .clone()
+      .oneshot(request(Method::OPTIONS, "https://example.com"))
+      .await
+      .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(
+      response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+      "https://example.com",
     );
+
+    let response = router
+      .oneshot(request(Method::GET, "https://example.org"))
+      .await
+      .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response
+      .headers()
+      .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+
+    let response = Server::router(state(), vec!["*".into()])
+      .oneshot(request(Method::GET, "https://example.org"))
+      .await
+      .unwrap();
+    assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+
+    let response = Server::router(state(), Vec::new())
+      .oneshot(request(Method::GET, "https://example.com"))
+      .await
+      .unwrap();
+    assert!(!response
+      .headers()
+      .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+  }
+
+  #[tokio::test]
+  async fn max_connections() {
+    let connections = Arc::new(Semaphore::new(2));
+
+    let mut state = State::new(
+      Source::from(Package::load(&app_package()).unwrap()),
+      Source::from(Package::load(&content_package()).unwrap()),
+    )
+    .unwrap();
+
+    state.connections = Some(connections.clone());
+
+    let router = Server::router(state, Vec::new());
+
+    let request = || {
+      axum::http::Request::builder()
+        .uri("/healthz")
+        .body(axum::body::Body::empty())
+        .unwrap()
+    };
+
+    let response = router.clone().oneshot(request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let held = connections.acquire().await.unwrap();
+
+    let response = router.clone().oneshot(request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let saturated = connections.acquire().await.unwrap();
+
+    let response = router.clone().oneshot(request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    drop(saturated);
+    drop(held);
+
+    let response = router.oneshot(request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(connections.available_permits(), 2);
   }
 }
@@ -0,0 +1,74 @@
+use {
+  super::*,
+  axum::{
+    extract::{Request, State},
+    middleware::Next,
+  },
+};
+
+const ALLOW_METHODS: &str = "GET, HEAD, OPTIONS";
+
+pub async fn cors(
+  State(origins): State<Arc<Vec<String>>>,
+  request: Request,
+  next: Next,
+) -> Response {
+  let allow_origin = request
+    .headers()
+    .get(header::ORIGIN)
+    .and_then(|origin| origin.to_str().ok())
+    .and_then(|origin| {
+      origins
+        .iter()
+        .find(|allowed| *allowed == "*" || *allowed == origin)
+        .map(|allowed| {
+          if allowed == "*" {
+            "*".to_owned()
+          } else {
+            origin.to_owned()
+          }
+        })
+    });
+
+  let Some(allow_origin) = allow_origin else {
+    return next.run(request).await;
+  };
+
+  let preflight = request.method() == Method::OPTIONS
+    && request
+      .headers()
+      .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+
+  let mut response = if preflight {
+    let mut response = StatusCode::NO_CONTENT.into_response();
+
+    if let Some(allow_headers) = request
+      .headers()
+      .get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+    {
+      response
+        .headers_mut()
+        .insert(header::ACCESS_CONTROL_ALLOW_HEADERS, allow_headers.clone());
+    }
+
+    response
+  } else {
+    next.run(request).await
+  };
+
+  let headers = response.headers_mut();
+
+  headers.insert(
+    header::ACCESS_CONTROL_ALLOW_ORIGIN,
+    allow_origin.parse().unwrap(),
+  );
+
+  headers.insert(
+    header::ACCESS_CONTROL_ALLOW_METHODS,
+    HeaderValue::from_static(ALLOW_METHODS),
+  );
+
+  headers.append(header::VARY, HeaderValue::from_static("origin"));
+
+  response
+}
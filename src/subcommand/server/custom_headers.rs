@@ -0,0 +1,115 @@
+use super::*;
+
+#[derive(Debug)]
+pub struct CustomHeaders {
+  globs: GlobSet,
+  headers: Vec<Vec<(String, String)>>,
+}
+
+impl CustomHeaders {
+  pub fn new(manifest: &Manifest) -> Result<Self> {
+    let Manifest::App { headers, .. } = manifest else {
+      return Ok(Self {
+        globs: GlobSet::empty(),
+        headers: Vec::new(),
+      });
+    };
+
+    let mut builder = GlobSetBuilder::new();
+
+    for glob in headers.keys() {
+      builder
+        .add(Glob::new(glob.trim_start_matches('/')).context(error::InvalidHeaderGlob { glob })?);
+    }
+
+    let globs = builder.build().context(error::InvalidHeaderGlob {
+      glob: headers.keys().cloned().collect::<Vec<String>>().join(", "),
+    })?;
+
+    Ok(Self {
+      globs,
+      headers: headers
+        .values()
+        .map(|headers| {
+          headers
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
+        })
+        .collect(),
+    })
+  }
+
+  pub fn get(&self, path: &str) -> Vec<(String, String)> {
+    let mut matches = self.globs.matches(path.trim_start_matches('/'));
+
+    matches.sort_unstable();
+
+    matches
+      .into_iter()
+      .flat_map(|i| self.headers[i].iter().cloned())
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn manifest(headers: &[(&str, &str, &str)]) -> Manifest {
+    let mut globs = BTreeMap::<String, BTreeMap<String, String>>::new();
+
+    for (glob, name, value) in headers {
+      globs
+        .entry((*glob).into())
+        .or_default()
+        .insert((*name).into(), (*value).into());
+    }
+
+    Manifest::App {
+      handles: [Type::Comic].into(),
+      headers: globs,
+      mime_overrides: BTreeMap::new(),
+      paths: BTreeMap::new(),
+      provenance: Provenance::default(),
+      redirects: BTreeMap::new(),
+    }
+  }
+
+  #[test]
+  fn get() {
+    let headers = CustomHeaders::new(&manifest(&[
+      ("*.html", "content-security-policy", "default-src 'self'"),
+      ("/assets/**", "cache-control", "no-store"),
+      ("**", "x-frame-options", "deny"),
+    ]))
+    .unwrap();
+
+    assert_eq!(
+      headers.get("index.html"),
+      [
+        ("x-frame-options".into(), "deny".into()),
+        (
+          "content-security-policy".into(),
+          "default-src 'self'".into()
+        ),
+      ],
+    );
+
+    assert_eq!(
+      headers.get("/assets/index.js"),
+      [
+        ("x-frame-options".into(), "deny".into()),
+        ("cache-control".into(), "no-store".into()),
+      ],
+    );
+  }
+
+  #[test]
+  fn invalid_glob() {
+    assert_matches!(
+      CustomHeaders::new(&manifest(&[("[a", "x-frame-options", "deny")])).err(),
+      Some(Error::InvalidHeaderGlob { glob, .. }) if glob == "[a",
+    );
+  }
+}
@@ -0,0 +1,182 @@
+use {
+  super::*,
+  brotli::enc::BrotliEncoderParams,
+  flate2::{write::GzEncoder, Compression},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+  Brotli,
+  Gzip,
+}
+
+impl Encoding {
+  const ALL: [Self; 2] = [Self::Brotli, Self::Gzip];
+
+  pub fn compressible(content_type: &Mime) -> bool {
+    content_type.type_() == mime::TEXT
+      || matches!(
+        content_type.essence_str(),
+        "application/javascript" | "application/json"
+      )
+  }
+
+  // fast levels, for responses compressed on the fly
+  pub fn compress(self, content: &[u8]) -> Vec<u8> {
+    self.encode(content, 5, Compression::default())
+  }
+
+  // maximum levels, for content compressed once and cached
+  pub fn compress_best(self, content: &[u8]) -> Vec<u8> {
+    self.encode(content, 11, Compression::best())
+  }
+
+  fn encode(self, content: &[u8], quality: i32, level: Compression) -> Vec<u8> {
+    match self {
+      Self::Brotli => {
+        let mut output = Vec::new();
+        brotli::BrotliCompress(
+          &mut Cursor::new(content),
+          &mut output,
+          &BrotliEncoderParams {
+            quality,
+            ..Default::default()
+          },
+        )
+        .unwrap();
+        output
+      }
+      Self::Gzip => {
+        let mut encoder = GzEncoder::new(Vec::new(), level);
+        encoder.write_all(content).unwrap();
+        encoder.finish().unwrap()
+      }
+    }
+  }
+
+  pub fn name(self) -> &'static str {
+    match self {
+      Self::Brotli => "br",
+      Self::Gzip => "gzip",
+    }
+  }
+
+  pub fn negotiate(accept_encoding: &str) -> Option<Self> {
+    let mut wildcard = None;
+    let mut qualities = [None; Self::ALL.len()];
+
+    for coding in accept_encoding.split(',') {
+      let mut parameters = coding.split(';');
+
+      let name = parameters.next().unwrap_or_default().trim();
+
+      let quality = parameters
+        .filter_map(|parameter| parameter.trim().strip_prefix("q="))
+        .find_map(|quality| quality.trim().parse::<f32>().ok())
+        .unwrap_or(1.0);
+
+      if name == "*" {
+        wildcard = Some(quality);
+      } else if let Some(i) = Self::ALL
+        .iter()
+        .position(|encoding| name.eq_ignore_ascii_case(encoding.name()))
+      {
+        qualities[i] = Some(quality);
+      }
+    }
+
+    let mut best = None;
+
+    for (encoding, quality) in Self::ALL.into_iter().zip(qualities) {
+      let quality = quality.or(wildcard).unwrap_or_default();
+
+      if quality > 0.0 && best.is_none_or(|(_, best)| quality > best) {
+        best = Some((encoding, quality));
+      }
+    }
+
+    best.map(|(encoding, _)| encoding)
+  }
+}
+
+#[derive(Debug)]
+pub struct Compressed {
+  brotli: Bytes,
+  gzip: Bytes,
+}
+
+impl Compressed {
+  pub fn new(content: &[u8]) -> Self {
+    Self {
+      brotli: Encoding::Brotli.compress_best(content).into(),
+      gzip: Encoding::Gzip.compress_best(content).into(),
+    }
+  }
+
+  pub fn get(&self, encoding: Encoding) -> Bytes {
+    match encoding {
+      Encoding::Brotli => self.brotli.clone(),
+      Encoding::Gzip => self.gzip.clone(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn compressible() {
+    assert!(Encoding::compressible(&mime::TEXT_HTML));
+    assert!(Encoding::compressible(&mime::TEXT_JAVASCRIPT));
+    assert!(Encoding::compressible(&mime::APPLICATION_JSON));
+    assert!(Encoding::compressible(&mime::APPLICATION_JAVASCRIPT));
+    assert!(!Encoding::compressible(&mime::IMAGE_JPEG));
+    assert!(!Encoding::compressible(&mime::APPLICATION_OCTET_STREAM));
+  }
+
+  #[test]
+  fn round_trip() {
+    let content = "foo bar baz ".repeat(100);
+
+    for encoding in Encoding::ALL {
+      for compressed in [
+        encoding.compress(content.as_bytes()),
+        encoding.compress_best(content.as_bytes()),
+      ] {
+        assert!(compressed.len() < content.len());
+
+        let mut decompressed = Vec::new();
+
+        match encoding {
+          Encoding::Brotli => {
+            brotli::BrotliDecompress(&mut Cursor::new(compressed), &mut decompressed).unwrap();
+          }
+          Encoding::Gzip => {
+            flate2::read::GzDecoder::new(compressed.as_slice())
+              .read_to_end(&mut decompressed)
+              .unwrap();
+          }
+        }
+
+        assert_eq!(decompressed, content.as_bytes());
+      }
+    }
+  }
+
+  #[test]
+  fn negotiate() {
+    assert_eq!(Encoding::negotiate(""), None);
+    assert_eq!(Encoding::negotiate("identity"), None);
+    assert_eq!(Encoding::negotiate("gzip"), Some(Encoding::Gzip));
+    assert_eq!(
+      Encoding::negotiate("gzip, deflate, br"),
+      Some(Encoding::Brotli)
+    );
+    assert_eq!(Encoding::negotiate("br;q=0.5, gzip"), Some(Encoding::Gzip));
+    assert_eq!(Encoding::negotiate("br;q=0, gzip;q=0"), None);
+    assert_eq!(Encoding::negotiate("*"), Some(Encoding::Brotli));
+    assert_eq!(Encoding::negotiate("br;q=0, *;q=0.1"), Some(Encoding::Gzip));
+    assert_eq!(Encoding::negotiate("GZIP"), Some(Encoding::Gzip));
+  }
+}
@@ -0,0 +1,101 @@
+use {super::*, regex::bytes::Regex, std::sync::LazyLock};
+
+// tags after which the script is inserted, in order of preference
+static TAGS: LazyLock<[Regex; 3]> = LazyLock::new(|| {
+  [
+    r"(?i)<head(\s[^>]*)?>",
+    r"(?i)<html(\s[^>]*)?>",
+    r"(?i)^\s*<!doctype[^>]*>",
+  ]
+  .map(|re| Regex::new(re).unwrap())
+});
+
+pub fn manifest(html: &[u8], manifest: &Manifest) -> Vec<u8> {
+  let json = String::from_utf8(manifest.json())
+    .unwrap()
+    .replace('<', "\\u003c");
+
+  let script = format!("<script>window.__MANIFEST__={json}</script>");
+
+  let offset = TAGS
+    .iter()
+    .find_map(|re| re.find(html))
+    .map(|tag| tag.end())
+    .unwrap_or_default();
+
+  let mut output = Vec::with_capacity(html.len() + script.len());
+  output.extend_from_slice(&html[..offset]);
+  output.extend_from_slice(script.as_bytes());
+  output.extend_from_slice(&html[offset..]);
+  output
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn placement() {
+    let manifest = Manifest::Comic {
+      content_types: Vec::new(),
+      dimensions: Vec::new(),
+      pages: Vec::new(),
+      provenance: Provenance::default(),
+      thumbnails: Vec::new(),
+    };
+
+    let script = format!(
+      "<script>window.__MANIFEST__={}</script>",
+      String::from_utf8(manifest.json()).unwrap(),
+    );
+
+    #[track_caller]
+    fn case(manifest: &Manifest, html: &str, expected: &str) {
+      assert_eq!(
+        String::from_utf8(super::manifest(html.as_bytes(), manifest)).unwrap(),
+        expected,
+      );
+    }
+
+    case(
+      &manifest,
+      "<html><head><title>foo</title></head></html>",
+      &format!("<html><head>{script}<title>foo</title></head></html>"),
+    );
+    case(
+      &manifest,
+      "<HTML><HEAD lang=en><header></header></HEAD></HTML>",
+      &format!("<HTML><HEAD lang=en>{script}<header></header></HEAD></HTML>"),
+    );
+    case(
+      &manifest,
+      "<!doctype html><html lang=en><header></header></html>",
+      &format!("<!doctype html><html lang=en>{script}<header></header></html>"),
+    );
+    case(
+      &manifest,
+      "<!DOCTYPE html><p>foo",
+      &format!("<!DOCTYPE html>{script}<p>foo"),
+    );
+    case(&manifest, "<p>foo", &format!("{script}<p>foo"));
+  }
+
+  #[test]
+  fn script_is_escaped() {
+    let manifest = Manifest::Comic {
+      content_types: Vec::new(),
+      dimensions: Vec::new(),
+      pages: Vec::new(),
+      provenance: Provenance {
+        creator: Some("</script><script>alert(1)</script>".into()),
+        ..Default::default()
+      },
+      thumbnails: Vec::new(),
+    };
+
+    let html = String::from_utf8(super::manifest(b"<head>", &manifest)).unwrap();
+
+    assert_eq!(html.matches("</script>").count(), 1);
+    assert!(html.contains(r"\u003c/script>\u003cscript>alert(1)\u003c/script>"));
+  }
+}
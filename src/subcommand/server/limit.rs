@@ -0,0 +1,23 @@
+use {
+  super::*,
+  axum::{
+    extract::{Request, State},
+    middleware::Next,
+  },
+};
+
+pub async fn limit(
+  State(connections): State<Arc<Semaphore>>,
+  request: Request,
+  next: Next,
+) -> Response {
+  let Ok(_permit) = connections.try_acquire() else {
+    return (
+      StatusCode::SERVICE_UNAVAILABLE,
+      "server is at maximum connections",
+    )
+      .into_response();
+  };
+
+  next.run(request).await
+}
@@ -0,0 +1,131 @@
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ManifestFormat {
+  Cbor,
+  Json,
+  Yaml,
+}
+
+impl ManifestFormat {
+  const ALL: [Self; 3] = [Self::Json, Self::Yaml, Self::Cbor];
+
+  pub fn content_type(self) -> Mime {
+    match self {
+      Self::Cbor => "application/cbor".parse().unwrap(),
+      Self::Json => mime::APPLICATION_JSON,
+      Self::Yaml => "application/yaml".parse().unwrap(),
+    }
+  }
+
+  pub fn negotiate(accept: &str) -> Self {
+    let mut best = None;
+
+    for media_range in accept.split(',') {
+      let mut parameters = media_range.split(';');
+
+      let name = parameters.next().unwrap_or_default().trim();
+
+      let quality = parameters
+        .filter_map(|parameter| parameter.trim().strip_prefix("q="))
+        .find_map(|quality| quality.trim().parse::<f32>().ok())
+        .unwrap_or(1.0);
+
+      if quality <= 0.0 {
+        continue;
+      }
+
+      let Some(format) = Self::ALL.into_iter().find(|format| {
+        let content_type = format.content_type();
+        name == "*/*"
+          || name.eq_ignore_ascii_case(content_type.essence_str())
+          || name
+            .strip_suffix("/*")
+            .is_some_and(|ty| ty.eq_ignore_ascii_case(content_type.type_().as_str()))
+      }) else {
+        continue;
+      };
+
+      if best.is_none_or(|(_, best)| quality > best) {
+        best = Some((format, quality));
+      }
+    }
+
+    best.map(|(format, _)| format).unwrap_or(Self::Json)
+  }
+
+  pub fn serialize(self, manifest: &Manifest) -> Vec<u8> {
+    match self {
+      Self::Cbor => manifest.cbor(),
+      Self::Json => manifest.json(),
+      Self::Yaml => manifest.yaml(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn negotiate() {
+    assert_eq!(ManifestFormat::negotiate(""), ManifestFormat::Json);
+    assert_eq!(ManifestFormat::negotiate("*/*"), ManifestFormat::Json);
+    assert_eq!(ManifestFormat::negotiate("text/html"), ManifestFormat::Json);
+    assert_eq!(
+      ManifestFormat::negotiate("application/yaml"),
+      ManifestFormat::Yaml
+    );
+    assert_eq!(
+      ManifestFormat::negotiate("application/cbor"),
+      ManifestFormat::Cbor
+    );
+    assert_eq!(
+      ManifestFormat::negotiate("APPLICATION/YAML"),
+      ManifestFormat::Yaml
+    );
+    assert_eq!(
+      ManifestFormat::negotiate("application/json;q=0.5, application/cbor"),
+      ManifestFormat::Cbor
+    );
+    assert_eq!(
+      ManifestFormat::negotiate("application/yaml, application/cbor"),
+      ManifestFormat::Yaml
+    );
+    assert_eq!(
+      ManifestFormat::negotiate("application/yaml;q=0, */*;q=0.1"),
+      ManifestFormat::Json
+    );
+    assert_eq!(
+      ManifestFormat::negotiate("application/*"),
+      ManifestFormat::Json
+    );
+  }
+
+  #[test]
+  fn serialize() {
+    let manifest = Manifest::Comic {
+      content_types: vec!["image/jpeg".into()],
+      dimensions: Vec::new(),
+      pages: vec![blake3::hash(b"foo")],
+      provenance: Provenance::default(),
+      thumbnails: Vec::new(),
+    };
+
+    assert_eq!(
+      serde_json::from_slice::<Manifest>(&ManifestFormat::Json.serialize(&manifest)).unwrap(),
+      manifest,
+    );
+
+    assert_eq!(
+      serde_yaml::from_slice::<Manifest>(&ManifestFormat::Yaml.serialize(&manifest)).unwrap(),
+      manifest,
+    );
+
+    assert_eq!(
+      ciborium::from_reader::<Manifest, _>(ManifestFormat::Cbor.serialize(&manifest).as_slice())
+        .unwrap(),
+      manifest,
+    );
+  }
+}
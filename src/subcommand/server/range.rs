@@ -0,0 +1,131 @@
+use super::*;
+
+// requests with more ranges than this are served in full, so that a single
+// request can't build a multipart body many times the size of the file
+const MAX_RANGES: usize = 16;
+
+pub fn parse(header: &str, len: u64) -> Option<Vec<RangeInclusive<u64>>> {
+  let (unit, specs) = header.split_once('=')?;
+
+  if !unit.trim().eq_ignore_ascii_case("bytes") {
+    return None;
+  }
+
+  if specs.split(',').count() > MAX_RANGES {
+    return None;
+  }
+
+  let mut ranges = Vec::new();
+
+  for spec in specs.split(',') {
+    let (start, end) = spec.trim().split_once('-')?;
+
+    if start.is_empty() {
+      let suffix = number(end)?;
+
+      if suffix == 0 || len == 0 {
+        continue;
+      }
+
+      ranges.push(len.saturating_sub(suffix)..=len - 1);
+    } else {
+      let start = number(start)?;
+
+      let end = if end.is_empty() {
+        u64::MAX
+      } else {
+        number(end)?
+      };
+
+      if end < start {
+        return None;
+      }
+
+      if start >= len {
+        continue;
+      }
+
+      ranges.push(start..=end.min(len - 1));
+    }
+  }
+
+  ranges.sort_by_key(|range| *range.start());
+
+  let mut merged = Vec::<RangeInclusive<u64>>::new();
+
+  for range in ranges {
+    match merged.last_mut() {
+      Some(last) if *range.start() <= last.end().saturating_add(1) => {
+        *last = *last.start()..=*last.end().max(range.end());
+      }
+      _ => merged.push(range),
+    }
+  }
+
+  Some(merged)
+}
+
+fn number(s: &str) -> Option<u64> {
+  if s.is_empty() || !s.bytes().all(|byte| byte.is_ascii_digit()) {
+    return None;
+  }
+
+  s.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn single() {
+    assert_eq!(parse("bytes=0-4", 10), Some(vec![0..=4]));
+    assert_eq!(parse("bytes=5-", 10), Some(vec![5..=9]));
+    assert_eq!(parse("bytes=-3", 10), Some(vec![7..=9]));
+    assert_eq!(parse("bytes=-30", 10), Some(vec![0..=9]));
+    assert_eq!(parse("bytes=8-100", 10), Some(vec![8..=9]));
+    assert_eq!(parse("BYTES=0-0", 10), Some(vec![0..=0]));
+  }
+
+  #[test]
+  fn multiple() {
+    assert_eq!(
+      parse("bytes=0-1, 4-5,-1", 10),
+      Some(vec![0..=1, 4..=5, 9..=9])
+    );
+  }
+
+  #[test]
+  fn merged() {
+    assert_eq!(parse("bytes=0-,0-,0-", 10), Some(vec![0..=9]));
+    assert_eq!(parse("bytes=4-5,0-2,3-3", 10), Some(vec![0..=5]));
+    assert_eq!(parse("bytes=6-8,0-1,-2", 10), Some(vec![0..=1, 6..=9]));
+  }
+
+  #[test]
+  fn too_many() {
+    let header = format!("bytes={}", vec!["0-"; MAX_RANGES].join(","));
+    assert_eq!(parse(&header, 10), Some(vec![0..=9]));
+
+    let header = format!("bytes={}", vec!["0-"; MAX_RANGES + 1].join(","));
+    assert_eq!(parse(&header, 10), None);
+  }
+
+  #[test]
+  fn unsatisfiable() {
+    assert_eq!(parse("bytes=10-", 10), Some(Vec::new()));
+    assert_eq!(parse("bytes=-0", 10), Some(Vec::new()));
+    assert_eq!(parse("bytes=0-", 0), Some(Vec::new()));
+    assert_eq!(parse("bytes=20-30,40-", 10), Some(Vec::new()));
+  }
+
+  #[test]
+  fn invalid() {
+    assert_eq!(parse("items=0-1", 10), None);
+    assert_eq!(parse("bytes=5-4", 10), None);
+    assert_eq!(parse("bytes=a-b", 10), None);
+    assert_eq!(parse("bytes=+1-2", 10), None);
+    assert_eq!(parse("bytes=1", 10), None);
+    assert_eq!(parse("bytes=-", 10), None);
+  }
+}
@@ -0,0 +1,197 @@
+use {super::*, rayon::prelude::*};
+
+#[derive(Parser)]
+pub struct Verify {
+  #[arg(
+    long,
+    help = "Verify up to <JOBS> packages in parallel. Defaults to the number of CPUs."
+  )]
+  pub jobs: Option<usize>,
+  #[arg(
+    help = "Verify integrity of <PACKAGE>, or of every `*.package` file under directory <PACKAGE>."
+  )]
+  pub package: Utf8PathBuf,
+  #[arg(long, help = "Don't print anything on success.")]
+  pub quiet: bool,
+}
+
+impl Verify {
+  pub fn run(self) -> Result {
+    if !self.package.is_dir() {
+      Package::verify(&self.package).context(error::PackageVerify {
+        path: &self.package,
+      })?;
+
+      if !self.quiet {
+        println!("{}: ok", self.package);
+      }
+
+      return Ok(());
+    }
+
+    let packages = self.packages()?;
+
+    let pool = rayon::ThreadPoolBuilder::new()
+      .num_threads(self.jobs.unwrap_or_default())
+      .build()
+      .context(error::ThreadPool)?;
+
+    let results = pool.install(|| {
+      packages
+        .par_iter()
+        .map(|path| Package::verify(path).map(|_manifest| ()))
+        .collect::<Vec<std::result::Result<(), crate::package::Error>>>()
+    });
+
+    let mut failed = 0u64;
+
+    for (path, result) in packages.iter().zip(results) {
+      match result {
+        Ok(()) => {
+          if !self.quiet {
+            println!("{path}: ok");
+          }
+        }
+        Err(source) => {
+          failed += 1;
+          error::PackageVerify { path }.into_error(source).report();
+        }
+      }
+    }
+
+    let total = packages.len().into_u64();
+
+    if !self.quiet || failed > 0 {
+      println!("{} passed, {failed} failed", total - failed);
+    }
+
+    ensure!(failed == 0, error::VerifyFailed { failed, total });
+
+    Ok(())
+  }
+
+  fn packages(&self) -> Result<Vec<Utf8PathBuf>> {
+    let mut packages = Vec::new();
+
+    for entry in WalkDir::new(&self.package).sort_by_file_name() {
+      let entry = entry.context(error::WalkDir {
+        root: &self.package,
+      })?;
+
+      if !entry.file_type().is_file() {
+        continue;
+      }
+
+      let path =
+        Utf8Path::from_path(entry.path()).context(error::PathUnicode { path: entry.path() })?;
+
+      if path.extension() == Some("package") {
+        packages.push(path.into());
+      }
+    }
+
+    Ok(packages)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn verify() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("content.package");
+
+    subcommand::package::Package {
+      root: vec!["content/comic".into()],
+      output: package.clone(),
+      ..Default::default()
+    }
+    .run()
+    .unwrap();
+
+    Verify {
+      jobs: None,
+      package,
+      quiet: true,
+    }
+    .run()
+    .unwrap();
+  }
+
+  #[test]
+  fn verify_error() {
+    let tempdir = tempdir();
+
+    let package = tempdir.path_utf8().join("content.package");
+
+    fs::write(&package, "this-is-not-a-package").unwrap();
+
+    assert_matches!(
+      Verify {
+        jobs: None,
+        package: package.clone(),
+        quiet: true,
+      }
+      .run()
+      .unwrap_err(),
+      Error::PackageVerify {
+        path,
+        source: crate::package::Error::MagicBytes { .. },
+      }
+      if path == package,
+    );
+  }
+
+  #[test]
+  fn verify_directory() {
+    let tempdir = tempdir();
+
+    let root = tempdir.path_utf8();
+
+    fs::create_dir_all(root.join("a/b")).unwrap();
+
+    for path in [
+      "content.package",
+      "a/content.package",
+      "a/b/content.package",
+    ] {
+      subcommand::package::Package {
+        root: vec!["content/comic".into()],
+        output: root.join(path),
+        ..Default::default()
+      }
+      .run()
+      .unwrap();
+    }
+
+    fs::write(root.join("a/notes.txt"), "not a package").unwrap();
+
+    Verify {
+      jobs: Some(2),
+      package: root.into(),
+      quiet: true,
+    }
+    .run()
+    .unwrap();
+
+    fs::write(root.join("a/b/corrupt.package"), "this-is-not-a-package").unwrap();
+
+    assert_matches!(
+      Verify {
+        jobs: Some(2),
+        package: root.into(),
+        quiet: true,
+      }
+      .run()
+      .unwrap_err(),
+      Error::VerifyFailed {
+        failed: 1,
+        total: 4,
+        ..
+      },
+    );
+  }
+}
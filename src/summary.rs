@@ -0,0 +1,17 @@
+use super::*;
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Summary {
+  #[serde(rename = "type")]
+  pub ty: Type,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub chapters: Option<u64>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub duration_ms: Option<u64>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub images: Option<u64>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub pages: Option<u64>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub tracks: Option<u64>,
+}
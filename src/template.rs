@@ -1,27 +1,136 @@
 use super::*;
 
 pub enum Template {
-  App { handles: Type },
-  Comic { pages: Vec<Utf8PathBuf> },
+  App {
+    handles: BTreeSet<Type>,
+    headers: BTreeMap<String, BTreeMap<String, String>>,
+    mime_overrides: BTreeMap<String, String>,
+    redirects: BTreeMap<String, Redirect>,
+  },
+  Audio {
+    durations_ms: Vec<Option<u64>>,
+    titles: Vec<String>,
+    tracks: Vec<Utf8PathBuf>,
+  },
+  Book {
+    chapters: Vec<Utf8PathBuf>,
+    toc: Vec<(String, usize)>,
+  },
+  Comic {
+    dimensions: Vec<(u32, u32)>,
+    pages: Vec<Utf8PathBuf>,
+  },
+  Gallery {
+    captions: BTreeMap<String, String>,
+    images: Vec<Utf8PathBuf>,
+  },
+  Video {
+    captions: Option<Utf8PathBuf>,
+    video: Utf8PathBuf,
+  },
 }
 
 impl Template {
+  pub fn paths(&self) -> Vec<&Utf8Path> {
+    match self {
+      Self::App { .. } => Vec::new(),
+      Self::Audio { tracks, .. } => tracks.iter().map(AsRef::as_ref).collect(),
+      Self::Book { chapters, .. } => chapters.iter().map(AsRef::as_ref).collect(),
+      Self::Comic { pages, .. } => pages.iter().map(AsRef::as_ref).collect(),
+      Self::Gallery { images, .. } => images.iter().map(AsRef::as_ref).collect(),
+      Self::Video { captions, video } => [video]
+        .into_iter()
+        .chain(captions)
+        .map(AsRef::as_ref)
+        .collect(),
+    }
+  }
+
+  pub fn sidecars(&self) -> &'static [&'static str] {
+    match self {
+      Self::Book { .. } => &[TocEntry::PATH],
+      Self::Gallery { .. } => &[Metadata::CAPTIONS_PATH],
+      _ => &[],
+    }
+  }
+
   pub fn manifest(self, hashes: &HashMap<Utf8PathBuf, (Hash, u64)>) -> Manifest {
     match self {
-      Self::App { handles } => {
+      Self::App {
+        handles,
+        headers,
+        mime_overrides,
+        redirects,
+      } => {
         let mut paths = BTreeMap::new();
 
         for (path, (hash, _len)) in hashes {
-          paths.insert(path.to_string(), *hash);
+          paths.insert(path.to_slash(), *hash);
         }
 
-        Manifest::App { handles, paths }
+        Manifest::App {
+          handles,
+          headers,
+          mime_overrides,
+          paths,
+          provenance: Provenance::default(),
+          redirects,
+        }
       }
-      Self::Comic { pages } => Manifest::Comic {
+      Self::Audio {
+        durations_ms,
+        titles,
+        tracks,
+      } => Manifest::Audio {
+        durations_ms,
+        titles,
+        tracks: tracks
+          .into_iter()
+          .map(|path| hashes.get(&path).unwrap().0)
+          .collect(),
+        provenance: Provenance::default(),
+      },
+      Self::Book { chapters, toc } => Manifest::Book {
+        chapters: chapters
+          .into_iter()
+          .map(|path| hashes.get(&path).unwrap().0)
+          .collect(),
+        toc,
+        provenance: Provenance::default(),
+      },
+      Self::Comic { dimensions, pages } => Manifest::Comic {
+        content_types: pages
+          .iter()
+          .map(|path| {
+            mime_guess::from_path(path)
+              .first_or(mime::IMAGE_JPEG)
+              .to_string()
+          })
+          .collect(),
+        dimensions,
         pages: pages
           .into_iter()
           .map(|path| hashes.get(&path).unwrap().0)
           .collect(),
+        provenance: Provenance::default(),
+        thumbnails: Vec::new(),
+      },
+      Self::Gallery { captions, images } => Manifest::Gallery {
+        captions,
+        images: images
+          .into_iter()
+          .map(|path| {
+            let hash = hashes.get(&path).unwrap().0;
+            (path.to_slash(), hash)
+          })
+          .collect(),
+        provenance: Provenance::default(),
+        thumbnails: Vec::new(),
+      },
+      Self::Video { captions, video } => Manifest::Video {
+        captions: captions.map(|path| hashes.get(&path).unwrap().0),
+        video: hashes.get(&video).unwrap().0,
+        provenance: Provenance::default(),
       },
     }
   }
@@ -1,9 +1,6 @@
 use super::*;
 
-pub use {
-  std::{fs, sync::Mutex},
-  tempfile::TempDir,
-};
+pub use tempfile::TempDir;
 
 pub fn tempdir() -> TempDir {
   tempfile::tempdir().unwrap()
@@ -31,3 +28,124 @@ macro_rules! assert_matches {
     }
   }
 }
+
+// a white one pixel image with `payload` in a comment segment
+pub fn jpeg(payload: &[u8]) -> Vec<u8> {
+  let mut encoded = Vec::new();
+
+  image::codecs::jpeg::JpegEncoder::new(&mut encoded)
+    .encode(&[255], 1, 1, image::ExtendedColorType::L8)
+    .unwrap();
+
+  let mut jpeg = encoded[..2].to_vec();
+  jpeg.extend_from_slice(b"\xFF\xFE");
+  jpeg.extend_from_slice(&u16::try_from(payload.len() + 2).unwrap().to_be_bytes());
+  jpeg.extend_from_slice(payload);
+  jpeg.extend_from_slice(&encoded[2..]);
+  jpeg
+}
+
+// a black eight-bit grayscale image
+pub fn png(width: u32, height: u32) -> Vec<u8> {
+  let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+  encoder
+    .write_all(&vec![
+      0;
+      (usize::try_from(width).unwrap() + 1)
+        * usize::try_from(height).unwrap()
+    ])
+    .unwrap();
+  let data = encoder.finish().unwrap();
+
+  let mut header = width.to_be_bytes().to_vec();
+  header.extend_from_slice(&height.to_be_bytes());
+  header.extend_from_slice(&[8, 0, 0, 0, 0]);
+
+  let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+  for (ty, content) in [(b"IHDR", header), (b"IDAT", data), (b"IEND", Vec::new())] {
+    png.extend_from_slice(&u32::try_from(content.len()).unwrap().to_be_bytes());
+    png.extend_from_slice(ty);
+    png.extend_from_slice(&content);
+    let mut crc = flate2::Crc::new();
+    crc.update(ty);
+    crc.update(&content);
+    png.extend_from_slice(&crc.sum().to_be_bytes());
+  }
+  png
+}
+
+// a black lossless image
+pub fn webp(width: u32, height: u32) -> Vec<u8> {
+  let mut webp = Vec::new();
+
+  image::codecs::webp::WebPEncoder::new_lossless(&mut webp)
+    .encode(
+      &vec![0; usize::try_from(width * height).unwrap()],
+      width,
+      height,
+      image::ExtendedColorType::L8,
+    )
+    .unwrap();
+
+  webp
+}
+
+// a black image
+pub fn gif(width: u32, height: u32) -> Vec<u8> {
+  let mut gif = Vec::new();
+
+  image::codecs::gif::GifEncoder::new(&mut gif)
+    .encode(
+      &vec![0; usize::try_from(width * height * 4).unwrap()],
+      width,
+      height,
+      image::ExtendedColorType::Rgba8,
+    )
+    .unwrap();
+
+  gif
+}
+
+// a file type box followed by an image spatial extents property, which is
+// enough to sniff but not to decode
+pub fn avif(width: u32, height: u32) -> Vec<u8> {
+  let mut avif = b"\0\0\0\x14ftypavif\0\0\0\0avif\0\0\0\x14ispe\0\0\0\0".to_vec();
+  avif.extend_from_slice(&width.to_be_bytes());
+  avif.extend_from_slice(&height.to_be_bytes());
+  avif
+}
+
+// silent MPEG-1 layer III frames at 128 kbps and 44.1 kHz, each 1152 samples
+pub fn mp3(frames: usize) -> Vec<u8> {
+  let mut frame = vec![0; 417];
+  frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0x00]);
+  frame.repeat(frames)
+}
+
+// writes names directly into the header, since `tar::Header::set_path`
+// rejects the unsafe paths some tests need
+pub fn tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+  let mut builder = tar::Builder::new(Vec::new());
+  for (name, content) in files {
+    let mut header = tar::Header::new_ustar();
+    header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+    header.set_mode(0o644);
+    header.set_size(content.len().into_u64());
+    header.set_cksum();
+    builder.append(&header, *content).unwrap();
+  }
+  builder.into_inner().unwrap()
+}
+
+pub fn unzip(archive: &[u8]) -> Vec<(Utf8PathBuf, Vec<u8>)> {
+  let mut archive = zip::ZipArchive::new(Cursor::new(archive)).unwrap();
+
+  (0..archive.len())
+    .map(|i| {
+      let mut file = archive.by_index(i).unwrap();
+      let mut content = Vec::new();
+      file.read_to_end(&mut content).unwrap();
+      (file.name().into(), content)
+    })
+    .collect()
+}
@@ -0,0 +1,120 @@
+use {
+  super::*,
+  image::{ImageFormat, ImageReader},
+};
+
+// images with more pixels than this are not decoded
+const MAX_PIXELS: u64 = 1 << 26;
+
+pub(crate) const MAX_SIZE: u32 = 256;
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Thumbnail {
+  Generated(Vec<u8>),
+  Original,
+}
+
+// returns a PNG no larger than `MAX_SIZE` on either side, `Original` if
+// `content` is already small enough, or `None` if it is too large or can't be
+// decoded
+pub(crate) fn thumbnail(content: &[u8]) -> Option<Thumbnail> {
+  let (width, height) = sniff::dimensions(content)?;
+
+  if width.max(height) <= MAX_SIZE {
+    return Some(Thumbnail::Original);
+  }
+
+  if u64::from(width) * u64::from(height) > MAX_PIXELS {
+    return None;
+  }
+
+  let image = ImageReader::new(Cursor::new(content))
+    .with_guessed_format()
+    .ok()?
+    .decode()
+    .ok()?;
+
+  let mut thumbnail = Vec::new();
+
+  image
+    .thumbnail(MAX_SIZE, MAX_SIZE)
+    .write_to(&mut Cursor::new(&mut thumbnail), ImageFormat::Png)
+    .ok()?;
+
+  Some(Thumbnail::Generated(thumbnail))
+}
+
+#[cfg(test)]
+mod tests {
+  use {
+    super::*,
+    image::{DynamicImage, GenericImageView, Rgba, RgbaImage},
+  };
+
+  fn encode(image: RgbaImage, format: ImageFormat) -> Vec<u8> {
+    let mut encoded = Vec::new();
+
+    DynamicImage::from(image)
+      .to_rgb8()
+      .write_to(&mut Cursor::new(&mut encoded), format)
+      .unwrap();
+
+    encoded
+  }
+
+  #[track_caller]
+  fn generated(content: &[u8]) -> DynamicImage {
+    let Some(Thumbnail::Generated(thumbnail)) = thumbnail(content) else {
+      panic!("no thumbnail generated");
+    };
+
+    image::load_from_memory_with_format(&thumbnail, ImageFormat::Png).unwrap()
+  }
+
+  #[test]
+  fn small() {
+    let image = RgbaImage::from_pixel(MAX_SIZE, 1, Rgba([255; 4]));
+    assert_eq!(
+      thumbnail(&encode(image, ImageFormat::Png)),
+      Some(Thumbnail::Original),
+    );
+  }
+
+  #[test]
+  fn unsupported() {
+    assert_eq!(thumbnail(b"foo"), None);
+    assert_eq!(thumbnail(b"RIFF\0\0\0\0WEBPVP8X"), None);
+    assert_eq!(thumbnail(&avif(512, 512)), None);
+    let webp = webp(512, 512);
+    assert_eq!(thumbnail(&webp[..webp.len() / 2]), None);
+  }
+
+  #[test]
+  fn gif() {
+    assert_eq!(
+      generated(&super::gif(MAX_SIZE * 2, 2)).dimensions(),
+      (MAX_SIZE, 1),
+    );
+  }
+
+  #[test]
+  fn png() {
+    let image = RgbaImage::from_pixel(MAX_SIZE * 2, 4, Rgba([10, 20, 30, 255]));
+
+    let thumbnail = generated(&encode(image, ImageFormat::Png)).to_rgba8();
+
+    assert_eq!(thumbnail.dimensions(), (MAX_SIZE, 2));
+    assert!(thumbnail
+      .pixels()
+      .all(|pixel| *pixel == Rgba([10, 20, 30, 255])));
+  }
+
+  #[test]
+  fn jpeg() {
+    let image = RgbaImage::from_pixel(4, MAX_SIZE * 4, Rgba([128, 128, 128, 255]));
+
+    let thumbnail = generated(&encode(image, ImageFormat::Jpeg));
+
+    assert_eq!((thumbnail.width(), thumbnail.height()), (1, MAX_SIZE));
+  }
+}
@@ -0,0 +1,16 @@
+use super::*;
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TocEntry {
+  pub title: String,
+  pub chapter: usize,
+}
+
+impl TocEntry {
+  pub const PATH: &'static str = "toc.yaml";
+
+  pub fn parse(path: &Utf8Path, yaml: &[u8]) -> Result<Vec<Self>> {
+    serde_yaml::from_slice(yaml).context(error::DeserializeYaml { path })
+  }
+}
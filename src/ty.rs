@@ -1,17 +1,75 @@
-use super::*;
+use {super::*, std::str::FromStr};
 
-#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Copy, Clone, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Type {
   App,
+  Audio,
+  Book,
   Comic,
+  Gallery,
+  Video,
 }
 
 impl Display for Type {
   fn fmt(&self, f: &mut Formatter) -> fmt::Result {
     match self {
       Self::App => write!(f, "app"),
+      Self::Audio => write!(f, "audio"),
+      Self::Book => write!(f, "book"),
       Self::Comic => write!(f, "comic"),
+      Self::Gallery => write!(f, "gallery"),
+      Self::Video => write!(f, "video"),
     }
   }
 }
+
+impl FromStr for Type {
+  type Err = ParseTypeError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "app" => Ok(Self::App),
+      "audio" => Ok(Self::Audio),
+      "book" => Ok(Self::Book),
+      "comic" => Ok(Self::Comic),
+      "gallery" => Ok(Self::Gallery),
+      "video" => Ok(Self::Video),
+      _ => ParseTypeSnafu { input: s }.fail(),
+    }
+  }
+}
+
+#[derive(Debug, Snafu)]
+#[snafu(display("unknown package type `{input}`"))]
+pub struct ParseTypeError {
+  input: String,
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn round_trip() {
+    for ty in [
+      Type::App,
+      Type::Audio,
+      Type::Book,
+      Type::Comic,
+      Type::Gallery,
+      Type::Video,
+    ] {
+      assert_eq!(ty.to_string().parse::<Type>().unwrap(), ty);
+      assert_eq!(
+        serde_yaml::to_string(&ty).unwrap().trim_end(),
+        ty.to_string()
+      );
+    }
+
+    assert_eq!(
+      "Comic".parse::<Type>().unwrap_err().to_string(),
+      "unknown package type `Comic`",
+    );
+  }
+}
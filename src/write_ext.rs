@@ -1,9 +1,17 @@
 use super::*;
 
+/// Integers are written little-endian regardless of target, so packages built
+/// on one architecture load on any other.
 pub trait WriteExt {
   fn write_hash(&mut self, value: Hash) -> io::Result<()>;
 
+  /// Write `value` as a little-endian `u16`.
+  fn write_u16(&mut self, value: u16) -> io::Result<()>;
+
+  /// Write `value` as a little-endian `u64`.
   fn write_u64(&mut self, value: u64) -> io::Result<()>;
+
+  fn write_u8(&mut self, value: u8) -> io::Result<()>;
 }
 
 impl<T: Write> WriteExt for T {
@@ -11,7 +19,48 @@ impl<T: Write> WriteExt for T {
     self.write_all(value.as_bytes())
   }
 
+  fn write_u16(&mut self, value: u16) -> io::Result<()> {
+    self.write_all(&value.to_le_bytes())
+  }
+
   fn write_u64(&mut self, value: u64) -> io::Result<()> {
     self.write_all(&value.to_le_bytes())
   }
+
+  fn write_u8(&mut self, value: u8) -> io::Result<()> {
+    self.write_all(&[value])
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn little_endian() {
+    let mut buffer = Vec::new();
+    buffer.write_u16(0x0102).unwrap();
+    buffer.write_u64(0x0708_090A_0B0C_0D0E).unwrap();
+
+    assert_eq!(buffer, [2, 1, 0x0E, 0x0D, 0x0C, 0x0B, 0x0A, 9, 8, 7],);
+
+    let mut reader = buffer.as_slice();
+    assert_eq!(reader.read_u16().unwrap(), 0x0102);
+    assert_eq!(reader.read_u64().unwrap(), 0x0708_090A_0B0C_0D0E);
+  }
+
+  #[test]
+  fn round_trip() {
+    for value in [0, 1, 0xFF, 0x1234_5678_9ABC_DEF0, u64::MAX] {
+      let mut buffer = Vec::new();
+      buffer.write_u64(value).unwrap();
+      assert_eq!(buffer.as_slice().read_u64().unwrap(), value);
+    }
+
+    for value in [0, 1, 0xFF, 0x1234, u16::MAX] {
+      let mut buffer = Vec::new();
+      buffer.write_u16(value).unwrap();
+      assert_eq!(buffer.as_slice().read_u16().unwrap(), value);
+    }
+  }
 }